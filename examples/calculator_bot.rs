@@ -3,31 +3,36 @@
 
 use std::io;
 
+use rust_beginner_toolkit::config::{Config, Personality};
+use rust_beginner_toolkit::input;
+
 fn main() {
+    // Rusty's personality comes from the same config.toml as the main calculator
+    let (config, warnings) = Config::load();
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
     // Bot introduction with personality
     println!("🦀✨ RUSTY THE CALCULATOR BOT ✨🦀");
     println!("=====================================");
     println!("🤖 Rusty: Hey there, friend! I'm Rusty, your friendly Rust-powered calculator!");
     println!("🤖 Rusty: I LOVE solving math problems! Give me expressions like '5 + 3'");
     println!("🤖 Rusty: I can handle +, -, *, and / operations!");
+    println!("🤖 Rusty: Long problem? End the line with an operator or '\\' and keep typing!");
     println!("🤖 Rusty: Type 'quit' when you're done calculating with me!");
     println!();
 
     let mut calculation_count = 0;
 
     // Main bot loop
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
     loop {
-        // Friendly prompt
-        print!("🤖 Rusty: What's your math problem? > ");
-        
-        // Flush stdout to ensure prompt appears
-        use std::io::Write;
-        io::stdout().flush().unwrap();
-
-        // Read user input
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
+        // Friendly prompt - long problems can continue on the next line
+        let prompt = "🤖 Rusty: What's your math problem? > ";
+        match input::read_expression(&mut stdin.lock(), &mut stdout, prompt, input::CONTINUATION_PROMPT) {
+            Ok(Some(input)) => {
                 let input = input.trim();
                 
                 // Handle quit with personality
@@ -48,11 +53,16 @@ fn main() {
                 match calculate_with_personality(input) {
                     Ok(result) => {
                         calculation_count += 1;
-                        show_success_message(input, result, calculation_count);
+                        show_success_message(input, result, calculation_count, config.personality);
                     }
                     Err(error) => show_error_message(&error),
                 }
             }
+            Ok(None) => {
+                println!();
+                println!("🤖 Rusty: Looks like we're out of problems! We solved {} together. Bye! 👋", calculation_count);
+                break;
+            }
             Err(error) => {
                 println!("🤖 Rusty: Oops! I had trouble reading that: {}", error);
                 break;
@@ -99,7 +109,13 @@ fn calculate_with_personality(expression: &str) -> Result<f64, String> {
 }
 
 /// Show success message with personality based on operation and count
-fn show_success_message(input: &str, result: f64, count: i32) {
+fn show_success_message(input: &str, result: f64, count: i32, personality: Personality) {
+    // A calm Rusty just gives the answer
+    if personality == Personality::Calm {
+        println!("🤖 Rusty: The answer is {}.", result);
+        return;
+    }
+
     let operation = if input.contains('+') {
         "addition"
     } else if input.contains('-') {
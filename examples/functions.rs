@@ -1,6 +1,9 @@
 // examples/functions.rs
 // Learning about functions in Rust - essential for our calculator
 
+// The explicit `return` below is shown on purpose for learners
+#![allow(clippy::needless_return)]
+
 fn main() {
     println!("🦀 Learning Rust Functions!");
     println!("===========================");
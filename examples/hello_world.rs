@@ -1,6 +1,9 @@
 // examples/hello_world.rs
 // A simple Hello World example to demonstrate basic Rust syntax
 
+// vec! is shown on purpose since it is the collection learners meet first
+#![allow(clippy::useless_vec)]
+
 fn main() {
     // Print a greeting to the console
     println!("Hello, World from Rust! 🦀");
@@ -1,6 +1,9 @@
 // examples/variables.rs
 // Learning about variables, data types, and mutability in Rust
 
+// The long-hand forms below are written out on purpose for learners
+#![allow(clippy::assign_op_pattern, clippy::approx_constant)]
+
 fn main() {
     println!("🦀 Learning Rust Variables and Data Types!");
     println!("==========================================");
@@ -0,0 +1,275 @@
+// src/ast.rs
+// The parsed form of an expression: a tree of numbers, names and operations,
+// which can be printed back as text or drawn as a tree
+
+use std::fmt;
+
+use crate::error::Span;
+
+/// Operators with one operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-x`
+    Negate,
+}
+
+/// Operators with two operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl BinaryOp {
+    /// The operator as it is written
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Power => "^",
+        }
+    }
+}
+
+/// What kind of node an expression is
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Number(f64),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function call: name and arguments
+    Call(String, Vec<Expr>),
+    /// A vector like [1, 2, 3]; a vector of vectors is a matrix
+    Vector(Vec<Expr>),
+}
+
+/// An expression node and the part of the input it came from
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+// Two expressions are equal when they have the same shape,
+// wherever they were typed
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// How tightly this node holds together when printed; higher binds tighter
+    /// 1: + -   2: * /   3: unary - (and negative numbers)   4: ^   5: numbers, names, calls, vectors
+    fn precedence(&self) -> u8 {
+        match &self.kind {
+            ExprKind::Binary(BinaryOp::Add | BinaryOp::Subtract, _, _) => 1,
+            ExprKind::Binary(BinaryOp::Multiply | BinaryOp::Divide, _, _) => 2,
+            ExprKind::Unary(..) => 3,
+            ExprKind::Number(value) if value.is_sign_negative() => 3,
+            ExprKind::Binary(BinaryOp::Power, _, _) => 4,
+            ExprKind::Number(_) | ExprKind::Variable(_) | ExprKind::Call(..) | ExprKind::Vector(_) => 5,
+        }
+    }
+
+    /// Draw the expression as a tree, one node per line, e.g. for "2 * (x + 1)":
+    ///
+    /// ```text
+    /// *
+    /// |-- 2
+    /// `-- +
+    ///     |-- x
+    ///     `-- 1
+    /// ```
+    pub fn tree(&self) -> String {
+        let mut text = String::new();
+        self.draw(&mut text, "", "");
+        text
+    }
+
+    /// Add this node to `text`: `first` goes before its own label,
+    /// `rest` before the lines of its children
+    fn draw(&self, text: &mut String, first: &str, rest: &str) {
+        let (label, children): (String, Vec<&Expr>) = match &self.kind {
+            ExprKind::Number(value) => (value.to_string(), Vec::new()),
+            ExprKind::Variable(name) => (name.clone(), Vec::new()),
+            ExprKind::Unary(UnaryOp::Negate, operand) => ("negate".to_string(), vec![operand]),
+            ExprKind::Binary(op, left, right) => (op.symbol().to_string(), vec![left, right]),
+            ExprKind::Call(name, args) => (format!("{}()", name), args.iter().collect()),
+            ExprKind::Vector(items) => ("[]".to_string(), items.iter().collect()),
+        };
+
+        text.push_str(first);
+        text.push_str(&label);
+        text.push('\n');
+
+        for (index, child) in children.iter().enumerate() {
+            if index + 1 == children.len() {
+                child.draw(text, &format!("{}`-- ", rest), &format!("{}    ", rest));
+            } else {
+                child.draw(text, &format!("{}|-- ", rest), &format!("{}|   ", rest));
+            }
+        }
+    }
+}
+
+/// Write `expr`, in brackets if `needs_brackets`
+fn write_operand(f: &mut fmt::Formatter, expr: &Expr, needs_brackets: bool) -> fmt::Result {
+    if needs_brackets {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+// Print the expression with only the brackets it needs, so parsing the
+// text gives the same tree back: "2 + 3 * (4 - 1)", "x^2 - 2", "-(a + b)"
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Number(value) => write!(f, "{}", value),
+            ExprKind::Variable(name) => write!(f, "{}", name),
+            ExprKind::Unary(UnaryOp::Negate, operand) => {
+                write!(f, "-")?;
+                write_operand(f, operand, operand.precedence() < 3)
+            }
+            ExprKind::Binary(BinaryOp::Power, base, exponent) => {
+                // The base must be a single number, name or call; the exponent may be
+                // anything from unary minus up (2^-1, 2^3^2)
+                write_operand(f, base, base.precedence() < 5)?;
+                write!(f, "^")?;
+                write_operand(f, exponent, exponent.precedence() < 3)
+            }
+            ExprKind::Binary(op, left, right) => {
+                // + - * / group from the left, so an equal operator on the right needs brackets
+                let precedence = self.precedence();
+                write_operand(f, left, left.precedence() < precedence)?;
+                write!(f, " {} ", op.symbol())?;
+                write_operand(f, right, right.precedence() <= precedence)
+            }
+            ExprKind::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                write_list(f, args)?;
+                write!(f, ")")
+            }
+            ExprKind::Vector(items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Write expressions separated by commas
+fn write_list(f: &mut fmt::Formatter, exprs: &[Expr]) -> fmt::Result {
+    for (index, expr) in exprs.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", expr)?;
+    }
+    Ok(())
+}
+
+/// One complete line of input
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// A plain expression to calculate
+    Expr(Expr),
+    /// `name = expression`
+    Assign(String, Expr),
+    /// `name(params) = body`
+    Define(String, Vec<String>, Expr),
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Expr(expr) => write!(f, "{}", expr),
+            Statement::Assign(name, expr) => write!(f, "{} = {}", name, expr),
+            Statement::Define(name, params, body) => write!(f, "{}({}) = {}", name, params.join(", "), body),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_expression, parse_statement};
+
+    fn normalize(input: &str) -> String {
+        parse_expression(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_only_needed_brackets_are_kept() {
+        assert_eq!(normalize("2 + 3 * (4 - 1)"), "2 + 3 * (4 - 1)");
+        assert_eq!(normalize("((2)) + (3 * 4)"), "2 + 3 * 4");
+        assert_eq!(normalize("(1 - 2) - 3"), "1 - 2 - 3");
+        assert_eq!(normalize("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(normalize("8 / (4 / 2)"), "8 / (4 / 2)");
+        assert_eq!(normalize("x ^ 2 - 2"), "x^2 - 2");
+        assert_eq!(normalize("2 ^ (3 ^ 2)"), "2^3^2");
+        assert_eq!(normalize("(2 ^ 3) ^ 2"), "(2^3)^2");
+        assert_eq!(normalize("(-2) ^ 2"), "(-2)^2");
+        assert_eq!(normalize("-(2 ^ 2)"), "-2^2");
+        assert_eq!(normalize("2 ^ (-1)"), "2^-1");
+        assert_eq!(normalize("-(a + b) * f(x,(y))"), "-(a + b) * f(x, y)");
+        assert_eq!(normalize("2 * (-3)"), "2 * -3");
+    }
+
+    #[test]
+    fn test_printing_then_parsing_gives_the_same_tree() {
+        for input in ["(a - b) - (c - d)", "-(-x) ^ 2", "((a / b) / c) * (d * e)", "f(1 + 2, g(-x)) ^ (y - 1)", "1.5e3 - .5", "[[1, -2], [x ^ 2, f([])]] * 2"] {
+            let expr = parse_expression(input).unwrap();
+            assert_eq!(parse_expression(&expr.to_string()).unwrap(), expr, "{} printed as {}", input, expr);
+        }
+    }
+
+    #[test]
+    fn test_negative_numbers_print_like_unary_minus() {
+        let expr = Expr::new(
+            ExprKind::Binary(
+                BinaryOp::Power,
+                Box::new(Expr::new(ExprKind::Number(-2.0), Span::new(0, 0))),
+                Box::new(Expr::new(ExprKind::Number(2.0), Span::new(0, 0))),
+            ),
+            Span::new(0, 0),
+        );
+        assert_eq!(expr.to_string(), "(-2)^2");
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(parse_statement("area(w,h)=w*h").unwrap().to_string(), "area(w, h) = w * h");
+        assert_eq!(parse_statement("x=(1+2)").unwrap().to_string(), "x = 1 + 2");
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = parse_expression("2 + 3 * (4 - f(x))").unwrap().tree();
+        let expected = "\
++
+|-- 2
+`-- *
+    |-- 3
+    `-- -
+        |-- 4
+        `-- f()
+            `-- x
+";
+        assert_eq!(tree, expected);
+        assert_eq!(parse_expression("-7").unwrap().tree(), "negate\n`-- 7\n");
+    }
+}
@@ -0,0 +1,186 @@
+// src/batch.rs
+// Batch mode: evaluate a script (or piped input) line by line without prompts
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::output::{self, OutputFormat};
+use crate::session::{Outcome, Session};
+
+/// Settings for a batch run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchOptions {
+    /// Keep evaluating after an error instead of stopping at the first one
+    pub keep_going: bool,
+    /// How results and errors are written
+    pub output: OutputFormat,
+}
+
+/// Remove a `# comment` from a line, leaving only the code before it
+pub fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+/// Run every line of `reader` in `session`, printing one result per line to `out`
+/// (assignments, definitions and settings print nothing in text output,
+/// and `diff(f, x)` prints the derivative unless x has a value)
+/// In text output errors go to `err` as "source:line: error: message";
+/// in JSON output every line, failed or not, becomes one object on `out`
+/// Returns Ok(true) if every line succeeded
+pub fn run_batch<R: BufRead, W: Write, E: Write>(
+    source: &str,
+    reader: R,
+    session: &mut Session,
+    options: BatchOptions,
+    out: &mut W,
+    err: &mut E,
+) -> io::Result<bool> {
+    let mut success = true;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let code = strip_comment(&line).trim();
+
+        // Blank lines and comment-only lines produce no output
+        if code.is_empty() {
+            continue;
+        }
+
+        let outcome = session.execute(code);
+        match (&outcome, options.output) {
+            (_, OutputFormat::Json) => writeln!(out, "{}", output::evaluation_json(code, &outcome, session.precision))?,
+            (Ok(Outcome::Value(result) | Outcome::Estimate(result, _)), OutputFormat::Text) => writeln!(out, "{}", session.format(result))?,
+            (Ok(Outcome::Derivative(_, _, Some(result))), OutputFormat::Text) => writeln!(out, "{}", session.format(result))?,
+            (Ok(Outcome::Derivative(_, slope, None)), OutputFormat::Text) => writeln!(out, "{}", slope)?,
+            (Ok(_), OutputFormat::Text) => {}
+            (Err(error), OutputFormat::Text) => writeln!(err, "{}:{}: error: {}", source, index + 1, error)?,
+        }
+
+        if outcome.is_err() {
+            success = false;
+            if !options.keep_going {
+                break;
+            }
+        }
+    }
+
+    Ok(success)
+}
+
+/// Run a startup script (like init.calc) into `session` before the REPL starts
+/// Results are not printed, errors go to `err` as "file:line: error: message",
+/// and a failing line never stops the rest of the script or the session
+/// A missing file is not an error; returns Ok(true) if every line succeeded
+pub fn run_init_script<E: Write>(path: &Path, session: &mut Session, err: &mut E) -> io::Result<bool> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(error) => return Err(error),
+    };
+
+    let options = BatchOptions { keep_going: true, output: OutputFormat::Text };
+    let history_before = session.history.len();
+    let success = run_batch(&path.display().to_string(), BufReader::new(file), session, options, &mut io::sink(), err)?;
+
+    // The script's own calculations are not part of the user's history
+    session.history.truncate(history_before);
+    Ok(success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str, keep_going: bool) -> (bool, String, String, Session) {
+        run_with(script, BatchOptions { keep_going, output: OutputFormat::Text })
+    }
+
+    fn run_with(script: &str, options: BatchOptions) -> (bool, String, String, Session) {
+        let mut session = Session::new();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let success = run_batch("test.calc", script.as_bytes(), &mut session, options, &mut out, &mut err).unwrap();
+        (success, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap(), session)
+    }
+
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("5 + 3 # eight"), "5 + 3 ");
+        assert_eq!(strip_comment("# only a comment"), "");
+        assert_eq!(strip_comment("5 + 3"), "5 + 3");
+    }
+
+    #[test]
+    fn test_results_one_per_line() {
+        let (success, out, err, session) = run("# totals\n5 + 3\n\n2 * 4 # doubled\n", false);
+        assert!(success);
+        assert_eq!(out, "8\n8\n");
+        assert!(err.is_empty());
+        assert_eq!(session.history.len(), 2);
+    }
+
+    #[test]
+    fn test_lines_share_state() {
+        let (success, out, _, session) = run("mode integer\nwidth = 7\nhalf(n) = n / 2\nhalf(width)\n", false);
+        assert!(success);
+        assert_eq!(out, "3\n");
+        assert_eq!(session.variables["width"], 7.0);
+    }
+
+    #[test]
+    fn test_stops_at_first_error() {
+        let (success, out, err, _) = run("1 + 1\n5 / 0\n2 + 2\n", false);
+        assert!(!success);
+        assert_eq!(out, "2\n");
+        assert_eq!(err, "test.calc:2: error: Cannot divide by zero!\n");
+    }
+
+    #[test]
+    fn test_keep_going_reports_every_error() {
+        let (success, out, err, _) = run("1 + 1\n5 / 0\nabc\n2 + 2\n", true);
+        assert!(!success);
+        assert_eq!(out, "2\n4\n");
+        assert_eq!(err.lines().count(), 2);
+        assert!(err.contains("test.calc:3: error:"));
+    }
+
+    #[test]
+    fn test_json_output_includes_errors() {
+        let options = BatchOptions { keep_going: false, output: OutputFormat::Json };
+        let (success, out, err, _) = run_with("2 * 3\n1 / 0\n", options);
+        assert!(!success);
+        assert!(err.is_empty());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(r#""value":6"#));
+        assert!(lines[1].contains(r#""kind":"division_by_zero""#));
+    }
+
+    #[test]
+    fn test_init_script_reports_errors_and_keeps_going() {
+        let path = std::env::temp_dir().join(format!("rust-calc-init-{}.calc", std::process::id()));
+        std::fs::write(&path, "# team prelude\n1 + 1\n2 / 0\n3 * 3\n").unwrap();
+
+        let mut session = Session::new();
+        let mut err = Vec::new();
+        let success = run_init_script(&path, &mut session, &mut err).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!success);
+        let err = String::from_utf8(err).unwrap();
+        assert_eq!(err, format!("{}:3: error: Cannot divide by zero!\n", path.display()));
+        assert!(session.history.is_empty());
+    }
+
+    #[test]
+    fn test_missing_init_script_is_fine() {
+        let mut session = Session::new();
+        let mut err = Vec::new();
+        let path = Path::new("/definitely/not/here/init.calc");
+        assert!(run_init_script(path, &mut session, &mut err).unwrap());
+        assert!(err.is_empty());
+    }
+}
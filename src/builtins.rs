@@ -0,0 +1,101 @@
+// src/builtins.rs
+// Functions and constants that every session knows without defining them
+
+use std::f64::consts;
+
+/// A built-in function of one number
+pub type Builtin = fn(f64) -> f64;
+
+/// Built-in functions, as (name, function)
+pub const FUNCTIONS: [(&str, Builtin); 15] = [
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("sinh", f64::sinh),
+    ("cosh", f64::cosh),
+    ("tanh", f64::tanh),
+    ("exp", f64::exp),
+    ("ln", f64::ln),
+    ("log", f64::log10),
+    ("sqrt", f64::sqrt),
+    ("abs", f64::abs),
+    ("cbrt", f64::cbrt),
+];
+
+/// Built-in constants, as (name, value)
+pub const CONSTANTS: [(&str, f64); 4] =
+    [("pi", consts::PI), ("e", consts::E), ("tau", consts::TAU), ("inf", f64::INFINITY)];
+
+/// Built-ins that work on a whole expression rather than a number, like diff(x^2, x)
+pub const FORMS: [&str; 2] = ["diff", "integrate"];
+
+/// Built-ins that make or work on polynomials, like poly(1, 0, -2)
+pub const POLYNOMIAL_FUNCTIONS: [&str; 5] = ["poly", "derivative", "degree", "gcd", "rem"];
+
+/// Built-ins that work on vectors and matrices, like det([[1, 2], [3, 4]]);
+/// hadamard multiplies entry by entry, since `*` is the matrix product
+pub const MATRIX_FUNCTIONS: [&str; 9] = ["dot", "cross", "matmul", "hadamard", "transpose", "det", "inverse", "rank", "linsolve"];
+
+/// Built-ins that work on a list of numbers, given as a vector or one by one: mean([1, 2]) or mean(1, 2)
+pub const STATISTICS_FUNCTIONS: [&str; 15] = [
+    "sum", "product", "mean", "median", "mode", "min", "max", "variance", "pvariance", "stddev", "pstddev", "quantile",
+    "cov", "corr", "linreg",
+];
+
+/// Built-ins for whole numbers, like modpow(4, 13, 497); gcd is with the polynomial ones
+/// and works on whole numbers too
+pub const NUMBER_THEORY_FUNCTIONS: [&str; 8] = ["lcm", "modpow", "modinv", "isprime", "factor", "prime", "totient", "binomial"];
+
+/// Counting on a calculator's keys: nCr(n, k) ways to choose k of n things
+/// (the same as binomial) and nPr(n, k) ways to line them up
+pub const COMBINATORICS_FUNCTIONS: [&str; 2] = ["nCr", "nPr"];
+
+/// Chances from probability distributions: the pdf, the cdf and its inverse
+/// for each of binomial, Poisson, normal, uniform and exponential, like normcdf(1.96)
+pub const DISTRIBUTION_FUNCTIONS: [&str; 15] = [
+    "binompdf", "binomcdf", "binominv", "poissonpdf", "poissoncdf", "poissoninv", "normpdf", "normcdf", "norminv",
+    "unifpdf", "unifcdf", "unifinv", "exppdf", "expcdf", "expinv",
+];
+
+/// Built-ins for random numbers; seed(n) makes the numbers after it repeatable
+pub const RANDOM_FUNCTIONS: [&str; 3] = ["rand", "randint", "seed"];
+
+/// Look up a built-in function by name
+pub fn function(name: &str) -> Option<Builtin> {
+    FUNCTIONS.iter().find(|(known, _)| *known == name).map(|(_, function)| *function)
+}
+
+/// Look up a built-in constant by name
+pub fn constant(name: &str) -> Option<f64> {
+    CONSTANTS.iter().find(|(known, _)| *known == name).map(|(_, value)| *value)
+}
+
+/// Is `name` a built-in function, form or constant?
+pub fn is_builtin(name: &str) -> bool {
+    function(name).is_some()
+        || constant(name).is_some()
+        || FORMS.contains(&name)
+        || POLYNOMIAL_FUNCTIONS.contains(&name)
+        || MATRIX_FUNCTIONS.contains(&name)
+        || STATISTICS_FUNCTIONS.contains(&name)
+        || NUMBER_THEORY_FUNCTIONS.contains(&name)
+        || COMBINATORICS_FUNCTIONS.contains(&name)
+        || DISTRIBUTION_FUNCTIONS.contains(&name)
+        || RANDOM_FUNCTIONS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(function("sqrt").unwrap()(16.0), 4.0);
+        assert_eq!(function("log").unwrap()(1000.0), 3.0);
+        assert_eq!(constant("pi"), Some(consts::PI));
+        assert!(is_builtin("ln") && is_builtin("e") && is_builtin("diff") && !is_builtin("x"));
+    }
+}
@@ -0,0 +1,1568 @@
+// src/calculator.rs
+// The calculator engine: numeric modes and evaluating parsed expressions
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::builtins;
+use crate::calculus;
+use crate::distributions::Distribution;
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::explain::{Explanation, Step};
+use crate::matrix::{Matrix, Shape};
+use crate::number_theory;
+use crate::parser::parse_expression;
+use crate::polynomial::Polynomial;
+use crate::random::Random;
+use crate::stats;
+use crate::value::Value;
+
+/// The largest whole number an f64 can store exactly (2^53 - 1)
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// How numbers are treated while calculating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Decimal numbers (the default)
+    #[default]
+    Float,
+    /// Whole numbers only; division drops the remainder and
+    /// results must stay within +/- MAX_SAFE_INTEGER
+    Integer,
+}
+
+/// Names accepted by `--mode` and the `mode` command
+pub const MODE_NAMES: [&str; 2] = ["float", "integer"];
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "float" => Ok(Mode::Float),
+            "integer" => Ok(Mode::Integer),
+            _ => Err(format!("unknown mode '{}' (expected one of: {})", name, MODE_NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mode::Float => write!(f, "float"),
+            Mode::Integer => write!(f, "integer"),
+        }
+    }
+}
+
+/// Format a number, rounded to `precision` decimal places if one is given
+pub fn format_number(value: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(places) => format!("{:.*}", places, value),
+        None => value.to_string(),
+    }
+}
+
+/// A function the user defined, like `area(w, h) = w * h`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+
+// Shown as "(a, b) = body", with the body in its normalized form,
+// which is also how it is saved
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}) = {}", self.params.join(", "), self.body)
+    }
+}
+
+/// How deep user functions may call each other before we give up
+pub const MAX_CALL_DEPTH: usize = 100;
+
+/// The highest power a polynomial may be raised to with `^`
+const MAX_POLYNOMIAL_POWER: u32 = 64;
+
+/// Everything an expression can refer to while it is calculated
+pub struct Context<'a> {
+    pub variables: &'a BTreeMap<String, Value>,
+    pub functions: &'a BTreeMap<String, Function>,
+    pub mode: Mode,
+    /// Where rand() and randint() get their numbers
+    pub random: &'a Random,
+}
+
+/// Collects the steps of a calculation when an explanation was asked for
+struct Tracer {
+    steps: Option<Vec<Step>>,
+    /// How far off the last integral worked out may be
+    error: Option<f64>,
+}
+
+impl Tracer {
+    /// Note one step; `calculation` is only built when steps are being kept
+    fn record(&mut self, calculation: impl FnOnce() -> String, result: &Value, span: Span, depth: usize) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(Step { calculation: calculation(), result: result.clone(), span, depth });
+        }
+    }
+}
+
+/// Show an operand inside a step, with brackets around negative numbers and longer polynomials
+fn operand(value: &Value) -> String {
+    match value {
+        Value::Number(number) if *number < 0.0 => format!("({})", number),
+        Value::Polynomial(polynomial) if polynomial.coefficients().iter().filter(|c| **c != 0.0).count() > 1 => {
+            format!("({})", polynomial)
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// The number inside `value`, or an error pointing at `span` if it is something else
+fn expect_number(value: &Value, span: Span) -> Result<f64, CalcError> {
+    value.as_number().ok_or_else(|| {
+        CalcError::new(ErrorKind::TypeMismatch, format!("Expected a number here, but this is a {}", value.type_name()), Some(span))
+    })
+}
+
+/// Check that a built-in got the number of arguments it takes
+fn check_count(name: &str, expected: usize, args: &[Expr], span: Span) -> Result<(), CalcError> {
+    if args.len() == expected {
+        return Ok(());
+    }
+    Err(CalcError::new(
+        ErrorKind::WrongArgumentCount,
+        format!("'{}' takes {} argument{} but was given {}", name, expected, if expected == 1 { "" } else { "s" }, args.len()),
+        Some(span),
+    ))
+}
+
+/// The vector inside `value`, or an error pointing at `span` if it is something else
+fn expect_vector(value: &Value, span: Span) -> Result<&[f64], CalcError> {
+    match value {
+        Value::Vector(values) => Ok(values),
+        _ => Err(CalcError::new(ErrorKind::TypeMismatch, format!("Expected a vector here, but this is a {}", value.type_name()), Some(span))),
+    }
+}
+
+/// The matrix inside `value`, or an error pointing at `span` if it is something else
+fn expect_matrix(value: &Value, span: Span) -> Result<&Matrix, CalcError> {
+    match value {
+        Value::Matrix(matrix) => Ok(matrix),
+        _ => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            format!("Expected a matrix here, like [[1, 2], [3, 4]], but this is a {}", value.type_name()),
+            Some(span),
+        )),
+    }
+}
+
+/// The matrix inside `value`, which `name` needs to be square
+fn expect_square<'v>(name: &str, value: &'v Value, span: Span) -> Result<&'v Matrix, CalcError> {
+    let matrix = expect_matrix(value, span)?;
+    if matrix.rows() != matrix.cols() {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("'{}' needs a square matrix, but this is a {}", name, matrix.shape()),
+            Some(span),
+        ));
+    }
+    Ok(matrix)
+}
+
+/// The matrix product of a matrix with a matrix or vector; a vector on the left is a row
+/// and on the right a column. `problem` explains the error when the sizes do not fit
+fn matrix_product(a: &Value, b: &Value, problem: &str, span: Span) -> Result<Value, CalcError> {
+    let (product, left, right) = match (a, b) {
+        (Value::Matrix(a), Value::Matrix(b)) => (a.times(b).map(Value::Matrix), a.shape(), b.shape()),
+        (Value::Matrix(a), Value::Vector(v)) => (a.times_vector(v).map(Value::Vector), a.shape(), Shape::Vector(v.len())),
+        (Value::Vector(v), Value::Matrix(b)) => (b.transpose().times_vector(v).map(Value::Vector), Shape::Vector(v.len()), b.shape()),
+        _ => unreachable!("the matrix product needs a matrix and a matrix or vector"),
+    };
+    product.ok_or_else(|| shape_mismatch(problem, left, right, span))
+}
+
+/// A square matrix to a whole power; a negative power is a power of the inverse
+fn matrix_power(matrix: &Matrix, power: f64, span: Span) -> Result<Value, CalcError> {
+    let problem = if matrix.rows() != matrix.cols() {
+        format!("Only a square matrix can be raised to a power, but this is a {}", matrix.shape())
+    } else if !is_whole(power) {
+        format!("A matrix can only be raised to a whole power, not {}", power)
+    } else {
+        let base = if power < 0.0 { matrix.inverse() } else { Some(matrix.clone()) };
+        if let Some(result) = base.and_then(|base| base.power(power.abs() as u64)) {
+            return Ok(Value::Matrix(result));
+        }
+        "This matrix is singular, so it has no inverse to raise to a negative power".to_string()
+    };
+    Err(CalcError::new(ErrorKind::InvalidArgument, problem, Some(span)))
+}
+
+/// An error for sizes that do not fit, naming both
+fn shape_mismatch(problem: &str, left: Shape, right: Shape, span: Span) -> CalcError {
+    CalcError::new(ErrorKind::ShapeMismatch(left, right), format!("{}: {} and {}", problem, left, right), Some(span))
+}
+
+/// The whole number inside `value`, or an error pointing at `span`
+fn whole_number(value: &Value, span: Span) -> Result<i64, CalcError> {
+    let number = expect_number(value, span)?;
+    // Past 2^53 an f64 skips whole numbers, so it may not be the number that was typed
+    if number.fract() == 0.0 && number.abs() > MAX_SAFE_INTEGER {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("{} is too large to hold exactly; whole numbers go up to {}", number, MAX_SAFE_INTEGER),
+            Some(span),
+        ));
+    }
+    if !is_whole(number) {
+        return Err(CalcError::new(ErrorKind::InvalidArgument, format!("Expected a whole number here, not {}", number), Some(span)));
+    }
+    Ok(number as i64)
+}
+
+/// The inverse of `a` modulo `m`, or an error saying why there is none
+fn inverse_mod(a: u64, m: u64, span: Span) -> Result<u64, CalcError> {
+    number_theory::mod_inverse(a, m).ok_or_else(|| {
+        CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("{} has no inverse modulo {} (they share the factor {})", a, m, number_theory::gcd(a, m)),
+            Some(span),
+        )
+    })
+}
+
+/// Numbers can stand in for constant polynomials
+fn to_polynomial(value: &Value, span: Span) -> Result<Polynomial, CalcError> {
+    match value {
+        Value::Number(number) => Ok(Polynomial::constant(*number)),
+        Value::Polynomial(polynomial) => Ok(polynomial.clone()),
+        _ => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            format!("Expected a number or polynomial here, but this is a {}", value.type_name()),
+            Some(span),
+        )),
+    }
+}
+
+/// `[1, 2]` is a vector; `[[1, 2], [3, 4]]`, a list of vectors of the same length, is a matrix
+fn collect_vector(values: Vec<Value>, items: &[Expr]) -> Result<Value, CalcError> {
+    if !matches!(values.first(), Some(Value::Vector(_))) {
+        let numbers = values.iter().zip(items).map(|(value, item)| expect_number(value, item.span)).collect::<Result<_, _>>()?;
+        return Ok(Value::Vector(numbers));
+    }
+
+    let mut rows: Vec<Vec<f64>> = Vec::new();
+    for (value, item) in values.into_iter().zip(items) {
+        let Value::Vector(row) = value else {
+            return Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                format!("Every row of a matrix must be a vector like [1, 2], but this is a {}", value.type_name()),
+                Some(item.span),
+            ));
+        };
+        if let Some(first) = rows.first()
+            && first.len() != row.len()
+        {
+            let (first, this) = (Shape::Vector(first.len()), Shape::Vector(row.len()));
+            return Err(shape_mismatch("Every row of a matrix must be the same length", first, this, item.span));
+        }
+        rows.push(row);
+    }
+    Matrix::from_rows(&rows)
+        .map(Value::Matrix)
+        .ok_or_else(|| CalcError::new(ErrorKind::InvalidArgument, "A matrix needs at least one number in each row", Some(items[0].span)))
+}
+
+impl Context<'_> {
+    /// Calculate `expr`, which must give a number
+    pub(crate) fn eval(&self, expr: &Expr) -> Result<f64, CalcError> {
+        expect_number(&self.evaluate(expr)?, expr.span)
+    }
+
+    /// Calculate `expr` using these variables, functions and mode
+    pub(crate) fn evaluate(&self, expr: &Expr) -> Result<Value, CalcError> {
+        self.estimate(expr).map(|(value, _)| value)
+    }
+
+    /// Calculate `expr`, with roughly how far off the answer may be when it is
+    /// an estimate rather than exact: `integrate(...)` on its own
+    pub(crate) fn estimate(&self, expr: &Expr) -> Result<(Value, Option<f64>), CalcError> {
+        let mut tracer = Tracer { steps: None, error: None };
+        let value = self.eval_in(expr, &BTreeMap::new(), 0, &mut tracer)?;
+        // The integral is worked out last, after its arguments, so its error is the one kept
+        let integral = matches!(&expr.kind, ExprKind::Call(name, _) if name == "integrate" && !self.functions.contains_key(name));
+        Ok((value, tracer.error.filter(|_| integral)))
+    }
+
+    /// Calculate `expr`, keeping every step in the order it was worked out
+    pub(crate) fn explain(&self, expr: &Expr) -> Result<Explanation, CalcError> {
+        let mut tracer = Tracer { steps: Some(Vec::new()), error: None };
+        let result = self.eval_in(expr, &BTreeMap::new(), 0, &mut tracer)?;
+        Ok(Explanation { steps: tracer.steps.unwrap_or_default(), result })
+    }
+
+    /// Calculate `expr` where `locals` are the parameters of the function being run
+    fn eval_in(&self, expr: &Expr, locals: &BTreeMap<String, Value>, depth: usize, tracer: &mut Tracer) -> Result<Value, CalcError> {
+        let result = match &expr.kind {
+            ExprKind::Number(value) => {
+                if self.mode == Mode::Integer && !is_whole(*value) {
+                    return Err(CalcError::new(
+                        ErrorKind::InvalidNumber,
+                        format!("'{}' is not a whole number (integer mode)", value),
+                        Some(expr.span),
+                    ));
+                }
+                Value::Number(*value)
+            }
+            // Parameters first, then the user's variables, then constants like pi
+            ExprKind::Variable(name) => {
+                let value = locals.get(name).or_else(|| self.variables.get(name)).cloned();
+                match value.or_else(|| builtins::constant(name).map(Value::Number)) {
+                    Some(value) => {
+                        tracer.record(|| name.clone(), &value, expr.span, depth);
+                        value
+                    }
+                    None => {
+                        return Err(CalcError::new(
+                            ErrorKind::UnknownVariable,
+                            format!("Unknown variable '{}' - give it a value first, like '{} = 5'", name, name),
+                            Some(expr.span),
+                        ))
+                    }
+                }
+            }
+            ExprKind::Unary(UnaryOp::Negate, inner) => {
+                let operand = self.eval_in(inner, locals, depth, tracer)?;
+                let value = match &operand {
+                    Value::Number(number) => Value::Number(-number),
+                    Value::Polynomial(polynomial) => Value::Polynomial(polynomial.scaled(-1.0)),
+                    Value::Vector(values) => Value::Vector(values.iter().map(|value| -value).collect()),
+                    Value::Matrix(matrix) => Value::Matrix(matrix.map(|value| -value)),
+                };
+                // "-5" is just a number, but "-(2 + 3)" is a step of its own
+                if !matches!(inner.kind, ExprKind::Number(_)) {
+                    tracer.record(|| format!("-({})", operand), &value, expr.span, depth);
+                }
+                value
+            }
+            ExprKind::Binary(op, left, right) => {
+                let a = self.eval_in(left, locals, depth, tracer)?;
+                let b = self.eval_in(right, locals, depth, tracer)?;
+                let result = self.apply(*op, &a, &b, right.span)?;
+                tracer.record(|| format!("{} {} {}", operand(&a), op.symbol(), operand(&b)), &result, expr.span, depth);
+                result
+            }
+            ExprKind::Call(name, args) => self.call(name, args, expr.span, locals, depth, tracer)?,
+            ExprKind::Vector(items) => collect_vector(self.eval_all(items, locals, depth, tracer)?, items)?,
+        };
+
+        self.check_integer(result, expr.span)
+    }
+
+    /// Apply a binary operator; `right_span` points at the right operand for its errors
+    fn apply(&self, op: BinaryOp, a: &Value, b: &Value, right_span: Span) -> Result<Value, CalcError> {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(self.apply_numbers(op, *a, *b, right_span)?)),
+            (Value::Matrix(_), _) | (_, Value::Matrix(_)) => self.apply_matrices(op, a, b, right_span),
+            (Value::Vector(_), _) | (_, Value::Vector(_)) => {
+                self.apply_elementwise(op, a, b, right_span)
+            }
+            _ => self.apply_polynomials(op, a, b, right_span),
+        }
+    }
+
+    /// Apply a binary operator to two numbers
+    fn apply_numbers(&self, op: BinaryOp, a: f64, b: f64, right_span: Span) -> Result<f64, CalcError> {
+        Ok(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => {
+                if b == 0.0 {
+                    return Err(CalcError::new(ErrorKind::DivisionByZero, "Cannot divide by zero!", Some(right_span)));
+                }
+                match self.mode {
+                    Mode::Float => a / b,
+                    // Integer division drops the remainder, like Rust's own `/` on integers
+                    Mode::Integer => (a / b).trunc(),
+                }
+            }
+            BinaryOp::Power => a.powf(b),
+        })
+    }
+
+    /// Arithmetic with a matrix: `*` is the matrix product and `^` multiplies a square matrix
+    /// by itself (a negative power uses the inverse); `+`, `-` and `/` by a number go entry by entry
+    fn apply_matrices(&self, op: BinaryOp, a: &Value, b: &Value, right_span: Span) -> Result<Value, CalcError> {
+        match (op, a, b) {
+            (BinaryOp::Multiply, Value::Matrix(_) | Value::Vector(_), Value::Matrix(_) | Value::Vector(_)) => {
+                let problem = "'*' is the matrix product, so the left side needs as many columns as the right has rows \
+                               (use hadamard to multiply entry by entry)";
+                matrix_product(a, b, problem, right_span)
+            }
+            (BinaryOp::Power, Value::Matrix(matrix), Value::Number(power)) => matrix_power(matrix, *power, right_span),
+            (BinaryOp::Power, _, _) => Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                format!("Only a square matrix can be raised to a power, and only to a number (not a {} to a {})", a.type_name(), b.type_name()),
+                Some(right_span),
+            )),
+            (BinaryOp::Divide, _, Value::Matrix(_)) => Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                "Cannot divide by a matrix; multiply by its inverse instead, like A * inverse(B)",
+                Some(right_span),
+            )),
+            _ => self.apply_elementwise(op, a, b, right_span),
+        }
+    }
+
+    /// Arithmetic on vectors and matrices, one entry at a time; a number is used with every entry
+    fn apply_elementwise(&self, op: BinaryOp, a: &Value, b: &Value, right_span: Span) -> Result<Value, CalcError> {
+        if let (Value::Polynomial(_), other) | (other, Value::Polynomial(_)) = (a, b) {
+            return Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                format!("Cannot use '{}' with a polynomial and a {}", op.symbol(), other.type_name()),
+                Some(right_span),
+            ));
+        }
+
+        let shape = match (a.shape(), b.shape()) {
+            (Some(left), Some(right)) if left != right => {
+                let problem = format!("Cannot use '{}' on values of different sizes", op.symbol());
+                return Err(shape_mismatch(&problem, left, right, right_span));
+            }
+            (Some(shape), _) | (_, Some(shape)) => shape,
+            (None, None) => unreachable!("one side is a vector or matrix"),
+        };
+
+        // A number is repeated for every entry of the other side
+        let entry = |value: &Value, index: usize| match value {
+            Value::Number(number) => *number,
+            _ => value.elements().map_or(0.0, |elements| elements[index]),
+        };
+        let count = a.elements().or(b.elements()).map_or(0, |elements| elements.len());
+        let data = (0..count).map(|index| self.apply_numbers(op, entry(a, index), entry(b, index), right_span)).collect::<Result<Vec<_>, _>>()?;
+        Ok(match shape {
+            Shape::Vector(_) => Value::Vector(data),
+            Shape::Matrix(rows, cols) => Value::Matrix(Matrix::new(rows, cols, data)),
+        })
+    }
+
+    /// Arithmetic with at least one polynomial; `/` keeps the quotient of long division
+    fn apply_polynomials(&self, op: BinaryOp, a: &Value, b: &Value, right_span: Span) -> Result<Value, CalcError> {
+        let (p, q) = (to_polynomial(a, right_span)?, to_polynomial(b, right_span)?);
+        Ok(Value::Polynomial(match op {
+            BinaryOp::Add => p.plus(&q),
+            BinaryOp::Subtract => p.minus(&q),
+            BinaryOp::Multiply => p.times(&q),
+            BinaryOp::Divide => match p.divide(&q) {
+                Some((quotient, _)) => quotient,
+                None => return Err(CalcError::new(ErrorKind::DivisionByZero, "Cannot divide by zero!", Some(right_span))),
+            },
+            BinaryOp::Power => match b {
+                Value::Number(power) if is_whole(*power) && (0.0..=MAX_POLYNOMIAL_POWER as f64).contains(power) => {
+                    p.power(*power as u32)
+                }
+                Value::Number(_) => {
+                    return Err(CalcError::new(
+                        ErrorKind::InvalidArgument,
+                        format!("A polynomial can only be raised to a whole power from 0 to {}", MAX_POLYNOMIAL_POWER),
+                        Some(right_span),
+                    ))
+                }
+                _ => {
+                    return Err(CalcError::new(
+                        ErrorKind::TypeMismatch,
+                        format!("A power must be a number, not a {}", b.type_name()),
+                        Some(right_span),
+                    ))
+                }
+            },
+        }))
+    }
+
+    /// Call a function: the user's own first, then a polynomial variable like p(3),
+    /// then `diff` and `integrate`, then the built-in ones
+    fn call(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        let Some(function) = self.functions.get(name) else {
+            if let Some(Value::Polynomial(polynomial)) = locals.get(name).or_else(|| self.variables.get(name)) {
+                return self.call_polynomial_value(name, polynomial, args, span, locals, depth, tracer);
+            }
+            if name == "diff" {
+                return self.diff(args, span, locals, depth, tracer);
+            }
+            if name == "integrate" {
+                return self.integrate(args, span, locals, depth, tracer);
+            }
+            if builtins::POLYNOMIAL_FUNCTIONS.contains(&name) {
+                return self.call_polynomial_function(name, args, span, locals, depth, tracer);
+            }
+            if builtins::MATRIX_FUNCTIONS.contains(&name) {
+                return self.call_matrix_function(name, args, span, locals, depth, tracer);
+            }
+            if builtins::STATISTICS_FUNCTIONS.contains(&name) {
+                return self.call_statistics_function(name, args, span, locals, depth, tracer);
+            }
+            if builtins::NUMBER_THEORY_FUNCTIONS.contains(&name) || builtins::COMBINATORICS_FUNCTIONS.contains(&name) {
+                return self.call_number_theory_function(name, args, span, locals, depth, tracer);
+            }
+            if builtins::DISTRIBUTION_FUNCTIONS.contains(&name) {
+                return self.call_distribution_function(name, args, span, locals, depth, tracer);
+            }
+            if builtins::RANDOM_FUNCTIONS.contains(&name) {
+                return self.call_random_function(name, args, span, locals, depth, tracer);
+            }
+            if let Some(builtin) = builtins::function(name) {
+                return self.call_builtin(name, builtin, args, span, locals, depth, tracer);
+            }
+            return Err(CalcError::new(
+                ErrorKind::UnknownFunction,
+                format!("Unknown function '{}' - define it first, like '{}(x) = x * 2'", name, name),
+                Some(span),
+            ));
+        };
+
+        if args.len() != function.params.len() {
+            return Err(CalcError::new(
+                ErrorKind::WrongArgumentCount,
+                format!("'{}' takes {} argument(s) but was given {}", name, function.params.len(), args.len()),
+                Some(span),
+            ));
+        }
+
+        if depth >= MAX_CALL_DEPTH {
+            return Err(CalcError::new(
+                ErrorKind::RecursionLimit,
+                format!("Functions called each other more than {} levels deep (does '{}' call itself?)", MAX_CALL_DEPTH, name),
+                Some(span),
+            ));
+        }
+
+        // Arguments are calculated in the caller's scope, the body only sees its parameters
+        let mut params = BTreeMap::new();
+        let mut values = Vec::new();
+        for (param, arg) in function.params.iter().zip(args) {
+            let value = self.eval_in(arg, locals, depth, tracer)?;
+            values.push(value.to_string());
+            params.insert(param.clone(), value);
+        }
+        let result = self.eval_in(&function.body, &params, depth + 1, tracer)?;
+        tracer.record(|| format!("{}({})", name, values.join(", ")), &result, span, depth);
+        Ok(result)
+    }
+
+    /// Calculate arguments in the caller's scope
+    fn eval_all(&self, args: &[Expr], locals: &BTreeMap<String, Value>, depth: usize, tracer: &mut Tracer) -> Result<Vec<Value>, CalcError> {
+        args.iter().map(|arg| self.eval_in(arg, locals, depth, tracer)).collect()
+    }
+
+    /// Call a built-in function like sin or sqrt, which takes exactly one number;
+    /// given a vector or matrix, it is used on every entry
+    #[allow(clippy::too_many_arguments)]
+    fn call_builtin(
+        &self,
+        name: &str,
+        builtin: builtins::Builtin,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        check_count(name, 1, args, span)?;
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        let apply = |value: f64| {
+            let result = builtin(value);
+            // sqrt(-1) or ln(0) have no real answer
+            if !result.is_finite() && value.is_finite() {
+                return Err(CalcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("{} is not defined for {}", name, value),
+                    Some(args[0].span),
+                ));
+            }
+            Ok(result)
+        };
+        let result = match &values[0] {
+            Value::Vector(entries) => Value::Vector(entries.iter().map(|value| apply(*value)).collect::<Result<_, _>>()?),
+            Value::Matrix(matrix) => {
+                let data = matrix.data().iter().map(|value| apply(*value)).collect::<Result<_, _>>()?;
+                Value::Matrix(Matrix::new(matrix.rows(), matrix.cols(), data))
+            }
+            value => Value::Number(apply(expect_number(value, args[0].span)?)?),
+        };
+        tracer.record(|| format!("{}({})", name, values[0]), &result, span, depth);
+        Ok(result)
+    }
+
+    /// A polynomial stored in a variable can be called to get its value: p(3)
+    #[allow(clippy::too_many_arguments)]
+    fn call_polynomial_value(
+        &self,
+        name: &str,
+        polynomial: &Polynomial,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        check_count(name, 1, args, span)?;
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        // A polynomial of a polynomial is a polynomial too
+        let result = match &values[0] {
+            Value::Number(x) => Value::Number(polynomial.evaluate(*x)),
+            Value::Polynomial(inner) => Value::Polynomial(
+                polynomial.coefficients().iter().rev().fold(Polynomial::default(), |total, c| total.times(inner).plus(&Polynomial::constant(*c))),
+            ),
+            other => Value::Number(polynomial.evaluate(expect_number(other, args[0].span)?)),
+        };
+        tracer.record(|| format!("{}({})", name, values[0]), &result, span, depth);
+        Ok(result)
+    }
+
+    /// poly(1, 0, -2), derivative(p), gcd(p, q), rem(p, q) and degree(p)
+    /// Numbers count as constant polynomials
+    fn call_polynomial_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        match name {
+            "poly" if args.is_empty() => {
+                return Err(CalcError::new(
+                    ErrorKind::WrongArgumentCount,
+                    "'poly' needs its coefficients, highest power first, like poly(1, 0, -2) for x^2 - 2",
+                    Some(span),
+                ))
+            }
+            "poly" => {}
+            "derivative" | "degree" => check_count(name, 1, args, span)?,
+            _ => check_count(name, 2, args, span)?,
+        }
+        let values = self.eval_all(args, locals, depth, tracer)?;
+
+        let result = match name {
+            "poly" => {
+                let coefficients =
+                    values.iter().zip(args).map(|(value, arg)| expect_number(value, arg.span)).collect::<Result<Vec<_>, _>>()?;
+                Value::Polynomial(Polynomial::from_highest(&coefficients))
+            }
+            "derivative" => Value::Polynomial(to_polynomial(&values[0], args[0].span)?.derivative()),
+            "degree" => match to_polynomial(&values[0], args[0].span)?.degree() {
+                Some(degree) => Value::Number(degree as f64),
+                None => return Err(CalcError::new(ErrorKind::InvalidArgument, "The zero polynomial has no degree", Some(args[0].span))),
+            },
+            // Two whole numbers have a whole-number gcd, not a polynomial one
+            "gcd" if values.iter().all(|value| matches!(value, Value::Number(_))) => {
+                let (a, b) = (whole_number(&values[0], args[0].span)?, whole_number(&values[1], args[1].span)?);
+                Value::Number(number_theory::gcd(a.unsigned_abs(), b.unsigned_abs()) as f64)
+            }
+            "gcd" => Value::Polynomial(to_polynomial(&values[0], args[0].span)?.gcd(&to_polynomial(&values[1], args[1].span)?)),
+            _ => match to_polynomial(&values[0], args[0].span)?.divide(&to_polynomial(&values[1], args[1].span)?) {
+                Some((_, remainder)) => Value::Polynomial(remainder),
+                None => return Err(CalcError::new(ErrorKind::DivisionByZero, "Cannot divide by zero!", Some(args[1].span))),
+            },
+        };
+        let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        Ok(result)
+    }
+
+    /// dot, cross, matmul, hadamard, transpose, det, inverse, rank and linsolve
+    fn call_matrix_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        match name {
+            "transpose" | "det" | "inverse" | "rank" => check_count(name, 1, args, span)?,
+            _ => check_count(name, 2, args, span)?,
+        }
+        let values = self.eval_all(args, locals, depth, tracer)?;
+
+        let result = match name {
+            "dot" => {
+                let (u, v) = (expect_vector(&values[0], args[0].span)?, expect_vector(&values[1], args[1].span)?);
+                if u.len() != v.len() {
+                    return Err(shape_mismatch("'dot' needs vectors of the same length", Shape::Vector(u.len()), Shape::Vector(v.len()), span));
+                }
+                Value::Number(u.iter().zip(v).map(|(a, b)| a * b).sum())
+            }
+            "cross" => {
+                let (u, v) = (expect_vector(&values[0], args[0].span)?, expect_vector(&values[1], args[1].span)?);
+                let ([u1, u2, u3], [v1, v2, v3]) = (u, v) else {
+                    return Err(shape_mismatch("'cross' needs two vectors of length 3", Shape::Vector(u.len()), Shape::Vector(v.len()), span));
+                };
+                Value::Vector(vec![u2 * v3 - u3 * v2, u3 * v1 - u1 * v3, u1 * v2 - u2 * v1])
+            }
+            "matmul" => self.matmul(&values, args, span)?,
+            "hadamard" => {
+                for (value, arg) in values.iter().zip(args) {
+                    if value.shape().is_none() {
+                        return Err(expect_matrix(value, arg.span).unwrap_err());
+                    }
+                }
+                if let (Some(left), Some(right)) = (values[0].shape(), values[1].shape())
+                    && left != right
+                {
+                    return Err(shape_mismatch("'hadamard' needs two values of the same size", left, right, span));
+                }
+                self.apply_elementwise(BinaryOp::Multiply, &values[0], &values[1], span)?
+            }
+            "transpose" => match &values[0] {
+                // A vector is treated as a single row
+                Value::Vector(row) if !row.is_empty() => Value::Matrix(Matrix::new(1, row.len(), row.clone()).transpose()),
+                value => Value::Matrix(expect_matrix(value, args[0].span)?.transpose()),
+            },
+            "det" => Value::Number(expect_square(name, &values[0], args[0].span)?.determinant().unwrap_or_default()),
+            "inverse" => match expect_square(name, &values[0], args[0].span)?.inverse() {
+                Some(inverse) => Value::Matrix(inverse),
+                None => return Err(CalcError::new(ErrorKind::InvalidArgument, "This matrix is singular, so it has no inverse", Some(args[0].span))),
+            },
+            "rank" => Value::Number(expect_matrix(&values[0], args[0].span)?.rank() as f64),
+            _ => {
+                let a = expect_square(name, &values[0], args[0].span)?;
+                let b = expect_vector(&values[1], args[1].span)?;
+                if b.len() != a.rows() {
+                    return Err(shape_mismatch("'linsolve' needs one number in b for each row of A", a.shape(), Shape::Vector(b.len()), span));
+                }
+                match a.solve(b) {
+                    Some(x) => Value::Vector(x),
+                    None => {
+                        return Err(CalcError::new(
+                            ErrorKind::InvalidArgument,
+                            "This matrix is singular, so there is no single solution",
+                            Some(args[0].span),
+                        ))
+                    }
+                }
+            }
+        };
+        let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        Ok(result)
+    }
+
+    /// The matrix product, the same as `*`
+    fn matmul(&self, values: &[Value], args: &[Expr], span: Span) -> Result<Value, CalcError> {
+        match (&values[0], &values[1]) {
+            (Value::Matrix(_), Value::Matrix(_) | Value::Vector(_)) | (Value::Vector(_), Value::Matrix(_)) => {
+                matrix_product(&values[0], &values[1], "'matmul' needs as many columns on the left as rows on the right", span)
+            }
+            (Value::Matrix(_), other) | (Value::Vector(_), other) => Err(expect_matrix(other, args[1].span).unwrap_err()),
+            (other, _) => Err(expect_matrix(other, args[0].span).unwrap_err()),
+        }
+    }
+
+    /// mean, median, stddev and the other statistics; most take one list of numbers,
+    /// quantile also takes the fraction(s) to find, and cov, corr and linreg take two lists
+    fn call_statistics_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        if matches!(name, "quantile" | "cov" | "corr" | "linreg") {
+            check_count(name, 2, args, span)?;
+        }
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        let invalid = |message: String| CalcError::new(ErrorKind::InvalidArgument, message, Some(span));
+        let needs = |count: &str| invalid(format!("'{}' needs at least {} number{}", name, count, if count == "one" { "" } else { "s" }));
+
+        let result = match name {
+            "quantile" => {
+                let data = expect_vector(&values[0], args[0].span)?;
+                let quantile = |p: f64| {
+                    if data.is_empty() {
+                        return Err(needs("one"));
+                    }
+                    stats::quantile(data, p)
+                        .ok_or_else(|| CalcError::new(ErrorKind::InvalidArgument, format!("A quantile must be from 0 to 1, not {}", p), Some(args[1].span)))
+                };
+                // Several fractions at once give a vector: quantile(data, [0.25, 0.5, 0.75])
+                match &values[1] {
+                    Value::Vector(ps) => Value::Vector(ps.iter().map(|p| quantile(*p)).collect::<Result<_, _>>()?),
+                    other => Value::Number(quantile(expect_number(other, args[1].span)?)?),
+                }
+            }
+            "cov" | "corr" | "linreg" => {
+                let (xs, ys) = (expect_vector(&values[0], args[0].span)?, expect_vector(&values[1], args[1].span)?);
+                if xs.len() != ys.len() {
+                    let problem = format!("'{}' needs two lists of the same length", name);
+                    return Err(shape_mismatch(&problem, Shape::Vector(xs.len()), Shape::Vector(ys.len()), span));
+                }
+                match name {
+                    "cov" => Value::Number(stats::covariance(xs, ys).ok_or_else(|| needs("two"))?),
+                    "corr" => Value::Number(stats::correlation(xs, ys).ok_or_else(|| {
+                        invalid("'corr' needs at least two numbers in each list, and they must not all be the same".to_string())
+                    })?),
+                    _ => match stats::linear_regression(xs, ys) {
+                        Some((slope, intercept)) => Value::Vector(vec![slope, intercept]),
+                        None => return Err(invalid("'linreg' needs at least two different x values".to_string())),
+                    },
+                }
+            }
+            _ => {
+                // One vector, or the numbers themselves
+                let data = match values.as_slice() {
+                    [Value::Vector(data)] => data.clone(),
+                    _ => values.iter().zip(args).map(|(value, arg)| expect_number(value, arg.span)).collect::<Result<_, _>>()?,
+                };
+                Value::Number(match name {
+                    "sum" => stats::sum(&data),
+                    "product" => stats::product(&data),
+                    "mean" => stats::mean(&data).ok_or_else(|| needs("one"))?,
+                    "median" => stats::median(&data).ok_or_else(|| needs("one"))?,
+                    "mode" => stats::mode(&data).ok_or_else(|| needs("one"))?,
+                    "min" => stats::min(&data).ok_or_else(|| needs("one"))?,
+                    "max" => stats::max(&data).ok_or_else(|| needs("one"))?,
+                    "variance" => stats::variance(&data, true).ok_or_else(|| needs("two"))?,
+                    "stddev" => stats::stddev(&data, true).ok_or_else(|| needs("two"))?,
+                    "pvariance" => stats::variance(&data, false).ok_or_else(|| needs("one"))?,
+                    _ => stats::stddev(&data, false).ok_or_else(|| needs("one"))?,
+                })
+            }
+        };
+        let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        Ok(result)
+    }
+
+    /// lcm, modpow, modinv, isprime, factor, prime, totient, binomial (or nCr) and nPr, on whole numbers
+    /// up to 2^53 - 1 in either mode. Answers are exact; one too large to hold exactly is an overflow error
+    fn call_number_theory_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        let count = match name {
+            "modpow" => 3,
+            "lcm" | "modinv" | "binomial" | "nCr" | "nPr" => 2,
+            _ => 1,
+        };
+        check_count(name, count, args, span)?;
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        let numbers = values.iter().zip(args).map(|(value, arg)| whole_number(value, arg.span)).collect::<Result<Vec<_>, _>>()?;
+
+        // The argument at `index`, which must be at least `least`
+        let at_least = |index: usize, least: i64| {
+            if numbers[index] < least {
+                return Err(CalcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("'{}' needs a whole number of at least {} here, not {}", name, least, numbers[index]),
+                    Some(args[index].span),
+                ));
+            }
+            Ok(numbers[index] as u64)
+        };
+        let too_large = || CalcError::new(ErrorKind::Overflow, format!("The answer to '{}' is too large to hold exactly", name), Some(span));
+
+        let result = match name {
+            "lcm" => number_theory::lcm(numbers[0].unsigned_abs(), numbers[1].unsigned_abs()).ok_or_else(too_large)?,
+            "modpow" => {
+                let m = at_least(2, 1)?;
+                let base = numbers[0].rem_euclid(m as i64) as u64;
+                // A negative power uses the inverse: modpow(3, -1, 7) = modinv(3, 7)
+                let base = if numbers[1] < 0 { inverse_mod(base, m, args[0].span)? } else { base };
+                number_theory::pow_mod(base, numbers[1].unsigned_abs(), m)
+            }
+            "modinv" => {
+                let m = at_least(1, 1)?;
+                inverse_mod(numbers[0].rem_euclid(m as i64) as u64, m, args[0].span)?
+            }
+            "isprime" => u64::from(numbers[0] >= 0 && number_theory::is_prime(numbers[0] as u64)),
+            "factor" => {
+                let factors = number_theory::factorize(at_least(0, 1)?);
+                let result = Value::Vector(factors.into_iter().map(|factor| factor as f64).collect());
+                tracer.record(|| format!("factor({})", numbers[0]), &result, span, depth);
+                return Ok(result);
+            }
+            "prime" => {
+                let n = at_least(0, 1)?;
+                number_theory::nth_prime(n).ok_or_else(|| {
+                    CalcError::new(
+                        ErrorKind::InvalidArgument,
+                        format!("'prime' only goes up to the {}th prime", number_theory::MAX_NTH_PRIME),
+                        Some(args[0].span),
+                    )
+                })?
+            }
+            "totient" => number_theory::totient(at_least(0, 1)?),
+            "nPr" => number_theory::permutations(at_least(0, 0)?, at_least(1, 0)?).ok_or_else(too_large)?,
+            _ => number_theory::binomial(at_least(0, 0)?, at_least(1, 0)?).ok_or_else(too_large)?,
+        };
+        if result as f64 > MAX_SAFE_INTEGER {
+            return Err(too_large());
+        }
+        let result = Value::Number(result as f64);
+        let shown: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        Ok(result)
+    }
+
+    /// The pdf, cdf and inverse cdf of a distribution, like normcdf(x, mean, sd) or binompdf(k, n, p)
+    /// The first argument may be a vector or matrix, to work out many values at once
+    fn call_distribution_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        let (family, kind) = name.split_at(name.len() - 3);
+        // normpdf(x) is the standard normal, with mean 0 and standard deviation 1
+        if family == "norm" && args.len() == 1 {
+            check_count(name, 1, args, span)?;
+        } else {
+            check_count(name, if matches!(family, "poisson" | "exp") { 2 } else { 3 }, args, span)?;
+        }
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        let parameters = values[1..].iter().zip(&args[1..]).map(|(value, arg)| expect_number(value, arg.span)).collect::<Result<Vec<_>, _>>()?;
+
+        let distribution = match family {
+            "binom" => Distribution::binomial(parameters[0], parameters[1]),
+            "poisson" => Distribution::poisson(parameters[0]),
+            "norm" if parameters.is_empty() => Distribution::normal(0.0, 1.0),
+            "norm" => Distribution::normal(parameters[0], parameters[1]),
+            "unif" => Distribution::uniform(parameters[0], parameters[1]),
+            _ => Distribution::exponential(parameters[0]),
+        }
+        .map_err(|message| CalcError::new(ErrorKind::InvalidArgument, format!("'{}': {}", name, message), Some(span)))?;
+
+        let apply = |x: f64| match kind {
+            "pdf" => Ok(distribution.pdf(x)),
+            "cdf" => Ok(distribution.cdf(x)),
+            _ => distribution.inverse(x).ok_or_else(|| {
+                CalcError::new(ErrorKind::InvalidArgument, format!("'{}' needs a chance from 0 to 1, not {}", name, x), Some(args[0].span))
+            }),
+        };
+        let result = match &values[0] {
+            Value::Vector(entries) => Value::Vector(entries.iter().map(|x| apply(*x)).collect::<Result<_, _>>()?),
+            Value::Matrix(matrix) => {
+                let data = matrix.data().iter().map(|x| apply(*x)).collect::<Result<_, _>>()?;
+                Value::Matrix(Matrix::new(matrix.rows(), matrix.cols(), data))
+            }
+            value => Value::Number(apply(expect_number(value, args[0].span)?)?),
+        };
+        let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        self.check_integer(result, span)
+    }
+
+    /// rand() is a number from 0 up to 1, randint(a, b) a whole number from a to b,
+    /// and seed(n) starts the numbers again from n so they can be repeated
+    fn call_random_function(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        let count = match name {
+            "rand" => 0,
+            "seed" => 1,
+            _ => 2,
+        };
+        check_count(name, count, args, span)?;
+        let values = self.eval_all(args, locals, depth, tracer)?;
+        let numbers = values.iter().zip(args).map(|(value, arg)| whole_number(value, arg.span)).collect::<Result<Vec<_>, _>>()?;
+
+        let result = match name {
+            "rand" => Value::Number(self.random.next_f64()),
+            "seed" => {
+                self.random.seed(numbers[0] as u64);
+                Value::Number(numbers[0] as f64)
+            }
+            _ => {
+                if numbers[0] > numbers[1] {
+                    return Err(CalcError::new(
+                        ErrorKind::InvalidArgument,
+                        format!("'randint' needs the smaller number first, like randint({}, {})", numbers[1], numbers[0]),
+                        Some(span),
+                    ));
+                }
+                Value::Number(self.random.between(numbers[0], numbers[1]) as f64)
+            }
+        };
+        let shown: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+        tracer.record(|| format!("{}({})", name, shown.join(", ")), &result, span, depth);
+        self.check_integer(result, span)
+    }
+
+    /// `diff(f, x)` is the slope of f at the current value of x, `diff(f, x, a)` the slope where x = a
+    fn diff(
+        &self,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        let (function, variable) = calculus::diff_arguments(args, span)?;
+        let slope = calculus::derivative(&calculus::inline_functions(function, self.functions)?, variable)?;
+
+        let point = match args.get(2) {
+            Some(point) => expect_number(&self.eval_in(point, locals, depth, tracer)?, point.span)?,
+            None => match locals.get(variable).or_else(|| self.variables.get(variable)) {
+                Some(value) => expect_number(value, args[1].span)?,
+                None => {
+                    return Err(CalcError::new(
+                        ErrorKind::UnknownVariable,
+                        format!("'{}' has no value - say where to work out the slope, like diff({}, {}, 2)", variable, function, variable),
+                        Some(args[1].span),
+                    ))
+                }
+            },
+        };
+
+        // The slope may still use other variables and parameters, so keep them visible
+        let mut at_point = locals.clone();
+        at_point.insert(variable.to_string(), Value::Number(point));
+        let result = self.eval_in(&slope, &at_point, depth + 1, tracer)?;
+        tracer.record(|| format!("diff({}, {}, {})", function, variable, point), &result, span, depth);
+        Ok(result)
+    }
+
+    /// `integrate(f, x, a, b)` is the area under f as x goes from a to b (either may be inf);
+    /// a fifth argument sets the tolerance
+    fn integrate(
+        &self,
+        args: &[Expr],
+        span: Span,
+        locals: &BTreeMap<String, Value>,
+        depth: usize,
+        tracer: &mut Tracer,
+    ) -> Result<Value, CalcError> {
+        if args.len() != 4 && args.len() != 5 {
+            return Err(CalcError::new(
+                ErrorKind::WrongArgumentCount,
+                format!("'integrate' takes 4 or 5 arguments but was given {} (like integrate(x^2, x, 0, 3))", args.len()),
+                Some(span),
+            ));
+        }
+        let ExprKind::Variable(variable) = &args[1].kind else {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                "The second argument of 'integrate' must be a variable name, like integrate(x^2, x, 0, 3)",
+                Some(args[1].span),
+            ));
+        };
+        let a = expect_number(&self.eval_in(&args[2], locals, depth, tracer)?, args[2].span)?;
+        let b = expect_number(&self.eval_in(&args[3], locals, depth, tracer)?, args[3].span)?;
+        let tolerance = match args.get(4) {
+            Some(tolerance) => expect_number(&self.eval_in(tolerance, locals, depth, tracer)?, tolerance.span)?,
+            None => calculus::DEFAULT_TOLERANCE,
+        };
+
+        // The function is worked out hundreds of times, far too many steps to show
+        let mut at_point = locals.clone();
+        let integral = calculus::integrate(
+            |x| {
+                at_point.insert(variable.clone(), Value::Number(x));
+                expect_number(&self.eval_in(&args[0], &at_point, depth + 1, &mut Tracer { steps: None, error: None })?, args[0].span)
+            },
+            a,
+            b,
+            tolerance,
+            span,
+        )?;
+        let result = Value::Number(integral.value);
+        tracer.error = Some(integral.error);
+        tracer.record(|| format!("integrate({}, {}, {}, {})", args[0], variable, a, b), &result, span, depth);
+        Ok(result)
+    }
+
+    /// In integer mode, numbers (and the entries of vectors and matrices)
+    /// must be whole and within MAX_SAFE_INTEGER
+    fn check_integer(&self, result: Value, span: Span) -> Result<Value, CalcError> {
+        if self.mode != Mode::Integer {
+            return Ok(result);
+        }
+        let numbers = match &result {
+            Value::Number(value) => std::slice::from_ref(value),
+            other => other.elements().unwrap_or_default(),
+        };
+        for value in numbers.iter().filter(|value| value.is_finite()) {
+            check_whole(*value, span)?;
+        }
+        Ok(result)
+    }
+}
+
+/// A result in integer mode must be whole and within MAX_SAFE_INTEGER
+fn check_whole(value: f64, span: Span) -> Result<(), CalcError> {
+    if value.abs() > MAX_SAFE_INTEGER {
+        return Err(CalcError::new(
+            ErrorKind::Overflow,
+            format!("Result is too large for integer mode (limit is {})", MAX_SAFE_INTEGER),
+            Some(span),
+        ));
+    }
+    if !is_whole(value) {
+        return Err(CalcError::new(
+            ErrorKind::InvalidNumber,
+            format!("Result {} is not a whole number (integer mode)", value),
+            Some(span),
+        ));
+    }
+    Ok(())
+}
+
+/// Is `value` a whole number that f64 stores exactly?
+fn is_whole(value: f64) -> bool {
+    value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER
+}
+
+/// Parse and calculate mathematical expressions
+/// Supports +, -, *, /, ^ and parentheses (e.g., "2 + 3 * (4 - 1)")
+pub fn calculate(expression: &str) -> Result<f64, CalcError> {
+    calculate_with_mode(expression, Mode::Float)
+}
+
+/// Parse and calculate an expression in the given numeric mode
+pub fn calculate_with_mode(expression: &str, mode: Mode) -> Result<f64, CalcError> {
+    let expr = parse_expression(expression)?;
+    let (variables, functions) = (BTreeMap::new(), BTreeMap::new());
+    Context { variables: &variables, functions: &functions, mode, random: &Random::default() }.eval(&expr)
+}
+
+// Unit tests for our calculator functions
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addition() {
+        assert_eq!(calculate("5 + 3").unwrap(), 8.0);
+    }
+
+    #[test]
+    fn test_subtraction() {
+        assert_eq!(calculate("10 - 4").unwrap(), 6.0);
+    }
+
+    #[test]
+    fn test_multiplication() {
+        assert_eq!(calculate("6 * 7").unwrap(), 42.0);
+    }
+
+    #[test]
+    fn test_division() {
+        assert_eq!(calculate("15 / 3").unwrap(), 5.0);
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert!(calculate("5 / 0").is_err());
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        assert!(calculate("hello + 3").is_err());
+    }
+
+    #[test]
+    fn test_invalid_operator() {
+        assert!(calculate("5 % 3").is_err());
+    }
+
+    #[test]
+    fn test_error_kinds_and_spans() {
+        let error = calculate("5 / 0").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::DivisionByZero);
+        assert_eq!(error.span, Some(Span::new(4, 5)));
+
+        // Names are variables now, so an unknown one is its own kind of error
+        let error = calculate("hello + 3").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownVariable);
+        assert_eq!(error.span, Some(Span::new(0, 5)));
+
+        let error = calculate("1.2.3 + 3").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidNumber);
+        assert_eq!(error.span, Some(Span::new(0, 5)));
+
+        let error = calculate("5  %  3").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownOperator);
+        assert_eq!(error.span, Some(Span::new(3, 4)));
+
+        assert_eq!(calculate("5 +").unwrap_err().kind, ErrorKind::Syntax);
+    }
+
+    #[test]
+    fn test_integer_mode() {
+        assert_eq!(calculate_with_mode("7 / 2", Mode::Integer).unwrap(), 3.0);
+        assert_eq!(calculate_with_mode("-7 / 2", Mode::Integer).unwrap(), -3.0);
+        assert_eq!(calculate_with_mode("6 * 7", Mode::Integer).unwrap(), 42.0);
+        assert_eq!(calculate_with_mode("2.5 + 1", Mode::Integer).unwrap_err().kind, ErrorKind::InvalidNumber);
+        assert_eq!(
+            calculate_with_mode("9007199254740991 + 1", Mode::Integer).unwrap_err().kind,
+            ErrorKind::Overflow
+        );
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("integer".parse::<Mode>(), Ok(Mode::Integer));
+        assert_eq!(Mode::Float.to_string(), "float");
+        assert!("complex".parse::<Mode>().is_err());
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(2.0 / 3.0, Some(3)), "0.667");
+        assert_eq!(format_number(8.0, Some(2)), "8.00");
+        assert_eq!(format_number(8.0, None), "8");
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(calculate("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(calculate("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(calculate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(calculate("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(calculate("10 - 4 - 3").unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_division_by_zero_points_at_divisor() {
+        let error = calculate("1 + 6 / (3 - 3)").unwrap_err();
+        assert_eq!(error.span, Some(Span::new(8, 15)));
+    }
+
+    #[test]
+    fn test_integer_mode_checks_every_step() {
+        assert_eq!(calculate_with_mode("2 ^ 10", Mode::Integer).unwrap(), 1024.0);
+        assert_eq!(calculate_with_mode("2 ^ -1", Mode::Integer).unwrap_err().kind, ErrorKind::InvalidNumber);
+        assert_eq!(calculate_with_mode("(7 / 2) * 2", Mode::Integer).unwrap(), 6.0);
+    }
+
+    fn function(params: &[&str], body: &str) -> Function {
+        Function {
+            params: params.iter().map(|param| param.to_string()).collect(),
+            body: parse_expression(body).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_variables_and_functions() {
+        let variables = BTreeMap::from([("rate".to_string(), Value::Number(0.5))]);
+        let functions = BTreeMap::from([
+            ("double".to_string(), function(&["x"], "x * 2")),
+            ("scaled".to_string(), function(&["x"], "double(x) * rate")),
+        ]);
+        let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() };
+
+        assert_eq!(context.eval(&parse_expression("scaled(10) + rate").unwrap()).unwrap(), 10.5);
+
+        let error = context.eval(&parse_expression("double(1, 2)").unwrap()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::WrongArgumentCount);
+        let error = context.eval(&parse_expression("triple(1)").unwrap()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownFunction);
+        // Parameters are only visible inside their own function
+        let error = context.eval(&parse_expression("x").unwrap()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownVariable);
+    }
+
+    #[test]
+    fn test_runaway_recursion_is_stopped() {
+        let variables = BTreeMap::new();
+        let functions = BTreeMap::from([("forever".to_string(), function(&["n"], "forever(n + 1)"))]);
+        let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() };
+        let error = context.eval(&parse_expression("forever(1)").unwrap()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::RecursionLimit);
+    }
+
+    #[test]
+    fn test_builtin_functions_and_constants() {
+        assert_eq!(calculate("sqrt(16) + abs(-2)").unwrap(), 6.0);
+        assert!((calculate("sin(pi / 2)").unwrap() - 1.0).abs() < 1e-12);
+        assert!((calculate("ln(e^2)").unwrap() - 2.0).abs() < 1e-12);
+        assert_eq!(calculate("sqrt(1, 2)").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+
+        let error = calculate("ln(0 - 1)").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+        assert_eq!(error.span, Some(Span::new(3, 8)));
+    }
+
+    #[test]
+    fn test_diff_at_a_point() {
+        assert_eq!(calculate("diff(x^3, x, 2)").unwrap(), 12.0);
+        assert_eq!(calculate("diff(diff(x^3, x), x, 2)").unwrap(), 12.0);
+
+        let variables = BTreeMap::from([("x".to_string(), Value::Number(3.0)), ("k".to_string(), Value::Number(2.0))]);
+        let functions = BTreeMap::from([("f".to_string(), function(&["t"], "k * t^2"))]);
+        let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() };
+        // Without a point, x's own value is used
+        assert_eq!(context.eval(&parse_expression("diff(f(x), x)").unwrap()).unwrap(), 12.0);
+
+        assert_eq!(calculate("diff(x^2, y)").unwrap_err().kind, ErrorKind::UnknownVariable);
+        assert_eq!(calculate("diff(x^2, 2, 1)").unwrap_err().kind, ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_integrate() {
+        assert!((calculate("integrate(x^2, x, 0, 3)").unwrap() - 9.0).abs() < 1e-10);
+        assert!((calculate("integrate(exp(-t), t, 0, inf)").unwrap() - 1.0).abs() < 1e-10);
+        assert!((calculate("integrate(sin(x), x, 0, pi, 1e-4)").unwrap() - 2.0).abs() < 1e-4);
+        // Dividing by zero at one point of the range is not the end of it
+        assert!((calculate("integrate(1/sqrt(abs(x)), x, -1, 1)").unwrap() - 4.0).abs() < 1e-8);
+
+        let error = calculate("integrate(1 / x, x, 1, inf)").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::NoConvergence);
+        assert_eq!(error.span, Some(Span::new(0, 27)));
+        assert_eq!(calculate("integrate(x, x, 0)").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+        assert_eq!(calculate("integrate(ln(x), x, -1, 1)").unwrap_err().kind, ErrorKind::InvalidArgument);
+    }
+
+    fn evaluate(input: &str) -> Result<Value, CalcError> {
+        let (variables, functions) = (BTreeMap::new(), BTreeMap::new());
+        Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() }.evaluate(&parse_expression(input).unwrap())
+    }
+
+    #[test]
+    fn test_polynomial_arithmetic() {
+        let shown = |input: &str| evaluate(input).unwrap().to_string();
+        assert_eq!(shown("poly(1, 0, -2)"), "x^2 - 2");
+        assert_eq!(shown("poly(1, 1) * poly(1, -1) + 1"), "x^2");
+        assert_eq!(shown("-poly(2, 1) / 2"), "-x - 0.5");
+        assert_eq!(shown("poly(1, 1)^2"), "x^2 + 2x + 1");
+        // `/` keeps the quotient of long division and `rem` the remainder
+        assert_eq!(shown("poly(1, 0, -2, 5) / poly(1, -1)"), "x^2 + x - 1");
+        assert_eq!(shown("rem(poly(1, 0, -2, 5), poly(1, -1))"), "4");
+        assert_eq!(shown("gcd(poly(1, 1, -2), poly(2, -8, 6))"), "x - 1");
+        assert_eq!(shown("derivative(poly(1, 0, -2))"), "2x");
+        assert_eq!(shown("degree(poly(3, 2, 1))"), "2");
+    }
+
+    #[test]
+    fn test_polynomial_errors() {
+        assert_eq!(evaluate("poly(1, 2) / poly(0)").unwrap_err().kind, ErrorKind::DivisionByZero);
+        assert_eq!(evaluate("2 ^ poly(1, 0)").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("poly(1, 0) ^ 0.5").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("sin(poly(1, 0))").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("poly()").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+        assert_eq!(evaluate("poly(poly(1, 0))").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(calculate("poly(1, 0)").unwrap_err().kind, ErrorKind::TypeMismatch);
+    }
+
+    fn shown(input: &str) -> String {
+        evaluate(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_vector_and_matrix_arithmetic() {
+        assert_eq!(shown("[1, 2, 3] + [10, 20, 30]"), "[11, 22, 33]");
+        assert_eq!(shown("2 * [1, 2] - 1"), "[1, 3]");
+        assert_eq!(shown("[1, 2] * [3, 4] / 2"), "[1.5, 4]");
+        assert_eq!(shown("[[1, 2], [3, 4]] / 2 + 1"), "[[1.5, 2], [2.5, 3]]");
+        assert_eq!(shown("sqrt([4, 9])"), "[2, 3]");
+        assert_eq!(shown("[]"), "[]");
+        assert_eq!(evaluate("[1, 0] / [1, 0]").unwrap_err().kind, ErrorKind::DivisionByZero);
+    }
+
+    #[test]
+    fn test_matrix_product_and_power() {
+        // `*` is the matrix product, with a vector as a column on the right and a row on the left
+        assert_eq!(shown("[[1, 2], [3, 4]] * [[0, 1], [1, 0]]"), "[[2, 1], [4, 3]]");
+        assert_eq!(shown("[[1, 2], [3, 4]] * [1, 1]"), "[3, 7]");
+        assert_eq!(shown("[1, 1] * [[1, 2], [3, 4]]"), "[4, 6]");
+        assert_eq!(shown("[[1, 2]] * [[1], [2]]"), "[[5]]");
+        assert_eq!(shown("2 * [[1, 2], [3, 4]]"), "[[2, 4], [6, 8]]");
+        assert_eq!(shown("hadamard([[1, 2], [3, 4]], [[2, 2], [2, 2]])"), "[[2, 4], [6, 8]]");
+        // `^` multiplies a square matrix by itself; a negative power uses the inverse
+        assert_eq!(shown("-[[1, 2], [3, 4]] ^ 2"), "[[-7, -10], [-15, -22]]");
+        assert_eq!(shown("[[1, 1], [1, 0]] ^ 10"), "[[89, 55], [55, 34]]");
+        assert_eq!(shown("[[5, 7], [1, 2]] ^ 0"), "[[1, 0], [0, 1]]");
+        assert_eq!(shown("[[2, 0], [0, 4]] ^ -2"), "[[0.25, 0], [0, 0.0625]]");
+
+        assert_eq!(evaluate("[[1, 2], [2, 4]] ^ -1").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("[[1, 2, 3]] ^ 2").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("[[1, 2], [3, 4]] ^ 0.5").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("2 ^ [[1, 2], [3, 4]]").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("1 / [[1, 2], [3, 4]]").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("hadamard(2, [[1, 2]])").unwrap_err().kind, ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn test_matrix_functions() {
+        assert_eq!(shown("dot([1, 2, 3], [4, 5, 6])"), "32");
+        assert_eq!(shown("cross([1, 0, 0], [0, 1, 0])"), "[0, 0, 1]");
+        assert_eq!(shown("matmul([[1, 2], [3, 4]], [[0, 1], [1, 0]])"), "[[2, 1], [4, 3]]");
+        assert_eq!(shown("matmul([[1, 2], [3, 4]], [1, 1])"), "[3, 7]");
+        assert_eq!(shown("matmul([1, 1], [[1, 2], [3, 4]])"), "[4, 6]");
+        assert_eq!(shown("transpose([[1, 2, 3], [4, 5, 6]])"), "[[1, 4], [2, 5], [3, 6]]");
+        assert_eq!(shown("transpose([1, 2])"), "[[1], [2]]");
+        assert_eq!(shown("det([[1, 2], [3, 4]])"), "-2");
+        assert_eq!(shown("inverse([[2, 0], [0, 4]])"), "[[0.5, 0], [0, 0.25]]");
+        assert_eq!(shown("rank([[1, 2], [2, 4]])"), "1");
+        assert_eq!(shown("linsolve([[2, 1], [1, 3]], [3, 5])"), "[0.8, 1.4]");
+    }
+
+    #[test]
+    fn test_shape_errors_carry_both_shapes() {
+        let cases = [
+            ("[1, 2] + [1, 2, 3]", Shape::Vector(2), Shape::Vector(3)),
+            ("[[1, 2], [3]]", Shape::Vector(2), Shape::Vector(1)),
+            ("[[1, 2]] * [[1, 2]]", Shape::Matrix(1, 2), Shape::Matrix(1, 2)),
+            ("hadamard([1, 2], [[1, 2]])", Shape::Vector(2), Shape::Matrix(1, 2)),
+            ("matmul([[1, 2]], [[1, 2]])", Shape::Matrix(1, 2), Shape::Matrix(1, 2)),
+            ("dot([1], [1, 2])", Shape::Vector(1), Shape::Vector(2)),
+            ("linsolve([[1, 0], [0, 1]], [1, 2, 3])", Shape::Matrix(2, 2), Shape::Vector(3)),
+        ];
+        for (input, left, right) in cases {
+            let error = evaluate(input).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::ShapeMismatch(left, right), "for {}", input);
+            assert!(error.message.contains(&left.to_string()) && error.message.contains(&right.to_string()), "{}", error.message);
+        }
+        assert!(evaluate("[[1, 2]] * [[1, 2]]").unwrap_err().message.contains("hadamard"));
+    }
+
+    #[test]
+    fn test_matrix_errors() {
+        assert_eq!(evaluate("[1, poly(1, 0)]").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("[[1], 2]").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("[1, 2] + poly(1, 0)").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("[[], []]").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("det([[1, 2, 3]])").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("inverse([[1, 2], [2, 4]])").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("det([1, 2])").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("transpose(1)").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(calculate("[1, 2]").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(calculate_with_mode("[4, 3] / 2", Mode::Integer).unwrap_err().kind, ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn test_statistics() {
+        assert_eq!(shown("mean([2, 4, 4, 4, 5, 5, 7, 9])"), "5");
+        assert_eq!(shown("mean(1, 2, 3, 4)"), "2.5");
+        assert_eq!(shown("pstddev([2, 4, 4, 4, 5, 5, 7, 9])"), "2");
+        assert_eq!(shown("variance([1, 2, 3, 4])"), "1.6666666666666667");
+        assert_eq!(shown("median([5, 1, 3, 2])"), "2.5");
+        assert_eq!(shown("mode([1, 2, 2, 3])"), "2");
+        assert_eq!(shown("sum([0.1, 0.2, 0.3]) - 0.6"), "0");
+        assert_eq!(shown("product(1, 2, 3, 4)"), "24");
+        assert_eq!(shown("min([3, -1, 2]) + max(3, -1, 2)"), "2");
+        assert_eq!(shown("quantile([1, 2, 3, 4, 5], [0.25, 0.5, 0.75])"), "[2, 3, 4]");
+        assert_eq!(shown("cov([1, 2, 3], [2, 4, 6])"), "2");
+        assert_eq!(shown("corr([1, 2, 3], [3, 2, 1])"), "-1");
+        assert_eq!(shown("linreg([1, 2, 3, 4], [3, 5, 7, 9])"), "[2, 1]");
+    }
+
+    #[test]
+    fn test_number_theory() {
+        assert_eq!(shown("gcd(-12, 18) + lcm(4, 6)"), "18");
+        assert_eq!(shown("gcd(poly(1, -1), poly(1, 0, -1))"), "x - 1");
+        assert_eq!(shown("modpow(4, 13, 497)"), "445");
+        assert_eq!(shown("modpow(-2, 3, 5)"), "2");
+        assert_eq!(shown("modpow(3, -1, 7) - modinv(3, 7)"), "0");
+        assert_eq!(shown("isprime(97) + isprime(91) + isprime(-7)"), "1");
+        assert_eq!(shown("factor(360)"), "[2, 2, 2, 3, 3, 5]");
+        assert_eq!(shown("prime(100) + totient(36) + binomial(52, 5)"), "2599513");
+        // Near the top of the integer range, where plain products would overflow
+        assert_eq!(calculate_with_mode("modpow(3, 9007199254740990, 9007199254740991)", Mode::Integer).unwrap(), 5155089749987738.0);
+    }
+
+    #[test]
+    fn test_number_theory_errors() {
+        assert_eq!(evaluate("modinv(6, 9)").unwrap_err().message, "6 has no inverse modulo 9 (they share the factor 3)");
+        assert_eq!(evaluate("binomial(100, 50)").unwrap_err().kind, ErrorKind::Overflow);
+        assert_eq!(evaluate("lcm(2^40, 3^30)").unwrap_err().kind, ErrorKind::Overflow);
+        assert_eq!(evaluate("factor(2.5)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("factor(0)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("modpow(2, 3, 0)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("prime(2000000)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        // Past 2^53 - 1 the number may not be the one that was typed, in either mode
+        let too_large = evaluate("isprime(18446744073709551557)").unwrap_err();
+        assert_eq!(too_large.message, "18446744073709552000 is too large to hold exactly; whole numbers go up to 9007199254740991");
+        assert_eq!(calculate_with_mode("isprime(9007199254740881)", Mode::Integer).unwrap(), 1.0);
+        assert_eq!(calculate_with_mode("isprime(9007199254740881)", Mode::Float).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_combinatorics_and_distributions() {
+        assert_eq!(shown("nCr(5, 2) + nPr(5, 2)"), "30");
+        assert_eq!(shown("binompdf(2, 4, 0.5)"), "0.375");
+        assert_eq!(shown("binomcdf([0, 4], 4, 0.5)"), "[0.0625, 1]");
+        assert_eq!(shown("binominv(0.7, 4, 0.5)"), "3");
+        assert_eq!(shown("normcdf(0) + normcdf(100, 100, 15)"), "1");
+        assert_eq!(shown("normcdf([-inf, inf, 1e200, -1e200])"), "[0, 1, 1, 0]");
+        assert_eq!(shown("unifcdf(3, 2, 6) + unifinv(0.5, 2, 6)"), "4.25");
+        assert_eq!(shown("poissoninv(0.5, 3) + expcdf(0, 2)"), "3");
+        assert!((calculate("norminv(0.975)").unwrap() - 1.959963984540054).abs() < 1e-12);
+        assert!((calculate("norminv(normcdf(1.3, 2, 0.5), 2, 0.5)").unwrap() - 1.3).abs() < 1e-12);
+        assert_eq!(calculate_with_mode("nCr(10, 3)", Mode::Integer).unwrap(), 120.0);
+
+        assert_eq!(evaluate("nPr(30, 20)").unwrap_err().kind, ErrorKind::Overflow);
+        assert_eq!(evaluate("normpdf(0, 0, -1)").unwrap_err().message, "'normpdf': the standard deviation must be above 0, not -1");
+        assert_eq!(evaluate("binompdf(1, 4, 2)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("norminv(1.5)").unwrap_err().span, Some(Span::new(8, 11)));
+        assert_eq!(evaluate("normpdf(0, 1)").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+        assert_eq!(calculate_with_mode("normpdf(0)", Mode::Integer).unwrap_err().kind, ErrorKind::InvalidNumber);
+    }
+
+    #[test]
+    fn test_seeded_random_numbers() {
+        let (variables, functions) = (BTreeMap::new(), BTreeMap::new());
+        let random = Random::new(1);
+        let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &random };
+        let draw = |input: &str| context.eval(&parse_expression(input).unwrap()).unwrap();
+
+        assert_eq!(draw("seed(42)"), 42.0);
+        let first = [draw("rand()"), draw("rand()"), draw("randint(1, 6)")];
+        draw("seed(42)");
+        assert_eq!([draw("rand()"), draw("rand()"), draw("randint(1, 6)")], first);
+        assert!((0.0..1.0).contains(&first[0]) && first[0] != first[1]);
+        assert!((1.0..=6.0).contains(&first[2]));
+        assert_eq!(draw("randint(3, 3)"), 3.0);
+
+        assert_eq!(evaluate("randint(6, 1)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("rand(1)").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+        assert_eq!(evaluate("seed(0.5)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(calculate_with_mode("rand()", Mode::Integer).unwrap_err().kind, ErrorKind::InvalidNumber);
+    }
+
+    #[test]
+    fn test_statistics_errors() {
+        assert_eq!(evaluate("mean([])").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("stddev([1])").unwrap_err().message, "'stddev' needs at least two numbers");
+        assert_eq!(evaluate("quantile([1, 2], 2)").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(evaluate("quantile([1, 2])").unwrap_err().kind, ErrorKind::WrongArgumentCount);
+        assert_eq!(evaluate("mean([1, 2], 3)").unwrap_err().kind, ErrorKind::TypeMismatch);
+        assert_eq!(evaluate("corr([1, 1], [1, 2])").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(
+            evaluate("linreg([1, 2], [1, 2, 3])").unwrap_err().kind,
+            ErrorKind::ShapeMismatch(Shape::Vector(2), Shape::Vector(3))
+        );
+    }
+}
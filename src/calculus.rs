@@ -0,0 +1,555 @@
+// src/calculus.rs
+// Derivatives of expressions, worked out symbolically with the usual rules,
+// and integrals, worked out numerically
+
+use std::collections::BTreeMap;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::calculator::{Function, MAX_CALL_DEPTH};
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::simplify::simplify;
+
+/// The derivative of `expr` with respect to `variable`, simplified
+/// Other variables are treated as constants. Works for + - * / ^, the built-in
+/// functions (sin, cos, tan, exp, ln, sqrt, ...) and `diff` itself
+pub fn derivative(expr: &Expr, variable: &str) -> Result<Expr, CalcError> {
+    Ok(simplify(&differentiate(expr, variable)?))
+}
+
+/// Replace calls to user-defined functions by their bodies, so that
+/// `derivative` can see inside them: with f(a) = a^2, f(x + 1) becomes (x + 1)^2
+pub fn inline_functions(expr: &Expr, functions: &BTreeMap<String, Function>) -> Result<Expr, CalcError> {
+    inline(expr, functions, 0)
+}
+
+fn inline(expr: &Expr, functions: &BTreeMap<String, Function>, depth: usize) -> Result<Expr, CalcError> {
+    let span = expr.span;
+    Ok(match &expr.kind {
+        ExprKind::Number(_) | ExprKind::Variable(_) => expr.clone(),
+        ExprKind::Unary(op, operand) => Expr::new(ExprKind::Unary(*op, Box::new(inline(operand, functions, depth)?)), span),
+        ExprKind::Binary(op, left, right) => Expr::new(
+            ExprKind::Binary(*op, Box::new(inline(left, functions, depth)?), Box::new(inline(right, functions, depth)?)),
+            span,
+        ),
+        ExprKind::Call(name, args) => {
+            let args = args.iter().map(|arg| inline(arg, functions, depth)).collect::<Result<Vec<_>, _>>()?;
+            let Some(function) = functions.get(name) else {
+                return Ok(Expr::new(ExprKind::Call(name.clone(), args), span));
+            };
+            if args.len() != function.params.len() {
+                return Err(CalcError::new(
+                    ErrorKind::WrongArgumentCount,
+                    format!("'{}' takes {} argument(s) but was given {}", name, function.params.len(), args.len()),
+                    Some(span),
+                ));
+            }
+            if depth >= MAX_CALL_DEPTH {
+                return Err(CalcError::new(
+                    ErrorKind::RecursionLimit,
+                    format!("Functions called each other more than {} levels deep (does '{}' call itself?)", MAX_CALL_DEPTH, name),
+                    Some(span),
+                ));
+            }
+            let values: BTreeMap<&str, &Expr> = function.params.iter().map(String::as_str).zip(&args).collect();
+            inline(&substitute(&function.body, &values), functions, depth + 1)?
+        }
+        ExprKind::Vector(items) => Expr::new(
+            ExprKind::Vector(items.iter().map(|item| inline(item, functions, depth)).collect::<Result<_, _>>()?),
+            span,
+        ),
+    })
+}
+
+/// Put expressions in place of variables
+fn substitute(expr: &Expr, values: &BTreeMap<&str, &Expr>) -> Expr {
+    let kind = match &expr.kind {
+        ExprKind::Variable(name) => match values.get(name.as_str()) {
+            Some(value) => return (*value).clone(),
+            None => expr.kind.clone(),
+        },
+        ExprKind::Number(_) => expr.kind.clone(),
+        ExprKind::Unary(op, operand) => ExprKind::Unary(*op, Box::new(substitute(operand, values))),
+        ExprKind::Binary(op, left, right) => {
+            ExprKind::Binary(*op, Box::new(substitute(left, values)), Box::new(substitute(right, values)))
+        }
+        ExprKind::Call(name, args) => ExprKind::Call(name.clone(), args.iter().map(|arg| substitute(arg, values)).collect()),
+        ExprKind::Vector(items) => ExprKind::Vector(items.iter().map(|item| substitute(item, values)).collect()),
+    };
+    Expr::new(kind, expr.span)
+}
+
+/// Does `expr` depend on `variable`?
+fn contains(expr: &Expr, variable: &str) -> bool {
+    match &expr.kind {
+        ExprKind::Number(_) => false,
+        ExprKind::Variable(name) => name == variable,
+        ExprKind::Unary(_, operand) => contains(operand, variable),
+        ExprKind::Binary(_, left, right) => contains(left, variable) || contains(right, variable),
+        ExprKind::Call(_, args) | ExprKind::Vector(args) => args.iter().any(|arg| contains(arg, variable)),
+    }
+}
+
+/// Apply the differentiation rules, without tidying up the result
+fn differentiate(expr: &Expr, x: &str) -> Result<Expr, CalcError> {
+    let b = Builder { span: expr.span };
+    if !contains(expr, x) {
+        return Ok(b.number(0.0));
+    }
+
+    Ok(match &expr.kind {
+        ExprKind::Number(_) => b.number(0.0),
+        ExprKind::Variable(_) => b.number(1.0),
+        ExprKind::Unary(UnaryOp::Negate, f) => b.negate(differentiate(f, x)?),
+        ExprKind::Binary(op, f, g) => {
+            let (f, g) = (f.as_ref().clone(), g.as_ref().clone());
+            match op {
+                BinaryOp::Add => b.binary(BinaryOp::Add, differentiate(&f, x)?, differentiate(&g, x)?),
+                BinaryOp::Subtract => b.binary(BinaryOp::Subtract, differentiate(&f, x)?, differentiate(&g, x)?),
+                // Product rule: (f g)' = f' g + f g'
+                BinaryOp::Multiply => b.binary(
+                    BinaryOp::Add,
+                    b.binary(BinaryOp::Multiply, differentiate(&f, x)?, g.clone()),
+                    b.binary(BinaryOp::Multiply, f.clone(), differentiate(&g, x)?),
+                ),
+                // Quotient rule: (f / g)' = (f' g - f g') / g^2
+                BinaryOp::Divide => b.binary(
+                    BinaryOp::Divide,
+                    b.binary(
+                        BinaryOp::Subtract,
+                        b.binary(BinaryOp::Multiply, differentiate(&f, x)?, g.clone()),
+                        b.binary(BinaryOp::Multiply, f.clone(), differentiate(&g, x)?),
+                    ),
+                    b.binary(BinaryOp::Power, g.clone(), b.number(2.0)),
+                ),
+                BinaryOp::Power => power_rule(&b, f, g, x)?,
+            }
+        }
+        ExprKind::Call(name, args) => call_rule(&b, name, args, x)?,
+        // Vectors are differentiated one entry at a time
+        ExprKind::Vector(items) => {
+            Expr::new(ExprKind::Vector(items.iter().map(|item| differentiate(item, x)).collect::<Result<_, _>>()?), expr.span)
+        }
+    })
+}
+
+/// The derivative of f^g
+fn power_rule(b: &Builder, f: Expr, g: Expr, x: &str) -> Result<Expr, CalcError> {
+    let expr = b.binary(BinaryOp::Power, f.clone(), g.clone());
+
+    // Power rule: (f^n)' = n f^(n-1) f'
+    if !contains(&g, x) {
+        let lower = b.binary(BinaryOp::Power, f.clone(), b.binary(BinaryOp::Subtract, g.clone(), b.number(1.0)));
+        return Ok(b.binary(BinaryOp::Multiply, b.binary(BinaryOp::Multiply, g, lower), differentiate(&f, x)?));
+    }
+
+    // e^g is exp(g): (e^g)' = e^g g'
+    if matches!(&f.kind, ExprKind::Variable(name) if name == "e") {
+        return Ok(b.binary(BinaryOp::Multiply, expr, differentiate(&g, x)?));
+    }
+
+    // a^g = exp(g ln a), so (a^g)' = a^g ln(a) g'
+    if !contains(&f, x) {
+        let ln = b.call("ln", f);
+        return Ok(b.binary(BinaryOp::Multiply, b.binary(BinaryOp::Multiply, expr, ln), differentiate(&g, x)?));
+    }
+
+    // Both depend on x: (f^g)' = f^g (g' ln f + g f' / f)
+    let inner = b.binary(
+        BinaryOp::Add,
+        b.binary(BinaryOp::Multiply, differentiate(&g, x)?, b.call("ln", f.clone())),
+        b.binary(BinaryOp::Divide, b.binary(BinaryOp::Multiply, g, differentiate(&f, x)?), f),
+    );
+    Ok(b.binary(BinaryOp::Multiply, expr, inner))
+}
+
+/// The derivative of a function call, using the chain rule: f(u)' = f'(u) u'
+fn call_rule(b: &Builder, name: &str, args: &[Expr], x: &str) -> Result<Expr, CalcError> {
+    // diff(f, y) inside an expression: differentiate f by y first
+    if name == "diff" {
+        let (f, y) = diff_arguments(args, b.span)?;
+        return differentiate(&differentiate(f, y)?, x);
+    }
+
+    if args.len() != 1 {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Cannot differentiate '{}' with {} arguments", name, args.len()),
+            Some(b.span),
+        ));
+    }
+    let u = args[0].clone();
+    let du = differentiate(&u, x)?;
+    let call = |name: &str| b.call(name, u.clone());
+    let square = |expr: Expr| b.binary(BinaryOp::Power, expr, b.number(2.0));
+    // sqrt(1 - u^2), used by asin and acos
+    let root = || b.call("sqrt", b.binary(BinaryOp::Subtract, b.number(1.0), square(u.clone())));
+
+    let outer = match name {
+        "sin" => call("cos"),
+        "cos" => b.negate(call("sin")),
+        "tan" => b.binary(BinaryOp::Divide, b.number(1.0), square(call("cos"))),
+        "asin" => b.binary(BinaryOp::Divide, b.number(1.0), root()),
+        "acos" => b.negate(b.binary(BinaryOp::Divide, b.number(1.0), root())),
+        "atan" => b.binary(BinaryOp::Divide, b.number(1.0), b.binary(BinaryOp::Add, b.number(1.0), square(u.clone()))),
+        "sinh" => call("cosh"),
+        "cosh" => call("sinh"),
+        "tanh" => b.binary(BinaryOp::Divide, b.number(1.0), square(call("cosh"))),
+        "exp" => call("exp"),
+        "ln" => b.binary(BinaryOp::Divide, b.number(1.0), u.clone()),
+        "log" => b.binary(BinaryOp::Divide, b.number(1.0), b.binary(BinaryOp::Multiply, u.clone(), b.call("ln", b.number(10.0)))),
+        "sqrt" => b.binary(BinaryOp::Divide, b.number(1.0), b.binary(BinaryOp::Multiply, b.number(2.0), call("sqrt"))),
+        "cbrt" => b.binary(BinaryOp::Divide, b.number(1.0), b.binary(BinaryOp::Multiply, b.number(3.0), square(call("cbrt")))),
+        "abs" => b.binary(BinaryOp::Divide, u.clone(), call("abs")),
+        _ => {
+            return Err(CalcError::new(
+                ErrorKind::UnknownFunction,
+                format!("Cannot differentiate unknown function '{}'", name),
+                Some(b.span),
+            ))
+        }
+    };
+    Ok(b.binary(BinaryOp::Multiply, outer, du))
+}
+
+/// Check the arguments of `diff(f, x)` (or `diff(f, x, point)`): the function and the variable name
+pub fn diff_arguments(args: &[Expr], span: Span) -> Result<(&Expr, &str), CalcError> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(CalcError::new(
+            ErrorKind::WrongArgumentCount,
+            format!("'diff' takes 2 or 3 arguments but was given {} (like diff(x^2, x) or diff(x^2, x, 3))", args.len()),
+            Some(span),
+        ));
+    }
+    match &args[1].kind {
+        ExprKind::Variable(name) => Ok((&args[0], name)),
+        _ => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            "The second argument of 'diff' must be a variable name, like diff(x^2, x)",
+            Some(args[1].span),
+        )),
+    }
+}
+
+/// How close `integrate` gets to the true answer unless told otherwise
+pub const DEFAULT_TOLERANCE: f64 = 1e-10;
+
+/// How many pieces `integrate` may cut its range into before it gives up
+const MAX_PIECES: usize = 2000;
+
+// The 15-point Gauss-Kronrod rule on -1..1: the nodes (only the positive half, the
+// rule is symmetric) and their weights. Every other node, plus the middle one, also
+// makes up a 7-point Gauss rule, so one set of values gives two answers to compare.
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+/// The result of a numerical integration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integral {
+    pub value: f64,
+    /// Roughly how far `value` may be from the true answer
+    pub error: f64,
+}
+
+/// Integrate `f` from `a` to `b` with adaptive Gauss-Kronrod quadrature
+/// Either bound may be infinite. The piece with the largest error is cut in half until the
+/// total error is within `tolerance` (relative to the answer, once that is bigger than 1)
+pub fn integrate<F>(mut f: F, a: f64, b: f64, tolerance: f64, span: Span) -> Result<Integral, CalcError>
+where
+    F: FnMut(f64) -> Result<f64, CalcError>,
+{
+    if a.is_nan() || b.is_nan() {
+        return Err(CalcError::new(ErrorKind::InvalidArgument, "The bounds of an integral must be numbers", Some(span)));
+    }
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("The tolerance must be more than 0 (got {})", tolerance),
+            Some(span),
+        ));
+    }
+    if a == b {
+        return Ok(Integral { value: 0.0, error: 0.0 });
+    }
+    if a > b {
+        let flipped = integrate(f, b, a, tolerance, span)?;
+        return Ok(Integral { value: -flipped.value, error: flipped.error });
+    }
+
+    // Infinite ranges are squeezed into finite ones: x = a + t / (1 - t) runs from a to inf
+    // as t goes from 0 to 1, and the integrand is scaled by dx/dt to make up for it.
+    // The nodes never touch the ends of the range, so t = 1 is never tried.
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive(&mut f, a, b, tolerance, span),
+        (true, false) => adaptive(&mut |t| Ok(f(a + t / (1.0 - t))? / (1.0 - t).powi(2)), 0.0, 1.0, tolerance, span),
+        (false, true) => adaptive(&mut |t| Ok(f(b - t / (1.0 - t))? / (1.0 - t).powi(2)), 0.0, 1.0, tolerance, span),
+        (false, false) => adaptive(
+            &mut |t| Ok(f(t / (1.0 - t * t))? * (1.0 + t * t) / (1.0 - t * t).powi(2)),
+            -1.0,
+            1.0,
+            tolerance,
+            span,
+        ),
+    }
+}
+
+/// Keep splitting the worst piece of a finite range until the error is small enough
+/// A piece where the function has no value at some node (like 1/sqrt(abs(x)) at 0)
+/// is split first, so the bad point ends up on the edge between pieces, where no node is
+fn adaptive(
+    f: &mut dyn FnMut(f64) -> Result<f64, CalcError>,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+    span: Span,
+) -> Result<Integral, CalcError> {
+    let blows_up = || {
+        CalcError::new(
+            ErrorKind::NoConvergence,
+            "The integral has no finite value (does the function blow up somewhere in the range?)",
+            Some(span),
+        )
+    };
+    let mut pieces = vec![(a, b, kronrod(f, a, b)?)];
+    loop {
+        let value: f64 = pieces.iter().flat_map(|(_, _, piece)| piece.map(|piece| piece.value)).sum();
+        let error: f64 = pieces.iter().flat_map(|(_, _, piece)| piece.map(|piece| piece.error)).sum();
+        let bad = pieces.iter().position(|(_, _, piece)| piece.is_none());
+        if bad.is_none() {
+            if !value.is_finite() || !error.is_finite() {
+                return Err(blows_up());
+            }
+            if error <= tolerance * value.abs().max(1.0) {
+                return Ok(Integral { value, error });
+            }
+        }
+
+        let size = |i: usize| pieces[i].2.map_or(f64::INFINITY, |piece| piece.error);
+        let worst = bad.unwrap_or_else(|| (0..pieces.len()).max_by(|&i, &j| size(i).total_cmp(&size(j))).unwrap_or(0));
+        let (start, end, piece) = pieces[worst];
+        let middle = (start + end) / 2.0;
+        // Out of pieces, or the worst one is too small to cut in half
+        if pieces.len() >= MAX_PIECES || middle <= start || middle >= end {
+            if piece.is_none() {
+                return Err(blows_up());
+            }
+            return Err(CalcError::new(
+                ErrorKind::NoConvergence,
+                format!(
+                    "The integral did not settle down: the best guess is {} but it may be off by {} (the tolerance is {})",
+                    value, error, tolerance
+                ),
+                Some(span),
+            ));
+        }
+        pieces.swap_remove(worst);
+        pieces.push((start, middle, kronrod(f, start, middle)?));
+        pieces.push((middle, end, kronrod(f, middle, end)?));
+    }
+}
+
+/// One 15-point Gauss-Kronrod step; the difference from the 7-point Gauss answer is the error estimate
+/// None if the function has no finite value at one of the nodes (dividing by zero counts too)
+fn kronrod(f: &mut dyn FnMut(f64) -> Result<f64, CalcError>, a: f64, b: f64) -> Result<Option<Integral>, CalcError> {
+    let (center, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    // In a tiny piece a node can round onto an end (like t = 1, where an infinite range
+    // ends up), so such a node is moved just inside
+    let inside = |x: f64| if x <= a { a.next_up() } else if x >= b { b.next_down() } else { x };
+    let mut at = |x: f64| match f(inside(x)) {
+        Ok(value) => Ok(value.is_finite().then_some(value)),
+        Err(error) if matches!(error.kind, ErrorKind::DivisionByZero | ErrorKind::Overflow) => Ok(None),
+        Err(error) => Err(error),
+    };
+    let Some(middle) = at(center)? else {
+        return Ok(None);
+    };
+    let mut kronrod = middle * KRONROD_WEIGHTS[7];
+    let mut gauss = middle * GAUSS_WEIGHTS[3];
+    for i in 0..7 {
+        let offset = half * KRONROD_NODES[i];
+        let (Some(left), Some(right)) = (at(center - offset)?, at(center + offset)?) else {
+            return Ok(None);
+        };
+        let pair = left + right;
+        kronrod += KRONROD_WEIGHTS[i] * pair;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * pair;
+        }
+    }
+    Ok(Some(Integral { value: kronrod * half, error: ((kronrod - gauss) * half).abs() }))
+}
+
+/// Makes new expression nodes that all point at the same part of the input
+struct Builder {
+    span: Span,
+}
+
+impl Builder {
+    fn number(&self, value: f64) -> Expr {
+        Expr::new(ExprKind::Number(value), self.span)
+    }
+
+    fn negate(&self, expr: Expr) -> Expr {
+        Expr::new(ExprKind::Unary(UnaryOp::Negate, Box::new(expr)), self.span)
+    }
+
+    fn binary(&self, op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::new(ExprKind::Binary(op, Box::new(left), Box::new(right)), self.span)
+    }
+
+    fn call(&self, name: &str, arg: Expr) -> Expr {
+        Expr::new(ExprKind::Call(name.to_string(), vec![arg]), self.span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::{Context, Mode};
+    use crate::random::Random;
+    use crate::parser::parse_expression;
+    use crate::value::Value;
+
+    fn derivative_of(input: &str) -> String {
+        derivative(&parse_expression(input).unwrap(), "x").unwrap().to_string()
+    }
+
+    /// Compare the derivative with a numerical estimate (a central difference) at a few points
+    fn assert_matches_slope(input: &str) {
+        let expr = parse_expression(input).unwrap();
+        let slope = derivative(&expr, "x").unwrap();
+        let functions = BTreeMap::new();
+        let at = |expr: &Expr, x: f64| {
+            let variables = BTreeMap::from([("x".to_string(), Value::Number(x))]);
+            Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() }.eval(expr).unwrap()
+        };
+        for x in [0.3, 0.9, 1.7, 2.4] {
+            let h = 1e-6;
+            let estimate = (at(&expr, x + h) - at(&expr, x - h)) / (2.0 * h);
+            let exact = at(&slope, x);
+            assert!((estimate - exact).abs() < 1e-5 * exact.abs().max(1.0), "d/dx {} = {}: {} vs {} at {}", input, slope, exact, estimate, x);
+        }
+    }
+
+    #[test]
+    fn test_basic_rules() {
+        assert_eq!(derivative_of("x^2"), "2 * x");
+        assert_eq!(derivative_of("3 * x^3 - 2 * x + 7"), "9 * x^2 - 2");
+        assert_eq!(derivative_of("y * x"), "y");
+        assert_eq!(derivative_of("5"), "0");
+        assert_eq!(derivative_of("1 / x"), "-(1 / x^2)");
+    }
+
+    #[test]
+    fn test_functions_and_chain_rule() {
+        assert_eq!(derivative_of("sin(x)"), "cos(x)");
+        assert_eq!(derivative_of("cos(2 * x)"), "-(2 * sin(2 * x))");
+        assert_eq!(derivative_of("ln(x)"), "1 / x");
+        assert_eq!(derivative_of("exp(x^2)"), "2 * exp(x^2) * x");
+        assert_eq!(derivative_of("e^x"), "e^x");
+    }
+
+    #[test]
+    fn test_derivatives_match_slopes() {
+        for input in [
+            "x^3 - 4 * x",
+            "sin(x) * x^2",
+            "sin(x) / x",
+            "tan(x^2 + 1)",
+            "exp(sin(x))",
+            "ln(x^2 + 1) * sqrt(x)",
+            "2^x",
+            "x^x",
+            "asin(x / 3) + acos(x / 4) + atan(x)",
+            "sinh(x) * cosh(x) - tanh(x)",
+            "log(x) + cbrt(x) + abs(x - 1)",
+            "diff(x^4, x)",
+        ] {
+            assert_matches_slope(input);
+        }
+    }
+
+    #[test]
+    fn test_user_functions_are_inlined() {
+        let square = Function { params: vec!["a".to_string()], body: parse_expression("a^2").unwrap() };
+        let functions = BTreeMap::from([("sq".to_string(), square)]);
+        let expr = inline_functions(&parse_expression("sq(x + 1)").unwrap(), &functions).unwrap();
+        assert_eq!(expr.to_string(), "(x + 1)^2");
+        assert_eq!(derivative(&expr, "x").unwrap().to_string(), "2 * x + 2");
+    }
+
+    fn integral_of(f: fn(f64) -> f64, a: f64, b: f64) -> Result<Integral, CalcError> {
+        integrate(|x| Ok(f(x)), a, b, DEFAULT_TOLERANCE, Span::new(0, 0))
+    }
+
+    #[test]
+    fn test_integrals() {
+        let integral = integral_of(|x| x * x, 0.0, 3.0).unwrap();
+        assert!((integral.value - 9.0).abs() < 1e-12 && integral.error < 1e-9);
+        assert!((integral_of(f64::sin, 0.0, std::f64::consts::PI).unwrap().value - 2.0).abs() < 1e-10);
+        // Swapping the bounds flips the sign
+        assert!((integral_of(f64::exp, 1.0, 0.0).unwrap().value + (std::f64::consts::E - 1.0)).abs() < 1e-10);
+        // A singularity at an end of the range is fine as long as the area is finite
+        assert!((integral_of(|x| 1.0 / x.sqrt(), 0.0, 1.0).unwrap().value - 2.0).abs() < 1e-8);
+        // So is one in the middle, even right on a node
+        assert!((integral_of(|x| 1.0 / x.abs().sqrt(), -1.0, 1.0).unwrap().value - 4.0).abs() < 1e-8);
+        let divided = |x: f64| match x {
+            0.0 => Err(CalcError::new(ErrorKind::DivisionByZero, "Cannot divide by zero!", None)),
+            _ => Ok(1.0 / x.abs().sqrt()),
+        };
+        assert!((integrate(divided, -1.0, 1.0, DEFAULT_TOLERANCE, Span::new(0, 0)).unwrap().value - 4.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_infinite_ranges() {
+        let inf = f64::INFINITY;
+        assert!((integral_of(|x| 1.0 / (x * x), 1.0, inf).unwrap().value - 1.0).abs() < 1e-10);
+        assert!((integral_of(f64::exp, -inf, 0.0).unwrap().value - 1.0).abs() < 1e-10);
+        let gaussian = integral_of(|x| (-x * x).exp(), -inf, inf).unwrap();
+        assert!((gaussian.value - std::f64::consts::PI.sqrt()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_integrals_that_do_not_converge() {
+        assert_eq!(integral_of(|x| 1.0 / x, 1.0, f64::INFINITY).unwrap_err().kind, ErrorKind::NoConvergence);
+        assert_eq!(integral_of(|x| 1.0 / x, 0.0, 1.0).unwrap_err().kind, ErrorKind::NoConvergence);
+        // The message says what went wrong: no finite value, or an answer that never settles
+        assert!(integral_of(|x| 1.0 / x, -1.0, 1.0).unwrap_err().message.contains("no finite value"));
+        assert!(integral_of(f64::sin, 0.0, f64::INFINITY).unwrap_err().message.contains("did not settle down"));
+        assert_eq!(
+            integrate(Ok, 0.0, 1.0, 0.0, Span::new(0, 0)).unwrap_err().kind,
+            ErrorKind::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = derivative(&parse_expression("f(x)").unwrap(), "x").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownFunction);
+        let error = derivative(&parse_expression("diff(x^2, 2)").unwrap(), "x").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    }
+}
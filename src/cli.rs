@@ -0,0 +1,311 @@
+// src/cli.rs
+// Command-line argument parsing and the generated usage text
+
+use crate::calculator::{Mode, MODE_NAMES};
+use crate::color::{Theme, THEME_NAMES};
+use crate::output::OutputFormat;
+
+/// Largest number of decimal places `--precision` accepts
+pub const MAX_PRECISION: usize = 20;
+
+/// What the user asked for on the command line
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    /// Print the usage text and exit (`--help`)
+    pub help: bool,
+    /// Print the version and exit (`--version`)
+    pub version: bool,
+    /// Skip the welcome banner and goodbye messages (`--quiet`)
+    pub quiet: bool,
+    /// Never use colors (`--no-color`)
+    pub no_color: bool,
+    /// Color theme (`--theme`); None keeps the configured one
+    pub theme: Option<Theme>,
+    /// Numeric mode to start in (`--mode`); None keeps the configured one
+    pub mode: Option<Mode>,
+    /// Decimal places shown in results (`--precision`); None keeps the configured one
+    pub precision: Option<usize>,
+    /// Script to run in batch mode (`--file`)
+    pub file: Option<String>,
+    /// Keep going after errors in batch mode (`--keep-going`)
+    pub keep_going: bool,
+    /// Plain text or JSON results (`--output`); None keeps the configured one
+    pub output: Option<OutputFormat>,
+    /// Expressions to evaluate directly
+    pub expressions: Vec<String>,
+}
+
+/// One command-line flag, as listed in the usage text
+struct Flag {
+    long: &'static str,
+    short: Option<&'static str>,
+    /// Name of the value the flag takes, if any
+    value: Option<&'static str>,
+    description: &'static str,
+}
+
+const FLAGS: [Flag; 11] = [
+    Flag { long: "--help", short: Some("-h"), value: None, description: "Show this help and exit" },
+    Flag { long: "--version", short: Some("-V"), value: None, description: "Show the version and exit" },
+    Flag { long: "--quiet", short: Some("-q"), value: None, description: "Skip the welcome banner and goodbye messages" },
+    Flag { long: "--no-color", short: None, value: None, description: "Disable colored output" },
+    Flag { long: "--theme", short: None, value: Some("THEME"), description: "Colors: default, high-contrast, monochrome" },
+    Flag { long: "--mode", short: None, value: Some("MODE"), description: "Numeric mode: float or integer" },
+    Flag { long: "--precision", short: None, value: Some("N"), description: "Show results with N decimal places" },
+    Flag { long: "--file", short: Some("-f"), value: Some("FILE"), description: "Run the lines of FILE in batch mode" },
+    Flag { long: "--keep-going", short: None, value: None, description: "In batch mode, continue after errors" },
+    Flag { long: "--output", short: Some("-o"), value: Some("FORMAT"), description: "Result format: text or json" },
+    Flag { long: "--", short: None, value: None, description: "Treat everything after this as expressions" },
+];
+
+/// Commands understood at the interactive prompt, as (usage, description)
+pub const REPL_COMMANDS: [(&str, &str); 19] = [
+    ("help", "Show this list of commands"),
+    ("history", "Show past calculations"),
+    ("clear", "Clear the calculation history"),
+    ("explain <expression>", "Show each step of working out an expression"),
+    ("ast <expression>", "Draw how an expression is understood, as a tree"),
+    ("simplify <expression>", "Simplify an expression that may contain unknowns"),
+    ("roots <polynomial>", "Find every root of a polynomial, like roots poly(1, 0, -2)"),
+    ("solve <equation> for <x>", "Find the roots, e.g. solve x^2 = 2 for x (add 'in 0, 5' to search)"),
+    ("plot <f>, <x>, <a>, <b>", "Draw f for x from a to b, e.g. plot sin(x), cos(x), x, -pi, pi"),
+    ("plot ... > <file.svg>", "Save the chart as an SVG picture instead"),
+    ("undo", "Take back the last assignment, definition, setting, clear or load"),
+    ("redo", "Apply the last undone change again"),
+    ("mode [float|integer]", "Show or change the numeric mode"),
+    ("precision [N|off]", "Show or change the decimal places in results"),
+    ("theme [NAME]", "Show or change the color theme"),
+    ("save <file>", "Save variables, functions, settings and history"),
+    ("load <file>", "Load a saved session into this one"),
+    ("load <file.csv> as <name>", "Load a CSV file; columns become <name>.<column>"),
+    ("quit, q", "Leave the calculator"),
+];
+
+/// The version line printed by `--version`
+pub fn version() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// The full `--help` text, built from the flag and command tables
+pub fn usage() -> String {
+    let mut text = String::new();
+    text.push_str(&version());
+    text.push_str("\nA beginner-friendly calculator written in Rust\n\n");
+    text.push_str("Usage:\n");
+    text.push_str("  rust-beginner-toolkit [OPTIONS]                  Start the interactive calculator\n");
+    text.push_str("  rust-beginner-toolkit [OPTIONS] EXPRESSION...    Print the result of each expression\n");
+    text.push_str("  rust-beginner-toolkit [OPTIONS] --file FILE      Evaluate a script, one line at a time\n\n");
+
+    text.push_str("Options:\n");
+    for flag in &FLAGS {
+        let mut names = match flag.short {
+            Some(short) => format!("{}, {}", short, flag.long),
+            None => format!("    {}", flag.long),
+        };
+        if let Some(value) = flag.value {
+            names.push(' ');
+            names.push_str(value);
+        }
+        text.push_str(&format!("  {:<26}{}\n", names, flag.description));
+    }
+
+    text.push_str("\nCommands at the > prompt:\n");
+    text.push_str(&repl_commands_text());
+
+    text.push_str("\nDefaults for these settings can be kept in config.toml\n");
+    text.push_str("(in $XDG_CONFIG_HOME/rust-calc or ~/.config/rust-calc); options given here win.\n");
+    text.push_str("Colors are left out when NO_COLOR is set or the output is not a terminal.\n");
+    text
+}
+
+/// The list of interactive commands, one per line
+pub fn repl_commands_text() -> String {
+    REPL_COMMANDS
+        .iter()
+        .map(|(command, description)| format!("  {:<26}{}\n", command, description))
+        .collect()
+}
+
+/// Does `line` start with one of the interactive commands (rather than an expression)?
+pub fn is_repl_command(line: &str) -> bool {
+    let Some(word) = line.split_whitespace().next() else {
+        return false;
+    };
+    // The names are everything in the usage before its arguments, e.g. "quit, q" or "mode"
+    REPL_COMMANDS.iter().any(|(usage, _)| {
+        let names = usage.split(['[', '<']).next().unwrap_or("");
+        names.split([',', ' ']).any(|name| !name.is_empty() && name.eq_ignore_ascii_case(word))
+    })
+}
+
+/// Turn the command-line arguments (without the program name) into Options
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        // `--name=value` is the same as `--name value`
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        // Fetch the value for flags that need one
+        let mut value = |flag: &str, what: &str| match inline_value.clone().or_else(|| args.next().cloned()) {
+            Some(value) => Ok(value),
+            None => Err(format!("'{}' needs {}", flag, what)),
+        };
+
+        match name {
+            "--help" | "-h" => options.help = true,
+            "--version" | "-V" => options.version = true,
+            "--quiet" | "-q" => options.quiet = true,
+            "--no-color" => options.no_color = true,
+            "--keep-going" => options.keep_going = true,
+            "--mode" => {
+                let modes = format!("a mode ({})", MODE_NAMES.join(", "));
+                options.mode = Some(value("--mode", &modes)?.parse()?);
+            }
+            "--theme" => {
+                let themes = format!("a theme ({})", THEME_NAMES.join(", "));
+                options.theme = Some(value("--theme", &themes)?.parse()?);
+            }
+            "--precision" => options.precision = Some(parse_precision(&value("--precision", "a number of decimal places")?)?),
+            "--file" | "-f" => options.file = Some(value("--file", "a file name")?),
+            "--output" | "-o" => options.output = Some(value("--output", "a format ('text' or 'json')")?.parse()?),
+            "--" => {
+                options.expressions.extend(args.by_ref().cloned());
+            }
+            flag if flag.starts_with("--") => return Err(unknown_flag(flag)),
+            expression => options.expressions.push(expression.to_string()),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Read a number of decimal places
+pub fn parse_precision(text: &str) -> Result<usize, String> {
+    match text.parse::<usize>() {
+        Ok(places) if places <= MAX_PRECISION => Ok(places),
+        _ => Err(format!("precision must be a whole number from 0 to {}, not '{}'", MAX_PRECISION, text)),
+    }
+}
+
+/// Explain an unknown flag, suggesting the closest real one
+fn unknown_flag(flag: &str) -> String {
+    let closest = FLAGS
+        .iter()
+        .filter(|known| known.long != "--")
+        .map(|known| (edit_distance(flag, known.long), known.long))
+        .min();
+
+    match closest {
+        Some((distance, known)) if distance <= 3 => {
+            format!("unknown option '{}' - did you mean '{}'? (see --help)", flag, known)
+        }
+        _ => format!("unknown option '{}' (see --help)", flag),
+    }
+}
+
+/// Number of single-character edits needed to turn `a` into `b`
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_no_arguments() {
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn test_boolean_flags() {
+        let options = parse(&["-h", "--version", "-q", "--no-color", "--keep-going"]).unwrap();
+        assert!(options.help && options.version && options.quiet && options.no_color && options.keep_going);
+    }
+
+    #[test]
+    fn test_flags_with_values() {
+        let options =
+            parse(&["--mode", "integer", "--precision=3", "-f", "script.calc", "-o", "json", "--theme=high-contrast"]).unwrap();
+        assert_eq!(options.mode, Some(Mode::Integer));
+        assert_eq!(options.theme, Some(Theme::HighContrast));
+        assert_eq!(options.precision, Some(3));
+        assert_eq!(options.file.as_deref(), Some("script.calc"));
+        assert_eq!(options.output, Some(OutputFormat::Json));
+    }
+
+    #[test]
+    fn test_expressions_keep_their_order() {
+        let options = parse(&["2 + 3", "-5 + 1", "--", "--7 * 1"]).unwrap();
+        assert_eq!(options.expressions, vec!["2 + 3", "-5 + 1", "--7 * 1"]);
+    }
+
+    #[test]
+    fn test_is_repl_command() {
+        assert!(is_repl_command("quit"));
+        assert!(is_repl_command("Q"));
+        assert!(is_repl_command("mode integer"));
+        assert!(is_repl_command("save work.calc"));
+        assert!(!is_repl_command("quitter + 1"));
+        assert!(!is_repl_command(""));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        assert_eq!(parse(&["--file"]).unwrap_err(), "'--file' needs a file name");
+    }
+
+    #[test]
+    fn test_bad_values() {
+        assert!(parse(&["--mode", "complex"]).is_err());
+        assert!(parse(&["--precision", "-1"]).is_err());
+        assert!(parse(&["--precision", "99"]).is_err());
+        assert!(parse(&["--output", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_flag_suggestion() {
+        assert_eq!(parse(&["--verison"]).unwrap_err(), "unknown option '--verison' - did you mean '--version'? (see --help)");
+        assert_eq!(parse(&["--frobnicate"]).unwrap_err(), "unknown option '--frobnicate' (see --help)");
+    }
+
+    #[test]
+    fn test_usage_lists_flags_and_commands() {
+        let text = usage();
+        for flag in &FLAGS {
+            assert!(text.contains(flag.long), "usage is missing {}", flag.long);
+        }
+        for (command, _) in &REPL_COMMANDS {
+            assert!(text.contains(command), "usage is missing {}", command);
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("--mode", "--mode"), 0);
+        assert_eq!(edit_distance("--mod", "--mode"), 1);
+        assert_eq!(edit_distance("--ouptut", "--output"), 2);
+    }
+}
@@ -0,0 +1,282 @@
+// src/color.rs
+// ANSI colors for the interactive calculator, with a few themes to pick from
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::lexer::{tokenize, TokenKind};
+
+/// Names accepted by `--theme`, the `theme` command and config.toml
+pub const THEME_NAMES: [&str; 3] = ["default", "high-contrast", "monochrome"];
+
+/// A set of colors for the different parts of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// Soft colors that suit most terminals
+    #[default]
+    Default,
+    /// Bright, bold colors that are easier to tell apart
+    HighContrast,
+    /// No colors at all, only bold and underline
+    Monochrome,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "default" => Ok(Theme::Default),
+            "high-contrast" => Ok(Theme::HighContrast),
+            "monochrome" => Ok(Theme::Monochrome),
+            _ => Err(format!("unknown theme '{}' (expected {})", name, THEME_NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Theme::Default => write!(f, "default"),
+            Theme::HighContrast => write!(f, "high-contrast"),
+            Theme::Monochrome => write!(f, "monochrome"),
+        }
+    }
+}
+
+/// What a piece of text is, which decides its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Prompt,
+    Result,
+    Error,
+    /// Messages about settings and commands
+    Info,
+    Number,
+    Operator,
+    Variable,
+    Function,
+    Bracket,
+    /// Characters (or names) the calculator does not understand
+    Unknown,
+    /// The bracket pair around the cursor while typing
+    Match,
+}
+
+impl Theme {
+    /// The ANSI style code (the part between "\x1b[" and "m") for a role
+    fn code(self, role: Role) -> &'static str {
+        match (self, role) {
+            (Theme::Default, Role::Prompt) => "36",
+            (Theme::Default, Role::Result) => "32",
+            (Theme::Default, Role::Error) => "31",
+            (Theme::Default, Role::Info) => "33",
+            (Theme::Default, Role::Number) => "36",
+            (Theme::Default, Role::Operator) => "35",
+            (Theme::Default, Role::Variable) => "34",
+            (Theme::Default, Role::Function) => "33",
+            (Theme::Default, Role::Bracket) => "37",
+            (Theme::Default, Role::Unknown) => "31",
+            (Theme::Default, Role::Match) => "1;7",
+
+            (Theme::HighContrast, Role::Prompt) => "1;96",
+            (Theme::HighContrast, Role::Result) => "1;92",
+            (Theme::HighContrast, Role::Error) => "1;91",
+            (Theme::HighContrast, Role::Info) => "1;93",
+            (Theme::HighContrast, Role::Number) => "1;96",
+            (Theme::HighContrast, Role::Operator) => "1;95",
+            (Theme::HighContrast, Role::Variable) => "1;94",
+            (Theme::HighContrast, Role::Function) => "1;93",
+            (Theme::HighContrast, Role::Bracket) => "1;97",
+            (Theme::HighContrast, Role::Unknown) => "1;97;41",
+            (Theme::HighContrast, Role::Match) => "1;30;103",
+
+            (Theme::Monochrome, Role::Result | Role::Prompt | Role::Function) => "1",
+            (Theme::Monochrome, Role::Error | Role::Unknown) => "1;4",
+            (Theme::Monochrome, Role::Match) => "7",
+            (Theme::Monochrome, _) => "0",
+        }
+    }
+}
+
+impl Theme {
+    /// The ANSI style code for the `index`th line of a plot; the colors repeat after six
+    fn series_code(self, index: usize) -> &'static str {
+        let codes = match self {
+            Theme::Default => ["34", "31", "32", "35", "33", "36"],
+            Theme::HighContrast => ["1;94", "1;91", "1;92", "1;95", "1;93", "1;96"],
+            // Without colors the lines can only be told apart by their labels
+            Theme::Monochrome => ["0"; 6],
+        };
+        codes[index % codes.len()]
+    }
+}
+
+/// Decide whether to use colors at all
+/// The config (or `--no-color`) can turn them off, and so can the NO_COLOR
+/// environment variable (https://no-color.org) or output that is not a terminal
+pub fn color_enabled(configured: bool, no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+    let no_color_set = no_color.is_some_and(|value| !value.is_empty());
+    configured && !no_color_set && is_terminal
+}
+
+/// How many columns `text` takes on screen, not counting color codes
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            // Color codes end with 'm'
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => {}
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+/// Paints text in a theme's colors, or leaves it alone when colors are off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Palette {
+    pub theme: Theme,
+    pub enabled: bool,
+}
+
+impl Palette {
+    pub fn new(theme: Theme, enabled: bool) -> Self {
+        Palette { theme, enabled }
+    }
+
+    /// A palette that never adds colors
+    pub fn plain() -> Self {
+        Palette::new(Theme::Default, false)
+    }
+
+    /// Wrap `text` in the color for `role`
+    pub fn paint(&self, role: Role, text: &str) -> String {
+        let code = self.theme.code(role);
+        if !self.enabled || code == "0" || text.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    /// Wrap `text` in the color for the `index`th line of a plot
+    pub fn paint_series(&self, index: usize, text: &str) -> String {
+        let code = self.theme.series_code(index);
+        if !self.enabled || code == "0" || text.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    /// Color an expression token by token, keeping its spacing exactly as typed
+    pub fn highlight(&self, input: &str) -> String {
+        self.highlight_with(input, |_| true, &[])
+    }
+
+    /// Like `highlight`, but names for which `is_known` is false are shown as
+    /// unknown, and brackets starting at the byte offsets in `matched` stand out
+    pub fn highlight_with(&self, input: &str, is_known: impl Fn(&str) -> bool, matched: &[usize]) -> String {
+        let tokens = tokenize(input);
+
+        // Names before '=' are being defined right now (`x = 1`, `f(a) = a`), so they count as known
+        let equals = tokens.iter().position(|token| token.kind == TokenKind::Equals).unwrap_or(0);
+        let defining: Vec<&str> = tokens[..equals]
+            .iter()
+            .filter_map(|token| match &token.kind {
+                TokenKind::Ident(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        let mut highlighted = String::new();
+        let mut position = 0;
+
+        for (index, token) in tokens.iter().enumerate() {
+            highlighted.push_str(&input[position..token.span.start]);
+            let role = match &token.kind {
+                TokenKind::Number(_) => Role::Number,
+                TokenKind::Ident(name) if !defining.contains(&name.as_str()) && !is_known(name) => Role::Unknown,
+                // A name followed by "(" is a function call
+                TokenKind::Ident(_) if tokens.get(index + 1).is_some_and(|next| next.kind == TokenKind::LParen) => {
+                    Role::Function
+                }
+                TokenKind::Ident(_) => Role::Variable,
+                TokenKind::LParen | TokenKind::RParen | TokenKind::LBracket | TokenKind::RBracket
+                    if matched.contains(&token.span.start) =>
+                {
+                    Role::Match
+                }
+                TokenKind::LParen | TokenKind::RParen | TokenKind::LBracket | TokenKind::RBracket => Role::Bracket,
+                TokenKind::Unknown(_) => Role::Unknown,
+                _ => Role::Operator,
+            };
+            highlighted.push_str(&self.paint(role, &input[token.span.start..token.span.end]));
+            position = token.span.end;
+        }
+
+        highlighted.push_str(&input[position..]);
+        highlighted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_names_round_trip() {
+        for name in THEME_NAMES {
+            assert_eq!(name.parse::<Theme>().unwrap().to_string(), name);
+        }
+        assert!("neon".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn test_color_enabled() {
+        assert!(color_enabled(true, None, true));
+        assert!(!color_enabled(false, None, true));
+        assert!(!color_enabled(true, None, false));
+        assert!(!color_enabled(true, Some(OsStr::new("1")), true));
+        // An empty NO_COLOR does not count as set
+        assert!(color_enabled(true, Some(OsStr::new("")), true));
+    }
+
+    #[test]
+    fn test_paint() {
+        let palette = Palette::new(Theme::Default, true);
+        assert_eq!(palette.paint(Role::Result, "42"), "\x1b[32m42\x1b[0m");
+        assert_eq!(Palette::plain().paint(Role::Result, "42"), "42");
+        assert_eq!(Palette::new(Theme::Monochrome, true).paint(Role::Error, "oops"), "\x1b[1;4moops\x1b[0m");
+    }
+
+    #[test]
+    fn test_highlight_keeps_spacing() {
+        let palette = Palette::new(Theme::Default, true);
+        assert_eq!(
+            palette.highlight("f(x)  + 2"),
+            "\x1b[33mf\x1b[0m\x1b[37m(\x1b[0m\x1b[34mx\x1b[0m\x1b[37m)\x1b[0m  \x1b[35m+\x1b[0m \x1b[36m2\x1b[0m"
+        );
+        assert_eq!(Palette::plain().highlight(" 5 % 3 "), " 5 % 3 ");
+    }
+
+    #[test]
+    fn test_unknown_names_and_matched_brackets() {
+        let palette = Palette::new(Theme::Default, true);
+        let known = |name: &str| name == "x";
+        assert_eq!(palette.highlight_with("x+y", known, &[]), "\x1b[34mx\x1b[0m\x1b[35m+\x1b[0m\x1b[31my\x1b[0m");
+        // Names being defined are not unknown
+        assert!(!palette.highlight_with("f(a) = a", known, &[]).contains("\x1b[31m"));
+        assert_eq!(palette.highlight_with("(x)", known, &[0]), "\x1b[1;7m(\x1b[0m\x1b[34mx\x1b[0m\x1b[37m)\x1b[0m");
+    }
+
+    #[test]
+    fn test_visible_width() {
+        let palette = Palette::new(Theme::HighContrast, true);
+        assert_eq!(visible_width(&palette.paint(Role::Prompt, "calc> ")), 6);
+        assert_eq!(visible_width("é> "), 3);
+    }
+}
@@ -0,0 +1,386 @@
+// src/config.rs
+// User preferences read from config.toml, so every session can start the way you like
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::calculator::Mode;
+use crate::cli::{self, Options};
+use crate::color::{self, Palette, Theme};
+use crate::output::OutputFormat;
+use crate::session::Session;
+
+/// Name of the folder (inside the config directory) that holds our files
+pub const APP_DIR: &str = "rust-calc";
+
+/// Every key config.toml understands
+pub const KNOWN_KEYS: [&str; 8] = [
+    "mode", "precision", "output", "prompt", "color", "theme", "history_size", "personality",
+];
+
+/// How chatty Rusty the calculator bot is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Personality {
+    /// Emojis, cheering and jokes (the classic Rusty)
+    #[default]
+    Cheerful,
+    /// Just the answers, politely
+    Calm,
+}
+
+impl FromStr for Personality {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "cheerful" => Ok(Personality::Cheerful),
+            "calm" => Ok(Personality::Calm),
+            _ => Err(format!("unknown personality '{}' (expected 'cheerful' or 'calm')", name)),
+        }
+    }
+}
+
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Personality::Cheerful => write!(f, "cheerful"),
+            Personality::Calm => write!(f, "calm"),
+        }
+    }
+}
+
+/// Default settings for new sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Numeric mode to start in
+    pub mode: Mode,
+    /// Decimal places shown in results (None shows the full value)
+    pub precision: Option<usize>,
+    /// Result format for argument and batch mode
+    pub output: OutputFormat,
+    /// Prompt shown by the interactive calculator
+    pub prompt: String,
+    /// Whether colored output is allowed
+    pub color: bool,
+    /// Colors used when they are allowed
+    pub theme: Theme,
+    /// How many calculations the history keeps (0 keeps everything)
+    pub history_size: usize,
+    /// How the calculator bot talks
+    pub personality: Personality,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::Float,
+            precision: None,
+            output: OutputFormat::Text,
+            prompt: "> ".to_string(),
+            color: true,
+            theme: Theme::Default,
+            history_size: 1000,
+            personality: Personality::Cheerful,
+        }
+    }
+}
+
+/// A value on the right-hand side of `key = value`
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "a number",
+            Value::Bool(_) => "true or false",
+        }
+    }
+}
+
+/// Where config.toml lives: $XDG_CONFIG_HOME/rust-calc, or ~/.config/rust-calc
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join(APP_DIR));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join(APP_DIR))
+}
+
+/// Full path of config.toml
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Full path of init.calc, the startup script run before the REPL
+pub fn init_script_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("init.calc"))
+}
+
+impl Config {
+    /// Load config.toml from the config directory
+    /// A missing file simply gives the defaults; problems in the file become warnings
+    pub fn load() -> (Config, Vec<String>) {
+        let Some(path) = config_path() else {
+            return (Config::default(), Vec::new());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let (config, warnings) = Config::parse(&text);
+                let name = path.display();
+                (config, warnings.into_iter().map(|warning| format!("{}: {}", name, warning)).collect())
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (Config::default(), Vec::new()),
+            Err(error) => (Config::default(), vec![format!("could not read {}: {}", path.display(), error)]),
+        }
+    }
+
+    /// Let options given on the command line win over the file
+    pub fn override_with(&mut self, options: &Options) {
+        if let Some(mode) = options.mode {
+            self.mode = mode;
+        }
+        if options.precision.is_some() {
+            self.precision = options.precision;
+        }
+        if let Some(output) = options.output {
+            self.output = output;
+        }
+        if options.no_color {
+            self.color = false;
+        }
+        if let Some(theme) = options.theme {
+            self.theme = theme;
+        }
+    }
+
+    /// The colors to use when writing to a terminal (`is_terminal`) or not
+    /// NO_COLOR in the environment turns them off too
+    pub fn palette(&self, is_terminal: bool) -> Palette {
+        let enabled = color::color_enabled(self.color, env::var_os("NO_COLOR").as_deref(), is_terminal);
+        Palette::new(self.theme, enabled)
+    }
+
+    /// A fresh session using these settings
+    pub fn new_session(&self) -> Session {
+        let mut session = Session::new();
+        session.mode = self.mode;
+        session.precision = self.precision;
+        session.history_size = self.history_size;
+        session
+    }
+
+    /// Read settings from the text of a config file
+    /// Unknown keys and bad values are skipped with a "line N: ..." warning
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut warnings = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            // Skip blank lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                warnings.push(format!("line {}: sections are not supported, '{}' ignored", line_number, line));
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected 'key = value'", line_number));
+                continue;
+            };
+
+            let key = key.trim();
+            let result = parse_value(value.trim()).and_then(|value| config.set(key, value));
+            if let Err(error) = result {
+                warnings.push(format!("line {}: {}", line_number, error));
+            }
+        }
+
+        (config, warnings)
+    }
+
+    /// Apply one setting, checking its type and value
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match (key, value) {
+            ("mode", Value::String(name)) => self.mode = name.parse()?,
+            ("precision", Value::Integer(places)) => {
+                self.precision = Some(cli::parse_precision(&places.to_string())?);
+            }
+            ("precision", Value::String(text)) if text == "off" => self.precision = None,
+            ("output", Value::String(name)) => self.output = name.parse()?,
+            ("prompt", Value::String(prompt)) => self.prompt = prompt,
+            ("color", Value::Bool(enabled)) => self.color = enabled,
+            ("theme", Value::String(name)) => self.theme = name.parse()?,
+            ("history_size", Value::Integer(size)) => {
+                self.history_size = usize::try_from(size).map_err(|_| "history_size cannot be negative".to_string())?;
+            }
+            ("personality", Value::String(name)) => self.personality = name.parse()?,
+            (key, value) if KNOWN_KEYS.contains(&key) => {
+                return Err(format!("'{}' cannot be {}", key, value.type_name()));
+            }
+            (key, _) => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+}
+
+/// Explain an unknown key, suggesting the closest real one
+fn unknown_key(key: &str) -> String {
+    let closest = KNOWN_KEYS.iter().map(|known| (cli::edit_distance(key, known), *known)).min();
+    match closest {
+        Some((distance, known)) if distance <= 2 => format!("unknown key '{}' ignored - did you mean '{}'?", key, known),
+        _ => format!("unknown key '{}' ignored", key),
+    }
+}
+
+/// Read a TOML value: a quoted string, a whole number, or true/false
+/// Anything after the value may only be a `# comment`
+fn parse_value(text: &str) -> Result<Value, String> {
+    let (value, rest) = if let Some(quoted) = text.strip_prefix('"') {
+        parse_basic_string(quoted)?
+    } else if let Some(quoted) = text.strip_prefix('\'') {
+        // Literal strings have no escapes at all
+        let end = quoted.find('\'').ok_or("string is missing its closing quote")?;
+        (Value::String(quoted[..end].to_string()), &quoted[end + 1..])
+    } else {
+        let end = text.find('#').unwrap_or(text.len());
+        let word = text[..end].trim();
+        let value = match word {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::Integer(word.replace('_', "").parse().map_err(|_| format!("cannot understand value '{}'", word))?),
+        };
+        (value, &text[end..])
+    };
+
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected '{}' after the value", rest));
+    }
+    Ok(value)
+}
+
+/// Read a "double-quoted" string (after the opening quote), handling escapes
+/// Returns the string and whatever follows the closing quote
+fn parse_basic_string(text: &str) -> Result<(Value, &str), String> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((Value::String(value), &text[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, '"')) => value.push('"'),
+                Some((_, '\\')) => value.push('\\'),
+                Some((_, other)) => return Err(format!("unknown escape '\\{}' in string", other)),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    Err("string is missing its closing quote".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_file_gives_defaults() {
+        let (config, warnings) = Config::parse("");
+        assert_eq!(config, Config::default());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_every_key() {
+        let text = r#"
+# My calculator settings
+mode = "integer"
+precision = 4          # decimal places
+output = 'json'
+prompt = "calc> "
+color = false
+theme = "monochrome"
+history_size = 50
+personality = "calm"
+"#;
+        let (config, warnings) = Config::parse(text);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.mode, Mode::Integer);
+        assert_eq!(config.precision, Some(4));
+        assert_eq!(config.output, OutputFormat::Json);
+        assert_eq!(config.prompt, "calc> ");
+        assert!(!config.color);
+        assert_eq!(config.theme, Theme::Monochrome);
+        assert_eq!(config.history_size, 50);
+        assert_eq!(config.personality, Personality::Calm);
+    }
+
+    #[test]
+    fn test_unknown_key_warns_with_suggestion() {
+        let (config, warnings) = Config::parse("colour = false\nprecision = 2\n");
+        assert_eq!(warnings, vec!["line 1: unknown key 'colour' ignored - did you mean 'color'?"]);
+        assert!(config.color);
+        assert_eq!(config.precision, Some(2));
+    }
+
+    #[test]
+    fn test_bad_values_warn_and_keep_defaults() {
+        let (config, warnings) = Config::parse("mode = \"complex\"\ncolor = \"yes\"\nhistory_size = -1\nprompt\n");
+        assert_eq!(config, Config::default());
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].starts_with("line 1: unknown mode 'complex'"));
+        assert_eq!(warnings[1], "line 2: 'color' cannot be a string");
+        assert_eq!(warnings[3], "line 4: expected 'key = value'");
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        let (mut config, _) = Config::parse("mode = \"integer\"\nprecision = 2\ncolor = true\n");
+        let options = cli::parse_args(&["--precision".to_string(), "5".to_string(), "--no-color".to_string()]).unwrap();
+        config.override_with(&options);
+        assert_eq!(config.mode, Mode::Integer);
+        assert_eq!(config.precision, Some(5));
+        assert!(!config.color);
+        assert!(!config.palette(true).enabled);
+
+        let session = config.new_session();
+        assert_eq!(session.mode, Mode::Integer);
+        assert_eq!(session.precision, Some(5));
+        assert_eq!(session.history_size, 1000);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(parse_value(r#""a \"b\"\n" # note"#), Ok(Value::String("a \"b\"\n".to_string())));
+        assert_eq!(parse_value(r#"'C:\calc'"#), Ok(Value::String("C:\\calc".to_string())));
+        assert!(parse_value("\"open").is_err());
+        assert!(parse_value("\"done\" extra").is_err());
+    }
+
+    #[test]
+    fn test_numbers_and_booleans() {
+        assert_eq!(parse_value("1_000"), Ok(Value::Integer(1000)));
+        assert_eq!(parse_value("true # yes"), Ok(Value::Bool(true)));
+        assert!(parse_value("maybe").is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// src/csv.rs
+// Reading tables of numbers from CSV files, for `load data.csv as d`
+
+use std::fmt;
+
+/// One column of numbers and the name it goes by
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// The numeric columns of a CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    /// Columns of text, which have no numbers to calculate with
+    pub skipped: Vec<String>,
+    /// How many rows of data there were (not counting the header)
+    pub rows: usize,
+}
+
+/// A problem in a CSV file, at a row and column counted from 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvError {
+    pub row: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl CsvError {
+    fn new(row: usize, column: usize, message: impl Into<String>) -> Self {
+        CsvError { row, column, message: message.into() }
+    }
+}
+
+// "row 3, column 2: 'abc' is not a number"
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}, column {}: {}", self.row, self.column, self.message)
+    }
+}
+
+/// What `load <file> as <name> [delimiter <d>]` asks for
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableLoad {
+    pub path: String,
+    pub name: String,
+    pub delimiter: char,
+}
+
+/// Read the argument of a `load` command that loads a table, like
+/// "data.csv as d" or "data.txt as d delimiter ;"
+/// Returns None for a plain `load <file>`, which loads a saved session instead
+pub fn parse_load(argument: &str) -> Option<Result<TableLoad, String>> {
+    let (rest, delimiter) = match argument.rsplit_once(" delimiter ") {
+        Some((rest, delimiter)) => (rest, Some(delimiter.trim())),
+        None => (argument, None),
+    };
+    let (path, name) = rest.rsplit_once(" as ")?;
+    let (path, name) = (path.trim(), name.trim());
+    Some(table_load(path, name, delimiter))
+}
+
+fn table_load(path: &str, name: &str, delimiter: Option<&str>) -> Result<TableLoad, String> {
+    if path.is_empty() {
+        return Err("load needs a file name, e.g. 'load data.csv as d'".to_string());
+    }
+    if path.contains("://") {
+        return Err(format!("only files on this computer can be loaded, not '{}'", path));
+    }
+    let mut chars = name.chars();
+    if !chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') || !chars.all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!("'{}' cannot be used as a name (use letters, digits and _)", name));
+    }
+
+    // Tab-separated files get tabs unless something else is asked for
+    let delimiter = match delimiter {
+        Some(delimiter) => parse_delimiter(delimiter)?,
+        None if path.to_lowercase().ends_with(".tsv") => '\t',
+        None => ',',
+    };
+    Ok(TableLoad { path: path.to_string(), name: name.to_string(), delimiter })
+}
+
+/// "tab", "space", or a single character, optionally in quotes: ; ';' "|"
+fn parse_delimiter(text: &str) -> Result<char, String> {
+    let unquoted = ['"', '\''].iter().find_map(|quote| text.strip_prefix(*quote)?.strip_suffix(*quote)).unwrap_or(text);
+    let mut chars = unquoted.chars();
+    match (text.to_lowercase().as_str(), chars.next(), chars.next()) {
+        ("tab" | "\\t", _, _) => Ok('\t'),
+        ("space", _, _) => Ok(' '),
+        (_, Some(c), None) if c != '"' && c != '\n' && c != '\r' => Ok(c),
+        _ => Err(format!("'{}' cannot be used as a delimiter (use one character, or 'tab')", text)),
+    }
+}
+
+/// Read a CSV file: fields are split at `delimiter`, and may be "quoted" to hold
+/// the delimiter, line breaks or "" (a quote). The first row is taken as a header
+/// if it has any text in it; otherwise columns are called c1, c2, ...
+/// Columns of text are skipped, but text in a column of numbers is an error
+pub fn parse(text: &str, delimiter: char) -> Result<Table, CsvError> {
+    let records = records(text, delimiter)?;
+    let Some(first) = records.first() else {
+        return Err(CsvError::new(1, 1, "the file has no rows"));
+    };
+    let width = first.len();
+    for (index, record) in records.iter().enumerate() {
+        if record.len() != width {
+            let message = format!("expected {} fields like the first row, but found {}", width, record.len());
+            return Err(CsvError::new(index + 1, record.len().min(width) + 1, message));
+        }
+    }
+
+    let header = first.iter().any(|field| number(field).is_none());
+    let names = if header {
+        let mut names: Vec<String> = Vec::new();
+        for (index, field) in first.iter().enumerate() {
+            let name = column_name(field, index);
+            if names.contains(&name) {
+                return Err(CsvError::new(1, index + 1, format!("the column name '{}' is used twice", name)));
+            }
+            names.push(name);
+        }
+        names
+    } else {
+        (1..=width).map(|index| format!("c{}", index)).collect()
+    };
+
+    let data = &records[usize::from(header)..];
+    let mut table = Table { columns: Vec::new(), skipped: Vec::new(), rows: data.len() };
+    for (col, name) in names.into_iter().enumerate() {
+        // A column is text if its first value is
+        if data.first().is_some_and(|record| number(&record[col]).is_none() && !record[col].trim().is_empty()) {
+            table.skipped.push(name);
+            continue;
+        }
+        let mut values = Vec::with_capacity(data.len());
+        for (index, record) in data.iter().enumerate() {
+            let field = record[col].trim();
+            let Some(value) = number(field) else {
+                let row = index + 1 + usize::from(header);
+                let problem = if field.is_empty() { "this cell is empty".to_string() } else { format!("'{}' is not a number", field) };
+                return Err(CsvError::new(row, col + 1, format!("{} (column '{}')", problem, name)));
+            };
+            values.push(value);
+        }
+        table.columns.push(Column { name, values });
+    }
+    Ok(table)
+}
+
+/// A field as a number, if it is one
+fn number(field: &str) -> Option<f64> {
+    field.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+/// Turn a header like "Unit Price" into a name the calculator can use: Unit_Price
+fn column_name(field: &str, index: usize) -> String {
+    let name: String = field.trim().chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    match name.chars().next() {
+        None => format!("c{}", index + 1),
+        Some(c) if c.is_ascii_digit() => format!("_{}", name),
+        Some(_) => name,
+    }
+}
+
+/// Split CSV text into rows of fields, handling quotes; blank lines are skipped
+fn records(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    // Whether the current field was quoted, and whether we are still inside the quotes
+    let (mut quoted, mut in_quotes) = (false, false);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let (row, column) = (records.len() + 1, record.len() + 1);
+        if in_quotes {
+            match c {
+                // "" inside quotes is one quote
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.trim().is_empty() && !quoted => {
+                field.clear();
+                quoted = true;
+                in_quotes = true;
+            }
+            c if c == delimiter => {
+                record.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].trim().is_empty() && !quoted) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+                quoted = false;
+            }
+            c if quoted && !c.is_whitespace() => {
+                return Err(CsvError::new(row, column, format!("unexpected '{}' after a closing quote", c)));
+            }
+            _ if quoted => {}
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(CsvError::new(records.len() + 1, record.len() + 1, "this quoted field is never closed"));
+    }
+    if !record.is_empty() || !field.trim().is_empty() || quoted {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column<'t>(table: &'t Table, name: &str) -> &'t [f64] {
+        &table.columns.iter().find(|column| column.name == name).unwrap().values
+    }
+
+    #[test]
+    fn test_header_and_columns() {
+        let table = parse("item,price,Unit Count\nnuts,1.5,10\nbolts,2,20\n", ',').unwrap();
+        assert_eq!(table.rows, 2);
+        assert_eq!(column(&table, "price"), [1.5, 2.0]);
+        assert_eq!(column(&table, "Unit_Count"), [10.0, 20.0]);
+        assert_eq!(table.skipped, ["item"]);
+
+        // All numbers: there is no header
+        let table = parse("1;2\r\n3;4\r\n\r\n", ';').unwrap();
+        assert_eq!((column(&table, "c1"), column(&table, "c2")), (&[1.0, 3.0][..], &[2.0, 4.0][..]));
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let records = records("\"a, b\",\"say \"\"hi\"\"\"\n\"two\nlines\", 3", ',').unwrap();
+        assert_eq!(records, [vec!["a, b", "say \"hi\""], vec!["two\nlines", " 3"]]);
+        let table = parse("name,\"total, $\"\n\"x\",\"1.5\"", ',').unwrap();
+        assert_eq!((column(&table, "total___"), table.skipped.as_slice()), (&[1.5][..], &["name".to_string()][..]));
+    }
+
+    #[test]
+    fn test_errors_have_row_and_column() {
+        assert_eq!(parse("a,b\n1,2\n3,x\n", ',').unwrap_err(), CsvError::new(3, 2, "'x' is not a number (column 'b')"));
+        assert_eq!(parse("a,b\n1,2\n3\n", ',').unwrap_err().to_string(), "row 3, column 2: expected 2 fields like the first row, but found 1");
+        assert_eq!(parse("a,b\n1,\n", ',').unwrap_err(), CsvError::new(2, 2, "this cell is empty (column 'b')"));
+        assert_eq!(parse("a,b\n\"1,2\n", ',').unwrap_err(), CsvError::new(2, 1, "this quoted field is never closed"));
+        assert_eq!(parse("a,b\n\"1\"x,2\n", ',').unwrap_err(), CsvError::new(2, 1, "unexpected 'x' after a closing quote"));
+        assert_eq!(parse("a,a\n1,2\n", ',').unwrap_err(), CsvError::new(1, 2, "the column name 'a' is used twice"));
+        assert_eq!(parse("\n", ',').unwrap_err().message, "the file has no rows");
+    }
+
+    #[test]
+    fn test_parse_load() {
+        let load = |argument| parse_load(argument).unwrap();
+        assert_eq!(load("data.csv as d"), Ok(TableLoad { path: "data.csv".into(), name: "d".into(), delimiter: ',' }));
+        assert_eq!(load("my data.tsv as d").unwrap().delimiter, '\t');
+        assert_eq!(load("data.txt as d delimiter ';'").unwrap().delimiter, ';');
+        assert_eq!(load("data.txt as d delimiter tab").unwrap().delimiter, '\t');
+        assert!(load("data.txt as d delimiter ab").is_err());
+        assert!(load("https://example.com/data.csv as d").is_err());
+        assert!(load("data.csv as 2d").is_err());
+        assert_eq!(parse_load("work.calc"), None);
+    }
+}
@@ -0,0 +1,482 @@
+// src/distributions.rs
+// Probability distributions: chances (pdf), running totals of chances (cdf)
+// and going back from a chance to a value (inverse cdf)
+//
+// For a few whole numbers the cdf just adds up their chances, which keeps simple
+// answers like binomcdf(0, 4, 0.5) = 0.0625 exact. Past that it is built on the
+// regularized incomplete gamma and beta functions, worked out with their series
+// and continued fractions, which stay fast and accurate however far out we go
+
+use std::f64::consts::{PI, SQRT_2};
+
+use crate::number_theory;
+
+/// Up to this many chances are added up one by one in a cdf
+const MAX_TERMS: f64 = 1000.0;
+
+/// One of the distributions the calculator knows, with its parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Successes in `trials` tries that each succeed with chance `p`
+    Binomial { trials: u64, p: f64 },
+    /// Events in a stretch of time where `rate` are expected
+    Poisson { rate: f64 },
+    /// The bell curve around `mean`
+    Normal { mean: f64, sd: f64 },
+    /// Every value from `low` to `high` equally likely
+    Uniform { low: f64, high: f64 },
+    /// The wait until the next event, with `rate` events per unit of time
+    Exponential { rate: f64 },
+}
+
+impl Distribution {
+    /// A binomial distribution; `trials` must be whole and `p` from 0 to 1
+    pub fn binomial(trials: f64, p: f64) -> Result<Self, String> {
+        if trials.fract() != 0.0 || !(0.0..=9_007_199_254_740_991.0).contains(&trials) {
+            return Err(format!("the number of trials must be a whole number of at least 0, not {}", trials));
+        }
+        if !(0.0..=1.0).contains(&p) {
+            return Err(format!("the chance of success must be from 0 to 1, not {}", p));
+        }
+        Ok(Distribution::Binomial { trials: trials as u64, p })
+    }
+
+    /// A Poisson distribution; the rate must be above 0
+    pub fn poisson(rate: f64) -> Result<Self, String> {
+        positive("rate", rate)?;
+        Ok(Distribution::Poisson { rate })
+    }
+
+    /// A normal distribution; the standard deviation must be above 0
+    pub fn normal(mean: f64, sd: f64) -> Result<Self, String> {
+        if !mean.is_finite() {
+            return Err(format!("the mean must be a finite number, not {}", mean));
+        }
+        positive("standard deviation", sd)?;
+        Ok(Distribution::Normal { mean, sd })
+    }
+
+    /// A uniform distribution from `low` to `high`, which must be above `low`
+    pub fn uniform(low: f64, high: f64) -> Result<Self, String> {
+        if !(low.is_finite() && high.is_finite() && low < high) {
+            return Err(format!("the range must go from a smaller to a larger number, not {} to {}", low, high));
+        }
+        Ok(Distribution::Uniform { low, high })
+    }
+
+    /// An exponential distribution; the rate must be above 0
+    pub fn exponential(rate: f64) -> Result<Self, String> {
+        positive("rate", rate)?;
+        Ok(Distribution::Exponential { rate })
+    }
+
+    /// The chance of exactly `x` (binomial, Poisson) or the density at `x` (the others)
+    pub fn pdf(&self, x: f64) -> f64 {
+        match *self {
+            Distribution::Binomial { trials, p } => {
+                if x.fract() != 0.0 || x < 0.0 || x > trials as f64 {
+                    return 0.0;
+                }
+                let (k, n) = (x, trials as f64);
+                if p == 0.0 || p == 1.0 {
+                    return f64::from((p == 0.0 && k == 0.0) || (p == 1.0 && k == n));
+                }
+                // Exactly when the number of ways fits, otherwise with logarithms
+                match number_theory::binomial(trials, k as u64).filter(|ways| *ways < 1 << 53) {
+                    Some(ways) => ways as f64 * p.powf(k) * (1.0 - p).powf(n - k),
+                    None => (ln_choose(n, k) + k * p.ln() + (n - k) * (-p).ln_1p()).exp(),
+                }
+            }
+            Distribution::Poisson { rate } => {
+                if x.fract() != 0.0 || x < 0.0 {
+                    return 0.0;
+                }
+                // k! is exact up to 20!
+                if x <= 20.0 && rate < 700.0 {
+                    let factorial: u64 = (1..=x as u64).product();
+                    rate.powf(x) * (-rate).exp() / factorial as f64
+                } else {
+                    (x * rate.ln() - rate - ln_gamma(x + 1.0)).exp()
+                }
+            }
+            Distribution::Normal { mean, sd } => {
+                let z = (x - mean) / sd;
+                (-0.5 * z * z).exp() / (sd * (2.0 * PI).sqrt())
+            }
+            Distribution::Uniform { low, high } => {
+                if (low..=high).contains(&x) {
+                    1.0 / (high - low)
+                } else {
+                    0.0
+                }
+            }
+            Distribution::Exponential { rate } => {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    rate * (-rate * x).exp()
+                }
+            }
+        }
+    }
+
+    /// The chance of a value of `x` or less
+    pub fn cdf(&self, x: f64) -> f64 {
+        match *self {
+            Distribution::Binomial { trials, p } => {
+                let k = x.floor();
+                if k < 0.0 {
+                    0.0
+                } else if k >= trials as f64 {
+                    1.0
+                } else if p == 0.0 || p == 1.0 {
+                    f64::from(p == 0.0)
+                } else if k < MAX_TERMS {
+                    self.sum_up_to(k)
+                } else {
+                    regularized_beta(trials as f64 - k, k + 1.0, 1.0 - p)
+                }
+            }
+            Distribution::Poisson { rate } => {
+                let k = x.floor();
+                if k < 0.0 {
+                    0.0
+                } else if k < MAX_TERMS {
+                    self.sum_up_to(k).min(1.0)
+                } else {
+                    regularized_gamma(k + 1.0, rate).1
+                }
+            }
+            Distribution::Normal { mean, sd } => 0.5 * erfc(-(x - mean) / (sd * SQRT_2)),
+            Distribution::Uniform { low, high } => ((x - low) / (high - low)).clamp(0.0, 1.0),
+            Distribution::Exponential { rate } => {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    -(-rate * x).exp_m1()
+                }
+            }
+        }
+    }
+
+    /// The chances of 0, 1, ..., k added up
+    fn sum_up_to(&self, k: f64) -> f64 {
+        (0..=k as u64).map(|i| self.pdf(i as f64)).sum()
+    }
+
+    /// The value with a chance `q` of being at or below it; for binomial and Poisson,
+    /// the smallest whole number whose cdf reaches `q`. None unless q is from 0 to 1
+    pub fn inverse(&self, q: f64) -> Option<f64> {
+        if !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        Some(match *self {
+            Distribution::Binomial { trials, .. } => self.smallest_reaching(q, trials as f64),
+            Distribution::Poisson { rate } => {
+                if q == 1.0 {
+                    return Some(f64::INFINITY);
+                }
+                // Double until past q, then search in between
+                let mut high = rate.ceil().max(1.0);
+                while self.cdf(high) < q {
+                    high *= 2.0;
+                }
+                self.smallest_reaching(q, high)
+            }
+            Distribution::Normal { mean, sd } => mean + sd * inverse_normal(q),
+            Distribution::Uniform { low, high } => low + q * (high - low),
+            Distribution::Exponential { rate } => -(-q).ln_1p() / rate,
+        })
+    }
+
+    /// The smallest whole number from 0 to `high` with a cdf of at least `q`, by halving
+    fn smallest_reaching(&self, q: f64, high: f64) -> f64 {
+        let (mut low, mut high) = (0.0, high);
+        while low < high {
+            let middle = ((low + high) / 2.0_f64).floor();
+            if self.cdf(middle) >= q {
+                high = middle;
+            } else {
+                low = middle + 1.0;
+            }
+        }
+        low
+    }
+}
+
+/// A parameter that must be a finite number above 0
+fn positive(what: &str, value: f64) -> Result<(), String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("the {} must be above 0, not {}", what, value))
+    }
+}
+
+/// ln(Γ(x)) for x > 0, with the Lanczos approximation (about 15 correct digits)
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection: Γ(x) Γ(1 - x) = π / sin(πx)
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let series = COEFFICIENTS[1..].iter().enumerate().fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// Stop a series or continued fraction once a step changes it by less than this
+const TOLERANCE: f64 = 1e-16;
+
+/// Give up on a series or continued fraction after this many steps
+const MAX_STEPS: usize = 1_000_000;
+
+/// Stands in for 0 in continued fractions, so nothing is divided by 0
+const TINY: f64 = 1e-300;
+
+/// The regularized incomplete gamma functions (P(a, x), Q(a, x)), which add up to 1
+/// Both are returned so a tail close to 0 does not lose its digits in 1 - P
+pub fn regularized_gamma(a: f64, x: f64) -> (f64, f64) {
+    if a.is_nan() || x.is_nan() {
+        return (f64::NAN, f64::NAN);
+    }
+    if x <= 0.0 {
+        return (0.0, 1.0);
+    }
+    // inf - inf below would be NaN; the whole chance is below infinity
+    if x == f64::INFINITY {
+        return (1.0, 0.0);
+    }
+    let front = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // The series converges quickly here
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..MAX_STEPS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * TOLERANCE {
+                break;
+            }
+        }
+        // Rounding can take it just past 1; clamp (unlike min) lets a NaN through
+        let p = (sum * front).clamp(0.0, 1.0);
+        (p, 1.0 - p)
+    } else {
+        // The continued fraction, by Lentz's method
+        let mut b = x + 1.0 - a;
+        let (mut c, mut d) = (1.0 / TINY, 1.0 / b);
+        let mut fraction = d;
+        for n in 1..MAX_STEPS {
+            let n = n as f64;
+            let an = -n * (n - a);
+            b += 2.0;
+            d = an * d + b;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = b + an / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            let change = d * c;
+            fraction *= change;
+            if (change - 1.0).abs() < TOLERANCE {
+                break;
+            }
+        }
+        let q = (front * fraction).clamp(0.0, 1.0);
+        (1.0 - q, q)
+    }
+}
+
+/// The regularized incomplete beta function I_x(a, b), for x from 0 to 1
+pub fn regularized_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (-x).ln_1p()).exp();
+    // The continued fraction converges quickly on this side; use symmetry for the other
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// The continued fraction for the incomplete beta function, by Lentz's method
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let nonzero = |value: f64| if value.abs() < TINY { TINY } else { value };
+    let mut c = 1.0;
+    let mut d = 1.0 / nonzero(1.0 - (a + b) * x / (a + 1.0));
+    let mut fraction = d;
+    for m in 1..MAX_STEPS {
+        let m = m as f64;
+        // The even step, then the odd one
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / nonzero(1.0 + even * d);
+        c = nonzero(1.0 + even / c);
+        fraction *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / nonzero(1.0 + odd * d);
+        c = nonzero(1.0 + odd / c);
+        let change = d * c;
+        fraction *= change;
+        if (change - 1.0).abs() < TOLERANCE {
+            break;
+        }
+    }
+    fraction
+}
+
+/// ln of "n choose k" for numbers too large to work out exactly
+fn ln_choose(n: f64, k: f64) -> f64 {
+    ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0)
+}
+
+/// The complementary error function, 1 - erf(x), accurate even when it is tiny
+pub fn erfc(x: f64) -> f64 {
+    // x * x is inf once x passes about 1e154, which regularized_gamma takes as the limit
+    let (p, q) = regularized_gamma(0.5, x * x);
+    if x >= 0.0 { q } else { 1.0 + p }
+}
+
+/// The z with a chance `p` of a standard normal value below it
+/// Acklam's approximation gets about 9 digits; one Halley step on the cdf gets the rest
+fn inverse_normal(p: f64) -> f64 {
+    const A: [f64; 6] = [-39.696_830_286_653_76, 220.946_098_424_520_5, -275.928_510_446_968_7, 138.357_751_867_269, -30.664_798_066_147_16, 2.506_628_277_459_239];
+    const B: [f64; 5] = [-54.476_098_798_224_06, 161.585_836_858_040_9, -155.698_979_859_886_6, 66.801_311_887_719_72, -13.280_681_552_885_72];
+    const C: [f64; 6] = [-0.007_784_894_002_430_293, -0.322_396_458_041_136_5, -2.400_758_277_161_838, -2.549_732_539_343_734, 4.374_664_141_464_968, 2.938_163_982_698_783];
+    const D: [f64; 4] = [0.007_784_695_709_041_462, 0.322_467_129_070_039_8, 2.445_134_137_142_996, 3.754_408_661_907_416];
+    const LOW: f64 = 0.024_25;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    // Polynomials with their leading coefficient first
+    let poly = |coefficients: &[f64], x: f64| coefficients.iter().fold(0.0, |sum, c| sum * x + c);
+    let tail = |p: f64| {
+        let q = (-2.0 * p.ln()).sqrt();
+        poly(&C, q) / (poly(&D, q) * q + 1.0)
+    };
+    let z = if p < LOW {
+        tail(p)
+    } else if p > 1.0 - LOW {
+        -tail(1.0 - p)
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        poly(&A, r) * q / (poly(&B, r) * r + 1.0)
+    };
+
+    let error = 0.5 * erfc(-z / SQRT_2) - p;
+    let u = error * (2.0 * PI).sqrt() * (z * z / 2.0).exp();
+    z - u / (1.0 + z * u / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= 1e-12 * expected.abs().max(1.0), "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(10.0), 362_880.0_f64.ln());
+        assert_close(ln_gamma(0.5), PI.sqrt().ln());
+        assert_close(erfc(0.0), 1.0);
+        assert_close(erfc(1.0), 0.157_299_207_050_285_13);
+        assert_close(erfc(-1.0), 1.842_700_792_949_715);
+        // Far in the tail there is still a full set of digits
+        assert!((erfc(10.0) / 2.088_487_583_762_545e-45 - 1.0).abs() < 1e-12);
+        assert_close(regularized_beta(2.0, 3.0, 0.4), 0.5248);
+        // The limits, even where x * x overflows, and NaN stays NaN
+        assert_eq!((erfc(f64::INFINITY), erfc(f64::NEG_INFINITY)), (0.0, 2.0));
+        assert_eq!((erfc(1e200), erfc(-1e200)), (0.0, 2.0));
+        assert!(erfc(f64::NAN).is_nan());
+        assert_eq!(regularized_gamma(3.0, f64::INFINITY), (1.0, 0.0));
+        assert!(regularized_gamma(3.0, f64::NAN).0.is_nan());
+    }
+
+    #[test]
+    fn test_binomial() {
+        let coin = Distribution::binomial(4.0, 0.5).unwrap();
+        assert_eq!(coin.pdf(2.0), 0.375);
+        assert_eq!(coin.pdf(2.5), 0.0);
+        assert_close(coin.cdf(2.0), 0.6875);
+        assert_eq!((coin.cdf(-1.0), coin.cdf(4.0)), (0.0, 1.0));
+        assert_eq!(coin.inverse(0.6875), Some(2.0));
+        assert_eq!(coin.inverse(0.7), Some(3.0));
+        assert_eq!(coin.inverse(0.0), Some(0.0));
+        // Too many ways to count exactly
+        assert_close(Distribution::binomial(1000.0, 0.5).unwrap().pdf(500.0), 0.025_225_018_178_360_8);
+        // Adding up chances and the incomplete beta function agree where one takes over from the other
+        let big = Distribution::binomial(3000.0, 0.4).unwrap();
+        assert_close(big.cdf(999.0), regularized_beta(2001.0, 1000.0, 0.6));
+        assert_close(big.cdf(1000.0) - big.cdf(999.0), big.pdf(1000.0));
+        assert!(Distribution::binomial(2.5, 0.5).is_err());
+        assert!(Distribution::binomial(4.0, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_poisson() {
+        let poisson = Distribution::poisson(3.0).unwrap();
+        assert_close(poisson.pdf(2.0), 4.5 * (-3.0_f64).exp());
+        assert_close(poisson.cdf(2.0), 8.5 * (-3.0_f64).exp());
+        assert_eq!(poisson.inverse(0.5), Some(3.0));
+        assert_eq!(poisson.inverse(1.0), Some(f64::INFINITY));
+        assert_close(Distribution::poisson(1000.0).unwrap().pdf(1000.0), 0.012_614_611_348_721_5);
+        let busy = Distribution::poisson(1000.0).unwrap();
+        assert_close(busy.cdf(1000.0) - busy.cdf(999.0), busy.pdf(1000.0));
+        assert!(Distribution::poisson(0.0).is_err());
+    }
+
+    #[test]
+    fn test_normal() {
+        let standard = Distribution::normal(0.0, 1.0).unwrap();
+        assert_close(standard.pdf(0.0), 1.0 / (2.0 * PI).sqrt());
+        assert_eq!(standard.cdf(0.0), 0.5);
+        assert_close(standard.cdf(1.96), 0.975_002_104_851_780);
+        assert_eq!((standard.cdf(f64::INFINITY), standard.cdf(f64::NEG_INFINITY)), (1.0, 0.0));
+        assert_eq!((standard.cdf(1e200), standard.cdf(-1e200)), (1.0, 0.0));
+        assert!(standard.cdf(f64::NAN).is_nan());
+        assert_close(standard.inverse(0.975).unwrap(), 1.959_963_984_540_054);
+        assert_close(standard.inverse(1e-10).unwrap(), -6.361_340_902_404_056);
+        assert_eq!(standard.inverse(0.5), Some(0.0));
+        assert_eq!(standard.inverse(1.5), None);
+        let scores = Distribution::normal(100.0, 15.0).unwrap();
+        assert_close(scores.cdf(115.0), standard.cdf(1.0));
+        assert_close(scores.inverse(scores.cdf(130.0)).unwrap(), 130.0);
+        assert!(Distribution::normal(0.0, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_uniform_and_exponential() {
+        let uniform = Distribution::uniform(2.0, 6.0).unwrap();
+        assert_eq!((uniform.pdf(3.0), uniform.pdf(7.0)), (0.25, 0.0));
+        assert_eq!((uniform.cdf(3.0), uniform.cdf(10.0)), (0.25, 1.0));
+        assert_eq!(uniform.inverse(0.5), Some(4.0));
+        assert!(Distribution::uniform(6.0, 2.0).is_err());
+
+        let exponential = Distribution::exponential(2.0).unwrap();
+        assert_eq!(exponential.pdf(0.0), 2.0);
+        assert_close(exponential.cdf(1.0), 1.0 - (-2.0_f64).exp());
+        assert_close(exponential.inverse(0.5).unwrap(), 2.0_f64.ln() / 2.0);
+        assert_eq!(exponential.cdf(-1.0), 0.0);
+    }
+}
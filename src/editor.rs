@@ -0,0 +1,406 @@
+// src/editor.rs
+// A small line editor for the interactive calculator: the expression is
+// colored while you type, and the bracket matching the one at the cursor lights up
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::color::visible_width;
+use crate::input;
+use crate::lexer::{tokenize, TokenKind};
+
+/// One key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    /// Ctrl+C: give up on the expression being typed
+    Interrupt,
+    /// Ctrl+D: end of input when the line is empty
+    EndOfInput,
+    /// Anything else (arrow up/down, function keys, ...), which is ignored
+    Other,
+}
+
+/// Read the next key press from a terminal in raw mode
+/// Returns Ok(None) when the input has ended
+pub fn read_key<R: Read>(reader: &mut R) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(reader)? else {
+        return Ok(None);
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        3 => Key::Interrupt,
+        4 => Key::EndOfInput,
+        // Ctrl+A and Ctrl+E, like in most shells
+        1 => Key::Home,
+        5 => Key::End,
+        0x1b => read_escape(reader)?,
+        byte if byte < 0x20 => Key::Other,
+        byte if byte < 0x80 => Key::Char(byte as char),
+        first => {
+            // A character longer than one byte: the first byte says how many follow
+            let length = match first {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            let mut bytes = vec![first];
+            for _ in 1..length {
+                bytes.extend(read_byte(reader)?);
+            }
+            match std::str::from_utf8(&bytes).ok().and_then(|text| text.chars().next()) {
+                Some(c) => Key::Char(c),
+                None => Key::Other,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+/// Read one byte, or None at the end of the input
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match reader.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+/// Decode the rest of an escape sequence such as "\x1b[D" (arrow left)
+fn read_escape<R: Read>(reader: &mut R) -> io::Result<Key> {
+    if read_byte(reader)? != Some(b'[') {
+        return Ok(Key::Other);
+    }
+
+    // Parameters (digits and ';') come first, then one final letter or '~'
+    let mut sequence = String::new();
+    while let Some(byte) = read_byte(reader)? {
+        sequence.push(byte as char);
+        if (0x40..=0x7e).contains(&byte) {
+            break;
+        }
+    }
+
+    Ok(match sequence.as_str() {
+        "C" => Key::Right,
+        "D" => Key::Left,
+        "H" | "1~" => Key::Home,
+        "F" | "4~" => Key::End,
+        "3~" => Key::Delete,
+        _ => Key::Other,
+    })
+}
+
+/// The text being typed and where the cursor is in it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LineBuffer {
+    pub text: String,
+    /// Byte offset of the cursor, always on a character boundary
+    pub cursor: usize,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Remove the character before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Remove the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+}
+
+/// Find the bracket that pairs with the one under the cursor (or just before it)
+/// Returns the byte offsets of both brackets, or None when the cursor is not
+/// next to a bracket or the bracket has no partner yet
+pub fn matching_bracket(text: &str, cursor: usize) -> Option<(usize, usize)> {
+    // Use the same tokens the parser sees, so highlighting and parsing never disagree
+    let tokens = tokenize(text);
+    let pair = |kind: &TokenKind| match kind {
+        TokenKind::LParen | TokenKind::RParen => Some((TokenKind::LParen, TokenKind::RParen)),
+        TokenKind::LBracket | TokenKind::RBracket => Some((TokenKind::LBracket, TokenKind::RBracket)),
+        _ => None,
+    };
+    let index = tokens
+        .iter()
+        .position(|token| token.span.start == cursor && pair(&token.kind).is_some())
+        .or_else(|| tokens.iter().position(|token| token.span.end == cursor && pair(&token.kind).is_some()))?;
+    let (open, close) = pair(&tokens[index].kind)?;
+
+    // Only brackets of the same kind count: "(" pairs with ")" and "[" with "]"
+    let mut depth = 0;
+    if tokens[index].kind == open {
+        for token in &tokens[index..] {
+            if token.kind == open {
+                depth += 1;
+            } else if token.kind == close {
+                depth -= 1;
+            }
+            if depth == 0 {
+                return Some((tokens[index].span.start, token.span.start));
+            }
+        }
+    } else {
+        for token in tokens[..=index].iter().rev() {
+            if token.kind == close {
+                depth += 1;
+            } else if token.kind == open {
+                depth -= 1;
+            }
+            if depth == 0 {
+                return Some((tokens[index].span.start, token.span.start));
+            }
+        }
+    }
+    None
+}
+
+/// Colors a line; given the text and the byte offsets of brackets to emphasize
+pub type Highlighter<'a> = dyn Fn(&str, &[usize]) -> String + 'a;
+
+/// Read one line key by key, redrawing it colored after every key press
+/// Ctrl+C gives an `Interrupted` error; the end of input gives Ok(None)
+pub fn read_line<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    prompt: &str,
+    highlight: &Highlighter,
+) -> io::Result<Option<String>> {
+    let mut line = LineBuffer::new();
+    redraw(writer, prompt, &line, highlight, true)?;
+
+    loop {
+        let Some(key) = read_key(reader)? else {
+            writeln!(writer)?;
+            return Ok(if line.text.is_empty() { None } else { Some(line.text) });
+        };
+
+        match key {
+            Key::Char(c) => line.insert(c),
+            Key::Backspace => line.backspace(),
+            Key::Delete => line.delete(),
+            Key::Left => line.left(),
+            Key::Right => line.right(),
+            Key::Home => line.cursor = 0,
+            Key::End => line.cursor = line.text.len(),
+            Key::EndOfInput if line.text.is_empty() => {
+                writeln!(writer)?;
+                return Ok(None);
+            }
+            Key::EndOfInput => line.delete(),
+            Key::Interrupt => {
+                writeln!(writer, "^C")?;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "input cancelled"));
+            }
+            Key::Enter => {
+                // Leave the finished line on screen without the bracket highlight
+                redraw(writer, prompt, &line, highlight, false)?;
+                writeln!(writer)?;
+                return Ok(Some(line.text));
+            }
+            Key::Other => {}
+        }
+        redraw(writer, prompt, &line, highlight, true)?;
+    }
+}
+
+/// Draw the prompt and the colored line, then put the cursor back in place
+fn redraw<W: Write>(writer: &mut W, prompt: &str, line: &LineBuffer, highlight: &Highlighter, brackets: bool) -> io::Result<()> {
+    let matched = match matching_bracket(&line.text, line.cursor).filter(|_| brackets) {
+        Some((bracket, partner)) => vec![bracket, partner],
+        None => Vec::new(),
+    };
+
+    // "\r" goes back to the start of the line and "\x1b[K" clears what was left of the old one
+    write!(writer, "\r{}{}\x1b[K\r", prompt, highlight(&line.text, &matched))?;
+    let column = visible_width(prompt) + line.text[..line.cursor].chars().count();
+    if column > 0 {
+        write!(writer, "\x1b[{}C", column)?;
+    }
+    writer.flush()
+}
+
+/// Read one complete (possibly multi-line) expression with live highlighting,
+/// continuing lines the same way as `input::read_expression`
+pub fn read_expression<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    prompt: &str,
+    continuation_prompt: &str,
+    highlight: &Highlighter,
+) -> io::Result<Option<String>> {
+    input::collect_expression(prompt, continuation_prompt, |current_prompt| {
+        read_line(reader, writer, current_prompt, highlight)
+    })
+}
+
+/// While this is alive the terminal sends every key straight to us without echoing it
+/// Dropping it puts the terminal back the way it was
+pub struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    /// Switch the terminal (on stdin) to key-by-key input using `stty`
+    pub fn enable() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// How many columns wide the terminal is, asked from `stty size` ("rows columns")
+pub fn terminal_width() -> Option<usize> {
+    let size = stty(&["size"]).ok()?;
+    size.split_whitespace().nth(1)?.parse().ok().filter(|width| *width > 0)
+}
+
+/// Run `stty` on our terminal and return what it printed
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+        let mut reader = bytes;
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut reader).unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    /// A highlighter that marks emphasized brackets with [ ] so tests can see them
+    fn brackets(text: &str, matched: &[usize]) -> String {
+        text.char_indices().map(|(index, c)| if matched.contains(&index) { format!("[{}]", c) } else { c.to_string() }).collect()
+    }
+
+    fn type_line(bytes: &[u8]) -> (io::Result<Option<String>>, String) {
+        let mut reader = bytes;
+        let mut output = Vec::new();
+        let line = read_line(&mut reader, &mut output, "> ", &brackets);
+        (line, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_read_keys() {
+        assert_eq!(
+            keys(b"a\x1b[D\x1b[3~\x7f\r\x03"),
+            vec![Key::Char('a'), Key::Left, Key::Delete, Key::Backspace, Key::Enter, Key::Interrupt]
+        );
+        assert_eq!(keys("π".as_bytes()), vec![Key::Char('π')]);
+        assert_eq!(keys(b"\x1b[A"), vec![Key::Other]);
+    }
+
+    #[test]
+    fn test_line_buffer_editing() {
+        let mut line = LineBuffer::new();
+        for c in "2+é".chars() {
+            line.insert(c);
+        }
+        line.left();
+        line.backspace();
+        line.insert('*');
+        assert_eq!(line.text, "2*é");
+        line.right();
+        line.right();
+        assert_eq!(line.cursor, line.text.len());
+        line.cursor = 0;
+        line.delete();
+        assert_eq!(line.text, "*é");
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let text = "(1 + (2 * 3))";
+        assert_eq!(matching_bracket(text, 0), Some((0, 12)));
+        assert_eq!(matching_bracket(text, 5), Some((5, 11)));
+        assert_eq!(matching_bracket(text, 11), Some((11, 5)));
+        // Just after a closing bracket counts too
+        assert_eq!(matching_bracket(text, 13), Some((12, 0)));
+        assert_eq!(matching_bracket(text, 2), None);
+        assert_eq!(matching_bracket("(1 + 2", 0), None);
+        // Square brackets pair with each other, skipping round ones
+        assert_eq!(matching_bracket("[f(1), [2]]", 0), Some((0, 10)));
+        assert_eq!(matching_bracket("[f(1), [2]]", 7), Some((7, 9)));
+    }
+
+    #[test]
+    fn test_read_line_edits_and_highlights() {
+        // Type "2+3)", jump home, add the missing "(", jump to the end and submit
+        let (line, output) = type_line(b"2+3)\x1b[H(\x1b[F\r");
+        assert_eq!(line.unwrap(), Some("(2+3)".to_string()));
+        // While the cursor sat next to a bracket, both brackets were emphasized
+        assert!(output.contains("[(]2+3[)]"));
+        // The final redraw has no emphasis
+        assert!(output.ends_with("> (2+3)\x1b[K\r\x1b[7C\n"));
+    }
+
+    #[test]
+    fn test_read_line_interrupt_and_end() {
+        let (line, output) = type_line(b"12\x03");
+        assert_eq!(line.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(output.ends_with("^C\n"));
+
+        assert_eq!(type_line(b"\x04").0.unwrap(), None);
+        assert_eq!(type_line(b"").0.unwrap(), None);
+        assert_eq!(type_line(b"7").0.unwrap(), Some("7".to_string()));
+    }
+
+    #[test]
+    fn test_read_expression_continues_lines() {
+        let mut reader: &[u8] = b"(1 +\r2)\r";
+        let mut output = Vec::new();
+        let expression = read_expression(&mut reader, &mut output, "> ", "... ", &brackets).unwrap();
+        assert_eq!(expression, Some("(1 + 2)".to_string()));
+    }
+}
@@ -0,0 +1,94 @@
+// src/error.rs
+// Errors the calculator can report, with enough detail for tools to act on them
+
+use std::fmt;
+
+use crate::matrix::Shape;
+
+/// A range of byte offsets into the input, `start..end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+/// What sort of problem happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input does not have the shape of an expression
+    Syntax,
+    /// A number could not be read
+    InvalidNumber,
+    /// An operator the calculator does not know
+    UnknownOperator,
+    /// Division by zero
+    DivisionByZero,
+    /// A result too large for the current mode
+    Overflow,
+    /// A name that has no value yet
+    UnknownVariable,
+    /// A function that has not been defined
+    UnknownFunction,
+    /// A function called with too many or too few arguments
+    WrongArgumentCount,
+    /// Functions calling each other without end
+    RecursionLimit,
+    /// A command was given something it cannot use
+    InvalidArgument,
+    /// A numerical method could not reach the accuracy it was asked for
+    NoConvergence,
+    /// A value of the wrong kind, like a polynomial where a number is needed
+    TypeMismatch,
+    /// Vectors or matrices whose sizes do not fit together, with both shapes
+    ShapeMismatch(Shape, Shape),
+}
+
+impl ErrorKind {
+    /// Stable snake_case name, used in machine-readable output
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::InvalidNumber => "invalid_number",
+            ErrorKind::UnknownOperator => "unknown_operator",
+            ErrorKind::DivisionByZero => "division_by_zero",
+            ErrorKind::Overflow => "overflow",
+            ErrorKind::UnknownVariable => "unknown_variable",
+            ErrorKind::UnknownFunction => "unknown_function",
+            ErrorKind::WrongArgumentCount => "wrong_argument_count",
+            ErrorKind::RecursionLimit => "recursion_limit",
+            ErrorKind::InvalidArgument => "invalid_argument",
+            ErrorKind::NoConvergence => "no_convergence",
+            ErrorKind::TypeMismatch => "type_mismatch",
+            ErrorKind::ShapeMismatch(..) => "shape_mismatch",
+        }
+    }
+}
+
+/// An error with its kind, a friendly message and where in the input it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl CalcError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>, span: Option<Span>) -> Self {
+        CalcError { kind, message: message.into(), span }
+    }
+}
+
+// Printing an error shows just the friendly message
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CalcError {}
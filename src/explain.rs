@@ -0,0 +1,108 @@
+// src/explain.rs
+// "Show your work": the steps of a calculation in the order they are worked out
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::calculator::{Context, Mode};
+use crate::error::{CalcError, Span};
+use crate::parser::parse_expression;
+use crate::random::Random;
+use crate::value::Value;
+
+/// One step of a calculation, like `4 - 1 = 3`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// What was worked out, with its operands already replaced by their values (e.g. "4 - 1")
+    /// Variables are looked up in a step of their own, shown as just their name
+    pub calculation: String,
+    pub result: Value,
+    /// The part of the input this step covers
+    /// (inside a function, the part of the function's body)
+    pub span: Span,
+    /// 0 for the expression itself, 1 inside a function it calls, and so on
+    pub depth: usize,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.calculation, self.result)
+    }
+}
+
+/// Every step of a calculation, and its final result
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub steps: Vec<Step>,
+    pub result: Value,
+}
+
+// One numbered step per line, indented inside function calls
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.steps.is_empty() {
+            return writeln!(f, "Nothing to work out: the answer is {}", self.result);
+        }
+        for (number, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}{}. {}", "   ".repeat(step.depth), number + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Calculate `expression` in float mode, keeping every step
+pub fn explain(expression: &str) -> Result<Explanation, CalcError> {
+    let expr = parse_expression(expression)?;
+    let (variables, functions) = (BTreeMap::new(), BTreeMap::new());
+    Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() }.explain(&expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::session::Session;
+
+    fn steps(explanation: &Explanation) -> Vec<String> {
+        explanation.steps.iter().map(|step| step.to_string()).collect()
+    }
+
+    #[test]
+    fn test_steps_follow_evaluation_order() {
+        let explanation = explain("2 + 3 * (4 - 1)").unwrap();
+        assert_eq!(steps(&explanation), vec!["4 - 1 = 3", "3 * 3 = 9", "2 + 9 = 11"]);
+        assert_eq!(explanation.result, Value::Number(11.0));
+        // The first step covers "(4 - 1)", brackets included
+        assert_eq!(explanation.steps[0].span, Span::new(8, 15));
+        assert_eq!(explanation.to_string(), "1. 4 - 1 = 3\n2. 3 * 3 = 9\n3. 2 + 9 = 11\n");
+    }
+
+    #[test]
+    fn test_negative_numbers() {
+        assert_eq!(steps(&explain("-2 ^ 2").unwrap()), vec!["2 ^ 2 = 4", "-(4) = -4"]);
+        assert_eq!(steps(&explain("5 * -(1 + 2)").unwrap()), vec!["1 + 2 = 3", "-(3) = -3", "5 * (-3) = -15"]);
+    }
+
+    #[test]
+    fn test_a_number_needs_no_steps() {
+        let explanation = explain("42").unwrap();
+        assert!(explanation.steps.is_empty());
+        assert_eq!(explanation.to_string(), "Nothing to work out: the answer is 42\n");
+    }
+
+    #[test]
+    fn test_variables_and_functions() {
+        let mut session = Session::new();
+        session.execute("x = 3").unwrap();
+        session.execute("sq(a) = a * a").unwrap();
+        let explanation = session.explain("sq(x + 1)").unwrap();
+        assert_eq!(steps(&explanation), vec!["x = 3", "3 + 1 = 4", "a = 4", "a = 4", "4 * 4 = 16", "sq(4) = 16"]);
+        let depths: Vec<usize> = explanation.steps.iter().map(|step| step.depth).collect();
+        assert_eq!(depths, vec![0, 0, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_errors_stop_the_explanation() {
+        assert_eq!(explain("1 / (2 - 2)").unwrap_err().kind, ErrorKind::DivisionByZero);
+    }
+}
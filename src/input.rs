@@ -0,0 +1,183 @@
+// src/input.rs
+// Reading (possibly multi-line) expressions from the user
+
+use std::io::{self, BufRead, Write};
+
+/// Prompt shown while an expression continues onto the next line
+pub const CONTINUATION_PROMPT: &str = "... ";
+
+/// Operators (and the comma between items) that cannot end a complete expression
+const TRAILING_OPERATORS: [char; 7] = ['+', '-', '*', '/', '^', '=', ','];
+
+/// Check whether `buffer` is an unfinished expression
+/// An expression continues when it ends with an operator or a backslash,
+/// or when it has more '(' than ')' or more '[' than ']', so a long list can be pasted over several lines
+pub fn needs_continuation(buffer: &str) -> bool {
+    let trimmed = buffer.trim_end();
+
+    if trimmed.ends_with('\\') {
+        return true;
+    }
+
+    if trimmed.ends_with(TRAILING_OPERATORS) {
+        return true;
+    }
+
+    paren_depth(trimmed) > 0
+}
+
+/// Count how many '(' and '[' are still waiting for their ')' or ']'
+fn paren_depth(text: &str) -> i32 {
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Read one complete expression, showing `prompt` for the first line and
+/// `continuation_prompt` for every following line
+/// Returns Ok(None) when the input ends before anything was typed
+pub fn read_expression<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    prompt: &str,
+    continuation_prompt: &str,
+) -> io::Result<Option<String>> {
+    collect_expression(prompt, continuation_prompt, |current_prompt| {
+        // Show the prompt and make sure it appears before we block on input
+        write!(writer, "{}", current_prompt)?;
+        writer.flush()?;
+
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    })
+}
+
+/// Join lines into one complete expression
+/// `next_line` shows the prompt it is given and returns the line typed,
+/// or None when the input has ended
+pub fn collect_expression<F>(prompt: &str, continuation_prompt: &str, mut next_line: F) -> io::Result<Option<String>>
+where
+    F: FnMut(&str) -> io::Result<Option<String>>,
+{
+    let mut expression = String::new();
+    let mut current_prompt = prompt;
+
+    loop {
+        let Some(line) = next_line(current_prompt)? else {
+            // End of input: hand back whatever we collected so far
+            if expression.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(expression));
+        };
+
+        let line = line.trim();
+
+        // A blank line while continuing submits what we have so far
+        if line.is_empty() && !expression.is_empty() {
+            return Ok(Some(expression));
+        }
+
+        // A trailing backslash is only a "keep going" marker, not part of the expression
+        let piece = line.strip_suffix('\\').unwrap_or(line).trim_end();
+        if !expression.is_empty() && !piece.is_empty() {
+            expression.push(' ');
+        }
+        expression.push_str(piece);
+
+        // Parentheses are counted over the whole expression, not just this line
+        if !line.ends_with('\\') && !needs_continuation(&expression) {
+            return Ok(Some(expression));
+        }
+
+        current_prompt = continuation_prompt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(text: &str) -> (Option<String>, String) {
+        let mut reader = text.as_bytes();
+        let mut output = Vec::new();
+        let expression = read_expression(&mut reader, &mut output, "> ", CONTINUATION_PROMPT).unwrap();
+        (expression, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_complete_line_needs_no_continuation() {
+        assert!(!needs_continuation("5 + 3"));
+        assert!(!needs_continuation("(2 + 3) * 4"));
+        assert!(!needs_continuation("quit"));
+    }
+
+    #[test]
+    fn test_trailing_operator_continues() {
+        assert!(needs_continuation("5 +"));
+        assert!(needs_continuation("10 / "));
+        assert!(needs_continuation("f(x) ="));
+    }
+
+    #[test]
+    fn test_backslash_continues() {
+        assert!(needs_continuation("5 \\"));
+    }
+
+    #[test]
+    fn test_unbalanced_parens_continue() {
+        assert!(needs_continuation("(2 + 3"));
+        assert!(needs_continuation("((1 + 2) * 3"));
+        assert!(needs_continuation("mean([1, 2"));
+        assert!(needs_continuation("data = [1, 2,"));
+        assert!(!needs_continuation("data = [1, 2]"));
+    }
+
+    #[test]
+    fn test_single_line_expression() {
+        let (expression, output) = read_all("5 + 3\n");
+        assert_eq!(expression, Some("5 + 3".to_string()));
+        assert_eq!(output, "> ");
+    }
+
+    #[test]
+    fn test_multi_line_expression() {
+        let (expression, output) = read_all("5 +\n3\n");
+        assert_eq!(expression, Some("5 + 3".to_string()));
+        assert_eq!(output, "> ... ");
+    }
+
+    #[test]
+    fn test_backslash_is_removed() {
+        let (expression, _) = read_all("5 \\\n* 2\n");
+        assert_eq!(expression, Some("5 * 2".to_string()));
+    }
+
+    #[test]
+    fn test_parens_across_lines() {
+        let (expression, output) = read_all("(2 +\n3\n)\n");
+        assert_eq!(expression, Some("(2 + 3 )".to_string()));
+        assert_eq!(output, "> ... ... ");
+    }
+
+    #[test]
+    fn test_blank_line_submits_unfinished_expression() {
+        let (expression, _) = read_all("5 +\n\n7\n");
+        assert_eq!(expression, Some("5 +".to_string()));
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        assert_eq!(read_all("").0, None);
+        assert_eq!(read_all("5 +").0, Some("5 +".to_string()));
+    }
+}
@@ -0,0 +1,126 @@
+// src/journal.rs
+// The undo/redo journal: a record of how to put back each change to a session
+
+use std::collections::VecDeque;
+
+use crate::calculator::{Function, Mode};
+use crate::value::Value;
+
+/// How many changes `undo` can go back
+pub const JOURNAL_SIZE: usize = 100;
+
+/// A piece of session state as it was before a change
+/// Putting it back gives the state it replaced, which is how undo turns into redo
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A variable's old value (None if it did not exist)
+    Variable(String, Option<Value>),
+    /// A function's old definition (None if it did not exist)
+    Function(String, Option<Function>),
+    Mode(Mode),
+    Precision(Option<usize>),
+    /// The history before it was cleared
+    History(Vec<String>),
+    /// Several changes made by one action (like `load`), oldest first
+    Group(Vec<Change>),
+}
+
+/// One undoable action: what the user typed and how to reverse it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub action: String,
+    pub change: Change,
+}
+
+/// Changes that can be undone (the newest last) and undone changes that can be redone
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl Journal {
+    /// Remember a new action; anything that was undone can no longer be redone
+    pub fn record(&mut self, action: &str, change: Change) {
+        self.redo.clear();
+        self.undo.push_back(Entry { action: action.to_string(), change });
+        if self.undo.len() > JOURNAL_SIZE {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Number of actions that can be undone
+    pub fn len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Merge every action recorded since the journal had `start` entries into one
+    pub fn group_since(&mut self, start: usize, action: &str) {
+        let start = start.min(self.undo.len());
+        if start == self.undo.len() {
+            return;
+        }
+        let changes = self.undo.drain(start..).map(|entry| entry.change).collect();
+        self.undo.push_back(Entry { action: action.to_string(), change: Change::Group(changes) });
+    }
+
+    pub fn take_undo(&mut self) -> Option<Entry> {
+        self.undo.pop_back()
+    }
+
+    pub fn take_redo(&mut self) -> Option<Entry> {
+        self.redo.pop()
+    }
+
+    /// Keep the reverse of an undone action so `redo` can apply it again
+    pub fn push_redo(&mut self, entry: Entry) {
+        self.redo.push(entry);
+    }
+
+    /// Keep the reverse of a redone action without forgetting the other redos
+    pub fn push_undo(&mut self, entry: Entry) {
+        self.undo.push_back(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, value: f64) -> Change {
+        Change::Variable(name.to_string(), Some(Value::Number(value)))
+    }
+
+    #[test]
+    fn test_journal_is_bounded() {
+        let mut journal = Journal::default();
+        for i in 0..JOURNAL_SIZE + 5 {
+            journal.record(&format!("x = {}", i), variable("x", i as f64));
+        }
+        assert_eq!(journal.len(), JOURNAL_SIZE);
+        assert_eq!(journal.take_undo().unwrap().action, format!("x = {}", JOURNAL_SIZE + 4));
+    }
+
+    #[test]
+    fn test_new_action_clears_redo() {
+        let mut journal = Journal::default();
+        journal.record("x = 1", variable("x", 0.0));
+        let entry = journal.take_undo().unwrap();
+        journal.push_redo(entry);
+        journal.record("y = 2", variable("y", 0.0));
+        assert_eq!(journal.take_redo(), None);
+    }
+
+    #[test]
+    fn test_group_since() {
+        let mut journal = Journal::default();
+        journal.record("a = 1", variable("a", 0.0));
+        journal.record("b = 1", variable("b", 0.0));
+        journal.record("c = 1", variable("c", 0.0));
+        journal.group_since(1, "load work.calc");
+        assert_eq!(journal.len(), 2);
+        let entry = journal.take_undo().unwrap();
+        assert_eq!(entry.action, "load work.calc");
+        assert_eq!(entry.change, Change::Group(vec![variable("b", 0.0), variable("c", 0.0)]));
+    }
+}
@@ -0,0 +1,129 @@
+// src/json.rs
+// A tiny JSON writer, so machine-readable output needs no extra dependencies
+
+use std::fmt::{self, Write};
+
+/// A JSON value that can be written out with `to_string()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep the order they were added in
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Build an object from `(key, value)` pairs
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /// A JSON string value
+    pub fn string(text: impl Into<String>) -> Json {
+        Json::String(text.into())
+    }
+}
+
+/// Write `text` as a quoted JSON string, escaping anything JSON does not allow raw
+pub fn write_escaped<W: Write>(out: &mut W, text: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{08}' => out.write_str("\\b")?,
+            '\u{0C}' => out.write_str("\\f")?,
+            // Other control characters must use the \uXXXX form
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // JSON has no NaN or infinity, so those become null
+            Json::Number(value) if !value.is_finite() => f.write_str("null"),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(text) => write_escaped(f, text),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_values() {
+        assert_eq!(Json::Null.to_string(), "null");
+        assert_eq!(Json::Bool(true).to_string(), "true");
+        assert_eq!(Json::Number(8.0).to_string(), "8");
+        assert_eq!(Json::Number(-2.5).to_string(), "-2.5");
+        assert_eq!(Json::string("hi").to_string(), "\"hi\"");
+    }
+
+    #[test]
+    fn test_non_finite_numbers_become_null() {
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+        assert_eq!(Json::Number(f64::INFINITY).to_string(), "null");
+    }
+
+    #[test]
+    fn test_escaping_quotes_and_backslashes() {
+        assert_eq!(Json::string("say \"hi\"").to_string(), r#""say \"hi\"""#);
+        assert_eq!(Json::string("a\\b").to_string(), r#""a\\b""#);
+    }
+
+    #[test]
+    fn test_escaping_control_characters() {
+        assert_eq!(Json::string("a\nb\tc\r").to_string(), r#""a\nb\tc\r""#);
+        assert_eq!(Json::string("\u{08}\u{0C}").to_string(), r#""\b\f""#);
+        assert_eq!(Json::string("\u{01}\u{1f}").to_string(), r#""\u0001\u001f""#);
+    }
+
+    #[test]
+    fn test_unicode_is_kept() {
+        assert_eq!(Json::string("🦀 π").to_string(), "\"🦀 π\"");
+    }
+
+    #[test]
+    fn test_nested_structures() {
+        let value = Json::object(vec![
+            ("list", Json::Array(vec![Json::Number(1.0), Json::Null])),
+            ("key \"q\"", Json::object(vec![("ok", Json::Bool(false))])),
+        ]);
+        assert_eq!(value.to_string(), r#"{"list":[1,null],"key \"q\"":{"ok":false}}"#);
+    }
+}
@@ -0,0 +1,193 @@
+// src/lexer.rs
+// Splitting an expression into tokens: numbers, names, operators and brackets
+
+use crate::error::Span;
+
+/// The different kinds of token an expression is made of
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// A number as written, e.g. "3.14" or "2e10" (checked by the parser)
+    Number(String),
+    /// A variable or function name
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Equals,
+    /// A character the calculator does not understand
+    Unknown(char),
+}
+
+/// One token and where it sits in the input
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Split `input` into tokens
+/// This never fails: characters it does not know become `TokenKind::Unknown`,
+/// so callers can still show everything that was typed
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        // Numbers: digits with an optional decimal point and exponent (1.5e-3)
+        if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                let after_exponent = matches!(input[..index].chars().last(), Some('e' | 'E'));
+                let is_exponent = (c == 'e' || c == 'E') && starts_exponent(&input[index + 1..]);
+                if c.is_ascii_digit() || c == '.' || is_exponent || ((c == '+' || c == '-') && after_exponent) {
+                    end = index + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token { kind: TokenKind::Number(input[start..end].to_string()), span: Span::new(start, end) });
+            continue;
+        }
+
+        // Names: a letter or underscore, then letters, digits or underscores
+        // A dot followed by a letter continues the name, for table columns like d.price
+        if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                let starts_part = c == '.' && input[index + 1..].starts_with(|next: char| next.is_alphabetic() || next == '_');
+                if c.is_alphanumeric() || c == '_' || starts_part {
+                    end = index + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token { kind: TokenKind::Ident(input[start..end].to_string()), span: Span::new(start, end) });
+            continue;
+        }
+
+        chars.next();
+        let kind = match c {
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '^' => TokenKind::Caret,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            ',' => TokenKind::Comma,
+            '=' => TokenKind::Equals,
+            other => TokenKind::Unknown(other),
+        };
+        tokens.push(Token { kind, span: Span::new(start, start + c.len_utf8()) });
+    }
+
+    tokens
+}
+
+/// Does the text after an 'e' look like an exponent ("3", "-3", "+3")?
+fn starts_exponent(rest: &str) -> bool {
+    let mut chars = rest.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_digit() => true,
+        Some('+' | '-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input).into_iter().map(|token| token.kind).collect()
+    }
+
+    fn number(text: &str) -> TokenKind {
+        TokenKind::Number(text.to_string())
+    }
+
+    fn ident(text: &str) -> TokenKind {
+        TokenKind::Ident(text.to_string())
+    }
+
+    #[test]
+    fn test_simple_expression() {
+        assert_eq!(kinds("5 + 3"), vec![number("5"), TokenKind::Plus, number("3")]);
+    }
+
+    #[test]
+    fn test_no_spaces_needed() {
+        assert_eq!(
+            kinds("2*(x-1)^2"),
+            vec![
+                number("2"),
+                TokenKind::Star,
+                TokenKind::LParen,
+                ident("x"),
+                TokenKind::Minus,
+                number("1"),
+                TokenKind::RParen,
+                TokenKind::Caret,
+                number("2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(kinds("3.14 .5 1e3 2.5E-4"), vec![number("3.14"), number(".5"), number("1e3"), number("2.5E-4")]);
+        // An 'e' that does not start an exponent is a name
+        assert_eq!(kinds("2e"), vec![number("2"), ident("e")]);
+        // Malformed numbers are kept whole so the parser can point at them
+        assert_eq!(kinds("1.2.3"), vec![number("1.2.3")]);
+    }
+
+    #[test]
+    fn test_names_and_calls() {
+        assert_eq!(
+            kinds("f(x_1, y) = x_1"),
+            vec![
+                ident("f"),
+                TokenKind::LParen,
+                ident("x_1"),
+                TokenKind::Comma,
+                ident("y"),
+                TokenKind::RParen,
+                TokenKind::Equals,
+                ident("x_1"),
+            ]
+        );
+        assert_eq!(kinds("sum(d.price)"), vec![ident("sum"), TokenKind::LParen, ident("d.price"), TokenKind::RParen]);
+        // A dot before a digit is not part of the name
+        assert_eq!(kinds("x.5"), vec![ident("x"), number(".5")]);
+    }
+
+    #[test]
+    fn test_spans() {
+        let tokens = tokenize("12 +  x");
+        assert_eq!(tokens[0].span, Span::new(0, 2));
+        assert_eq!(tokens[1].span, Span::new(3, 4));
+        assert_eq!(tokens[2].span, Span::new(6, 7));
+    }
+
+    #[test]
+    fn test_unknown_characters_are_kept() {
+        assert_eq!(kinds("5 % 3"), vec![number("5"), TokenKind::Unknown('%'), number("3")]);
+    }
+}
@@ -0,0 +1,33 @@
+// src/lib.rs
+// Shared building blocks used by the main calculator and the examples
+
+pub mod ast;
+pub mod batch;
+pub mod builtins;
+pub mod calculus;
+pub mod calculator;
+pub mod cli;
+pub mod color;
+pub mod config;
+pub mod csv;
+pub mod distributions;
+pub mod editor;
+pub mod error;
+pub mod explain;
+pub mod input;
+pub mod json;
+mod journal;
+mod lexer;
+pub mod matrix;
+pub mod number_theory;
+pub mod output;
+pub mod parser;
+pub mod plot;
+pub mod polynomial;
+pub mod random;
+pub mod session;
+pub mod simplify;
+pub mod solve;
+pub mod stats;
+pub mod svg;
+pub mod value;
@@ -2,49 +2,236 @@
 // CLI Calculator - Main Application
 // This is our capstone project: a beginner-friendly calculator in Rust
 
-use std::io;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::process::ExitCode;
 
-fn main() {
-    println!("🦀 Welcome to Rust Calculator!");
-    println!("Enter expressions like '5 + 3' or type 'quit' to exit.");
-    println!("Supported operations: +, -, *, /");
-    println!("Special commands: 'history' to see past calculations, 'clear' to clear history");
-    println!();
+use rust_beginner_toolkit::batch::{self, BatchOptions};
+use rust_beginner_toolkit::cli;
+use rust_beginner_toolkit::color::{Palette, Role};
+use rust_beginner_toolkit::config::{self, Config};
+use rust_beginner_toolkit::csv;
+use rust_beginner_toolkit::editor::{self, RawMode};
+use rust_beginner_toolkit::error::CalcError;
+use rust_beginner_toolkit::input;
+use rust_beginner_toolkit::output::{self, OutputFormat};
+use rust_beginner_toolkit::parser;
+use rust_beginner_toolkit::plot;
+use rust_beginner_toolkit::simplify::simplify;
+use rust_beginner_toolkit::session::{command_argument, Outcome, Session};
 
-    // Store calculation history
-    let mut history: Vec<String> = Vec::new();
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if options.help {
+        print!("{}", cli::usage());
+        return ExitCode::SUCCESS;
+    }
+    if options.version {
+        println!("{}", cli::version());
+        return ExitCode::SUCCESS;
+    }
+
+    // Start from config.toml, then let command-line options override it
+    let (mut config, warnings) = Config::load();
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    config.override_with(&options);
+
+    // Every way of running the calculator starts from the same settings
+    let mut session = config.new_session();
+
+    // Expressions given on the command line are evaluated without the REPL,
+    // e.g. `rust-beginner-toolkit "2 + 3"` for use in shell scripts
+    if !options.expressions.is_empty() {
+        let stdout = io::stdout();
+        let stderr = io::stderr();
+        let success = evaluate_arguments(&mut session, &options.expressions, config.output, &mut stdout.lock(), &mut stderr.lock());
+        return exit_code(success);
+    }
+
+    // A script file, or input piped in from another program, runs in batch mode
+    let batch_options = BatchOptions { keep_going: options.keep_going, output: config.output };
+    if let Some(path) = &options.file {
+        return match File::open(path) {
+            Ok(file) => run_batch(&mut session, path, BufReader::new(file), batch_options),
+            Err(error) => {
+                eprintln!("Error: cannot open '{}': {}", path, error);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    if !io::stdin().is_terminal() {
+        return run_batch(&mut session, "<stdin>", io::stdin().lock(), batch_options);
+    }
+
+    let palette = config.palette(io::stdout().is_terminal());
+    run_repl(session, &config, palette, options.quiet);
+    ExitCode::SUCCESS
+}
+
+/// Run a batch script in the given session
+fn run_batch<R: io::BufRead>(session: &mut Session, source: &str, reader: R, options: BatchOptions) -> ExitCode {
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+    match batch::run_batch(source, reader, session, options, &mut stdout.lock(), &mut stderr.lock()) {
+        Ok(success) => exit_code(success),
+        Err(error) => {
+            eprintln!("Error reading {}: {}", source, error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Turn "did everything work?" into the process exit status
+fn exit_code(success: bool) -> ExitCode {
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Interactive read-eval-print loop
+fn run_repl(mut session: Session, config: &Config, mut palette: Palette, quiet: bool) {
+    if !quiet {
+        println!("🦀 Welcome to Rust Calculator!");
+        println!("Enter expressions like '5 + 3' or type 'quit' to exit.");
+        println!("Supported operations: +, -, *, /, ^ and parentheses");
+        println!("Store values with 'rate = 0.2' and define functions with 'tax(x) = x * rate'.");
+        println!("Special commands: 'history' to see past calculations, 'clear' to clear history, 'help' for more");
+        println!("End a line with an operator or '\\' to continue it on the next line.");
+        println!();
+    }
+
+    // Run the shared startup script; its errors are reported but never stop the session
+    if let Some(path) = config::init_script_path()
+        && let Err(error) = batch::run_init_script(&path, &mut session, &mut io::stderr())
+    {
+        eprintln!("Warning: could not read {}: {}", path.display(), error);
+    }
+
+    // With colors on, expressions are highlighted while they are typed
+    // (if the terminal cannot do that, we read plain lines instead)
+    let live_editing = palette.enabled && RawMode::enable().is_ok();
 
     // Main calculator loop
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
     loop {
-        // Display prompt
-        print!("> ");
-        
-        // Flush stdout to ensure prompt appears immediately
-        use std::io::Write;
-        io::stdout().flush().unwrap();
-
-        // Read user input
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
+        // Read one expression; lines ending in an operator, a backslash or
+        // with unclosed parentheses continue on the next line
+        let prompt = palette.paint(Role::Prompt, &config.prompt);
+        let continuation_prompt = palette.paint(Role::Prompt, input::CONTINUATION_PROMPT);
+        let expression = if live_editing {
+            read_highlighted(&session, &palette, &prompt, &continuation_prompt)
+        } else {
+            input::read_expression(&mut stdin.lock(), &mut stdout, &prompt, &continuation_prompt)
+        };
+        match expression {
+            Ok(Some(input)) => {
                 let input = input.trim(); // Remove whitespace and newline
                 
                 // Check if user wants to quit
                 if input.to_lowercase() == "quit" || input.to_lowercase() == "q" {
-                    println!("Thanks for using Rust Calculator! You performed {} calculations.", history.len());
-                    println!("Goodbye! 👋");
+                    if !quiet {
+                        println!("Thanks for using Rust Calculator! You performed {} calculations.", session.history.len());
+                        println!("Goodbye! 👋");
+                    }
                     break;
                 }
                 
                 // Handle special commands
+                if input.to_lowercase() == "help" {
+                    println!("📖 Commands:");
+                    print!("{}", cli::repl_commands_text());
+                    continue;
+                }
+                
+                if let Some(path) = command_argument(input, "save") {
+                    save_session(&session, path, &palette);
+                    continue;
+                }
+                
+                if let Some(argument) = command_argument(input, "load") {
+                    match csv::parse_load(argument) {
+                        Some(request) => load_table(&mut session, request, &palette),
+                        None => load_session(&mut session, argument, &palette),
+                    }
+                    continue;
+                }
+                
+                if let Some(expression) = command_argument(input, "explain") {
+                    explain(&session, expression, &palette);
+                    continue;
+                }
+                
+                if let Some(expression) = command_argument(input, "ast") {
+                    show_tree(expression, &palette);
+                    continue;
+                }
+                
+                if let Some(expression) = command_argument(input, "simplify") {
+                    match parser::parse_expression(expression) {
+                        Ok(expr) => println!("✨ {}", palette.highlight(&simplify(&expr).to_string())),
+                        Err(error) => show_error(expression, &error, &palette),
+                    }
+                    continue;
+                }
+                
+                if let Some(expression) = command_argument(input, "roots") {
+                    show_roots(&session, expression, &palette);
+                    continue;
+                }
+                
+                if let Some(argument) = command_argument(input, "plot") {
+                    plot(&session, argument, &palette);
+                    continue;
+                }
+                
+                if let Some(equation) = command_argument(input, "solve") {
+                    solve(&session, equation, &palette);
+                    continue;
+                }
+                
+                if let Some(argument) = command_argument(input, "theme") {
+                    change_theme(&mut palette, argument);
+                    continue;
+                }
+                
                 if input.to_lowercase() == "history" {
-                    show_history(&history);
+                    show_history(&session.history, &palette);
                     continue;
                 }
                 
                 if input.to_lowercase() == "clear" {
-                    history.clear();
-                    println!("History cleared! 🧹");
+                    session.clear_history();
+                    println!("History cleared! 🧹 (type 'undo' to bring it back)");
+                    continue;
+                }
+                
+                if input.to_lowercase() == "undo" {
+                    match session.undo() {
+                        Some(action) => println!("↩️ Undid: {}", palette.highlight(&action)),
+                        None => println!("Nothing to undo"),
+                    }
+                    continue;
+                }
+                
+                if input.to_lowercase() == "redo" {
+                    match session.redo() {
+                        Some(action) => println!("↪️ Redid: {}", palette.highlight(&action)),
+                        None => println!("Nothing to redo"),
+                    }
                     continue;
                 }
                 
@@ -53,15 +240,23 @@ fn main() {
                     continue;
                 }
                 
-                // Process the calculation
-                match calculate(input) {
-                    Ok(result) => {
-                        println!("Result: {}", result);
-                        // Add to history
-                        history.push(format!("{} = {}", input, result));
-                    }
-                    Err(error) => println!("Error: {}", error),
+                // Process the line (the session adds calculations to the history)
+                match session.execute(input) {
+                    Ok(outcome) => show_outcome(&session, &outcome, &palette),
+                    Err(error) => show_error(input, &error, &palette),
+                }
+            }
+            Ok(None) => {
+                // Input ended (e.g. Ctrl+D)
+                println!();
+                if !quiet {
+                    println!("Goodbye! 👋");
                 }
+                break;
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                // Ctrl+C only throws away what was being typed
+                continue;
             }
             Err(error) => {
                 println!("Error reading input: {}", error);
@@ -73,57 +268,260 @@ fn main() {
     }
 }
 
-/// Parse and calculate mathematical expressions
-/// Supports format: "number operator number" (e.g., "5 + 3")
-fn calculate(expression: &str) -> Result<f64, String> {
-    // Split the input into parts
-    let parts: Vec<&str> = expression.split_whitespace().collect();
-    
-    // Validate we have exactly 3 parts
-    if parts.len() != 3 {
-        return Err("Please enter in format: number operator number (e.g., '5 + 3')".to_string());
+/// Read an expression key by key, coloring it as it is typed:
+/// names the session does not know yet show up as errors
+fn read_highlighted(session: &Session, palette: &Palette, prompt: &str, continuation_prompt: &str) -> io::Result<Option<String>> {
+    let highlight = |line: &str, matched: &[usize]| {
+        if cli::is_repl_command(line) {
+            palette.highlight_with(line, |_| true, matched)
+        } else {
+            palette.highlight_with(line, |name| session.knows(name), matched)
+        }
+    };
+    // The terminal goes back to normal as soon as the expression is read
+    let _raw_mode = RawMode::enable()?;
+    editor::read_expression(&mut io::stdin().lock(), &mut io::stdout(), prompt, continuation_prompt, &highlight)
+}
+
+/// Tell the user what a line did
+fn show_outcome(session: &Session, outcome: &Outcome, palette: &Palette) {
+    match outcome {
+        Outcome::Value(result) => println!("Result: {}", palette.paint(Role::Result, &session.format(result))),
+        Outcome::Estimate(result, error) => {
+            println!("Result: {}", palette.paint(Role::Result, &session.format_estimate(result, *error)))
+        }
+        Outcome::Assigned(name, value) => {
+            println!("{} = {}", palette.paint(Role::Variable, name), palette.paint(Role::Result, &session.format(value)))
+        }
+        Outcome::Defined(name) => {
+            let definition = format!("{}{}", name, session.functions[name]);
+            println!("✅ Defined {}", palette.highlight(&definition));
+        }
+        Outcome::Setting(message) => println!("⚙️ {}", palette.paint(Role::Info, message)),
+        Outcome::Derivative(variable, slope, value) => {
+            println!("📐 d/d{} = {}", variable, palette.highlight(&slope.to_string()));
+            if let Some(value) = value {
+                println!("Result: {}", palette.paint(Role::Result, &session.format(value)));
+            }
+        }
+    }
+}
+
+/// Show an error, echoing the input with the problem underlined when we know where it is
+fn show_error(input: &str, error: &CalcError, palette: &Palette) {
+    if let Some(span) = error.span {
+        // Spans count bytes, but the terminal shows characters
+        let column = input[..span.start].chars().count();
+        let width = input[span.start..span.end].chars().count().max(1);
+        println!("  {}", palette.highlight(input));
+        println!("  {}{}", " ".repeat(column), palette.paint(Role::Error, &"^".repeat(width)));
+    }
+    println!("{} {}", palette.paint(Role::Error, "Error:"), error);
+}
+
+/// Show how an expression is worked out, one step per line
+fn explain(session: &Session, expression: &str, palette: &Palette) {
+    match session.explain(expression) {
+        Ok(explanation) => {
+            println!("🧮 Step by step:");
+            for line in explanation.to_string().lines() {
+                println!("  {}", line);
+            }
+            println!("Result: {}", palette.paint(Role::Result, &session.format(&explanation.result)));
+        }
+        Err(error) => show_error(expression, &error, palette),
     }
-    
-    // Parse the first number
-    let num1: f64 = match parts[0].parse() {
-        Ok(n) => n,
-        Err(_) => return Err(format!("'{}' is not a valid number", parts[0])),
+}
+
+/// Draw `plot f, g, ..., x, low, high` as wide as the terminal (80 columns if it is not one),
+/// or save it as an SVG picture with `plot ... > chart.svg`
+fn plot(session: &Session, argument: &str, palette: &Palette) {
+    match plot::split_output(argument) {
+        (_, Some("")) => println!("{} say where to save the chart, e.g. 'plot sin(x), x, 0, pi > chart.svg'", palette.paint(Role::Error, "Error:")),
+        (argument, Some(path)) => match session.plot(argument, plot::SVG_SAMPLES) {
+            Ok(chart) => {
+                let labels: Vec<&str> = chart.series.iter().map(|series| series.label.as_str()).collect();
+                match fs::write(path, plot::render_svg(&chart)) {
+                    Ok(()) => println!("📈 Saved the chart of {} to {}", labels.join(", "), path),
+                    Err(error) => println!("{} cannot write '{}': {}", palette.paint(Role::Error, "Error:"), path, error),
+                }
+            }
+            Err(error) => show_error(argument, &error, palette),
+        },
+        (argument, None) => {
+            let width = if io::stdout().is_terminal() { editor::terminal_width() } else { None }.unwrap_or(plot::DEFAULT_WIDTH);
+            // Two samples for each column of braille dots
+            match session.plot(argument, 2 * width) {
+                Ok(chart) => print!("{}", plot::render_terminal(&chart, width, palette)),
+                Err(error) => show_error(argument, &error, palette),
+            }
+        }
+    }
+}
+
+/// Solve an equation and show each root with how it was found
+fn solve(session: &Session, equation: &str, palette: &Palette) {
+    match session.solve(equation) {
+        Ok(solution) if solution.roots.is_empty() => println!("🎯 No roots found"),
+        Ok(solution) => {
+            for root in &solution.roots {
+                let value = root.value.format(session.precision);
+                println!("🎯 {} = {}  ({})", solution.variable, palette.paint(Role::Result, &value), root.report());
+            }
+        }
+        Err(error) => show_error(equation, &error, palette),
+    }
+}
+
+/// Show every root of a polynomial, complex ones included
+fn show_roots(session: &Session, expression: &str, palette: &Palette) {
+    match session.roots(expression) {
+        Ok(roots) if roots.is_empty() => println!("🎯 No roots"),
+        Ok(roots) => {
+            for root in roots {
+                println!("🎯 x = {}", palette.paint(Role::Result, &root.format(session.precision)));
+            }
+        }
+        Err(error) => show_error(expression, &error, palette),
+    }
+}
+
+/// Show the parse tree of an expression and its normalized form
+fn show_tree(expression: &str, palette: &Palette) {
+    match parser::parse_expression(expression) {
+        Ok(expr) => {
+            println!("🌳 {}", palette.highlight(&expr.to_string()));
+            print!("{}", expr.tree());
+        }
+        Err(error) => show_error(expression, &error, palette),
+    }
+}
+
+/// Show the color theme, or switch to another one
+fn change_theme(palette: &mut Palette, argument: &str) {
+    if argument.is_empty() {
+        println!("🎨 Current theme: {}", palette.theme);
+        return;
+    }
+    match argument.to_lowercase().parse() {
+        Ok(theme) => {
+            palette.theme = theme;
+            println!("🎨 Theme set to {}", palette.paint(Role::Info, &theme.to_string()));
+        }
+        Err(error) => println!("{} {}", palette.paint(Role::Error, "Error:"), error),
+    }
+}
+
+/// Write the session to a file that `load` (or batch mode) can run later
+fn save_session(session: &Session, path: &str, palette: &Palette) {
+    if path.is_empty() {
+        println!("{} save needs a file name, e.g. 'save work.calc'", palette.paint(Role::Error, "Error:"));
+        return;
+    }
+    match fs::write(path, session.save_script()) {
+        Ok(()) => println!(
+            "💾 Saved {} variables, {} functions and {} history entries to {}",
+            session.variables.len(),
+            session.functions.len(),
+            session.history.len(),
+            path
+        ),
+        Err(error) => println!("{} cannot write '{}': {}", palette.paint(Role::Error, "Error:"), path, error),
+    }
+}
+
+/// Run a saved session file, reporting replaced definitions and failing lines
+fn load_session(session: &mut Session, path: &str, palette: &Palette) {
+    if path.is_empty() {
+        println!("{} load needs a file name, e.g. 'load work.calc'", palette.paint(Role::Error, "Error:"));
+        return;
+    }
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(error) => {
+            println!("{} cannot read '{}': {}", palette.paint(Role::Error, "Error:"), path, error);
+            return;
+        }
+    };
+
+    let report = session.load_script(&script);
+    println!("📂 Loaded {} lines from {}", report.loaded, path);
+    for conflict in &report.conflicts {
+        println!("⚠️ {} replaced: was {}, now {}", conflict.name, conflict.old, conflict.new);
+    }
+    for (line, error) in &report.errors {
+        println!("{} {}:{}: {}", palette.paint(Role::Error, "Error:"), path, line, error);
+    }
+}
+
+/// Load a CSV file, keeping each column of numbers as a vector like d.price
+fn load_table(session: &mut Session, request: Result<csv::TableLoad, String>, palette: &Palette) {
+    let request = match request {
+        Ok(request) => request,
+        Err(message) => {
+            println!("{} {}", palette.paint(Role::Error, "Error:"), message);
+            return;
+        }
     };
-    
-    // Get the operator
-    let operator = parts[1];
-    
-    // Parse the second number
-    let num2: f64 = match parts[2].parse() {
-        Ok(n) => n,
-        Err(_) => return Err(format!("'{}' is not a valid number", parts[2])),
+    let table = match fs::read_to_string(&request.path) {
+        Ok(text) => csv::parse(&text, request.delimiter),
+        Err(error) => {
+            println!("{} cannot read '{}': {}", palette.paint(Role::Error, "Error:"), request.path, error);
+            return;
+        }
     };
-    
-    // Perform the calculation based on operator
-    match operator {
-        "+" => Ok(num1 + num2),
-        "-" => Ok(num1 - num2),
-        "*" => Ok(num1 * num2),
-        "/" => {
-            if num2 == 0.0 {
-                Err("Cannot divide by zero!".to_string())
-            } else {
-                Ok(num1 / num2)
-            }
+    let table = match table {
+        Ok(table) => table,
+        Err(error) => {
+            println!("{} {}: {}", palette.paint(Role::Error, "Error:"), request.path, error);
+            return;
+        }
+    };
+
+    let names = session.load_table(&request.name, &table);
+    println!("📂 Loaded {} rows from {}: {}", table.rows, request.path, names.join(", "));
+    for name in &table.skipped {
+        println!("⚠️ Skipped the text column '{}'", name);
+    }
+}
+
+/// Evaluate each argument in order, printing one result per line
+/// Stops at the first error, which goes to `err` (or to `out` as JSON);
+/// returns true if everything succeeded
+fn evaluate_arguments<W: Write, E: Write>(
+    session: &mut Session,
+    args: &[String],
+    format: OutputFormat,
+    out: &mut W,
+    err: &mut E,
+) -> bool {
+    for expression in args {
+        let expression = expression.trim();
+        let outcome = session.execute(expression);
+        let _ = match (&outcome, format) {
+            (_, OutputFormat::Json) => writeln!(out, "{}", output::evaluation_json(expression, &outcome, session.precision)),
+            (Ok(Outcome::Value(result) | Outcome::Estimate(result, _)), OutputFormat::Text) => writeln!(out, "{}", session.format(result)),
+            (Ok(Outcome::Derivative(_, _, Some(result))), OutputFormat::Text) => writeln!(out, "{}", session.format(result)),
+            (Ok(Outcome::Derivative(_, slope, None)), OutputFormat::Text) => writeln!(out, "{}", slope),
+            (Ok(_), OutputFormat::Text) => Ok(()),
+            (Err(error), OutputFormat::Text) => writeln!(err, "Error: {}", error),
+        };
+        if outcome.is_err() {
+            return false;
         }
-        _ => Err(format!("Unknown operator '{}'. Use +, -, *, or /", operator)),
     }
+    true
 }
 
 /// Display calculation history
-fn show_history(history: &Vec<String>) {
+fn show_history(history: &[String], palette: &Palette) {
     if history.is_empty() {
         println!("📝 No calculations yet! Start by entering an expression like '5 + 3'");
     } else {
         println!("📝 Calculation History ({} entries):", history.len());
         println!("================================");
         for (i, calculation) in history.iter().enumerate() {
-            println!("{}. {}", i + 1, calculation);
+            println!("{}. {}", i + 1, palette.highlight(calculation));
         }
         println!("================================");
     }
@@ -135,37 +533,38 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_addition() {
-        assert_eq!(calculate("5 + 3").unwrap(), 8.0);
-    }
-
-    #[test]
-    fn test_subtraction() {
-        assert_eq!(calculate("10 - 4").unwrap(), 6.0);
-    }
-
-    #[test]
-    fn test_multiplication() {
-        assert_eq!(calculate("6 * 7").unwrap(), 42.0);
-    }
-
-    #[test]
-    fn test_division() {
-        assert_eq!(calculate("15 / 3").unwrap(), 5.0);
+    fn test_evaluate_arguments_in_order() {
+        let args = vec!["2 + 3".to_string(), "6 * 7".to_string()];
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert!(evaluate_arguments(&mut Session::new(), &args, OutputFormat::Text, &mut out, &mut err));
+        assert_eq!(String::from_utf8(out).unwrap(), "5\n42\n");
+        assert!(err.is_empty());
     }
 
     #[test]
-    fn test_division_by_zero() {
-        assert!(calculate("5 / 0").is_err());
+    fn test_evaluate_arguments_stops_at_error() {
+        let args = vec!["1 + 1".to_string(), "5 / 0".to_string(), "2 + 2".to_string()];
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert!(!evaluate_arguments(&mut Session::new(), &args, OutputFormat::Text, &mut out, &mut err));
+        assert_eq!(String::from_utf8(out).unwrap(), "2\n");
+        assert_eq!(String::from_utf8(err).unwrap(), "Error: Cannot divide by zero!\n");
     }
 
     #[test]
-    fn test_invalid_number() {
-        assert!(calculate("hello + 3").is_err());
+    fn test_evaluate_arguments_share_variables() {
+        let args = vec!["r = 2".to_string(), "area(r) = 3 * r ^ 2".to_string(), "area(r)".to_string()];
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert!(evaluate_arguments(&mut Session::new(), &args, OutputFormat::Text, &mut out, &mut err));
+        assert_eq!(String::from_utf8(out).unwrap(), "12\n");
     }
 
     #[test]
-    fn test_invalid_operator() {
-        assert!(calculate("5 % 3").is_err());
+    fn test_evaluate_arguments_uses_session_settings() {
+        let mut session = Session::new();
+        session.precision = Some(2);
+        let args = vec!["2 / 3".to_string()];
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert!(evaluate_arguments(&mut session, &args, OutputFormat::Text, &mut out, &mut err));
+        assert_eq!(String::from_utf8(out).unwrap(), "0.67\n");
     }
 }
@@ -0,0 +1,365 @@
+// src/matrix.rs
+// Matrices of numbers: products, determinants, inverses, rank and solving A x = b
+
+use std::fmt;
+
+use crate::calculator::format_number;
+
+/// How much smaller than the largest entry a pivot may be before it counts as zero
+const SINGULAR_TOLERANCE: f64 = 1e-10;
+
+/// The size of a vector or matrix, as shown in errors about sizes that do not fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// A vector and its length
+    Vector(usize),
+    /// A matrix, rows by columns
+    Matrix(usize, usize),
+}
+
+// "vector of length 3", "2x3 matrix"
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Vector(length) => write!(f, "vector of length {}", length),
+            Shape::Matrix(rows, cols) => write!(f, "{}x{} matrix", rows, cols),
+        }
+    }
+}
+
+/// Show a list of numbers as "[1, 2, 3]", each rounded to `precision` places if one is given
+pub fn format_vector(values: &[f64], precision: Option<usize>) -> String {
+    let values: Vec<String> = values.iter().map(|value| format_number(*value, precision)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// A grid of numbers with at least one row and one column
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    /// The numbers row by row
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    /// Make a `rows` x `cols` matrix from its numbers, row by row
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Self {
+        assert!(rows > 0 && cols > 0 && data.len() == rows * cols, "a matrix needs rows x cols numbers");
+        Matrix { rows, cols, data }
+    }
+
+    /// Make a matrix from its rows; None if there are none, or they are empty or of different lengths
+    pub fn from_rows(rows: &[Vec<f64>]) -> Option<Self> {
+        let cols = rows.first()?.len();
+        if cols == 0 || rows.iter().any(|row| row.len() != cols) {
+            return None;
+        }
+        Some(Matrix::new(rows.len(), cols, rows.concat()))
+    }
+
+    /// The n x n matrix with ones down the diagonal
+    pub fn identity(n: usize) -> Self {
+        let mut identity = Matrix::new(n, n, vec![0.0; n * n]);
+        for i in 0..n {
+            identity.data[i * n + i] = 1.0;
+        }
+        identity
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Matrix(self.rows, self.cols)
+    }
+
+    /// All the numbers, row by row
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// The numbers in row `row`, counting from 0
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    /// The same numbers with a new value for each
+    pub fn map(&self, f: impl FnMut(&f64) -> f64) -> Matrix {
+        Matrix::new(self.rows, self.cols, self.data.iter().map(f).collect())
+    }
+
+    /// Rows become columns
+    pub fn transpose(&self) -> Matrix {
+        let data = (0..self.cols).flat_map(|col| (0..self.rows).map(move |row| self.get(row, col))).collect();
+        Matrix::new(self.cols, self.rows, data)
+    }
+
+    /// The matrix product; None unless this has as many columns as `other` has rows
+    pub fn times(&self, other: &Matrix) -> Option<Matrix> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let mut data = Vec::with_capacity(self.rows * other.cols);
+        for row in 0..self.rows {
+            for col in 0..other.cols {
+                data.push((0..self.cols).map(|k| self.get(row, k) * other.get(k, col)).sum());
+            }
+        }
+        Some(Matrix::new(self.rows, other.cols, data))
+    }
+
+    /// The matrix multiplied by itself `exponent` times, by repeated squaring;
+    /// the identity for 0, and None unless the matrix is square
+    pub fn power(&self, mut exponent: u64) -> Option<Matrix> {
+        if self.rows != self.cols {
+            return None;
+        }
+        let mut result = Matrix::identity(self.rows);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.times(&base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.times(&base)?;
+            }
+        }
+        Some(result)
+    }
+
+    /// This matrix times a column of numbers; None unless it has one column per number
+    pub fn times_vector(&self, vector: &[f64]) -> Option<Vec<f64>> {
+        if self.cols != vector.len() {
+            return None;
+        }
+        Some((0..self.rows).map(|row| self.row(row).iter().zip(vector).map(|(a, b)| a * b).sum()).collect())
+    }
+
+    /// The determinant, or None if the matrix is not square
+    pub fn determinant(&self) -> Option<f64> {
+        if self.rows != self.cols {
+            return None;
+        }
+        // Eliminate below each pivot; the determinant is the product of the pivots,
+        // with the sign flipped for every row swap
+        let mut a = self.clone();
+        let mut determinant = 1.0;
+        for col in 0..a.cols {
+            let pivot = a.pivot_row(col, col);
+            if a.get(pivot, col) == 0.0 {
+                return Some(0.0);
+            }
+            if pivot != col {
+                a.swap_rows(pivot, col);
+                determinant = -determinant;
+            }
+            determinant *= a.get(col, col);
+            a.eliminate_below(col, col);
+        }
+        // A matrix of whole numbers has a whole determinant, so drop rounding errors (and -0)
+        if self.data.iter().all(|value| value.fract() == 0.0) {
+            determinant = determinant.round() + 0.0;
+        }
+        Some(determinant)
+    }
+
+    /// The inverse, or None if the matrix is not square or is singular
+    pub fn inverse(&self) -> Option<Matrix> {
+        if self.rows != self.cols {
+            return None;
+        }
+        self.solve_columns(Matrix::identity(self.rows))
+    }
+
+    /// Solve `self * x = b` for x; None if the matrix is not square,
+    /// `b` is the wrong length, or there is no single answer
+    pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        if self.rows != self.cols || b.len() != self.rows {
+            return None;
+        }
+        self.solve_columns(Matrix::new(b.len(), 1, b.to_vec())).map(|x| x.data)
+    }
+
+    /// How many rows are independent of each other
+    pub fn rank(&self) -> usize {
+        let mut a = self.clone();
+        let tolerance = self.tolerance();
+        let mut rank = 0;
+        for col in 0..a.cols {
+            if rank == a.rows {
+                break;
+            }
+            let pivot = a.pivot_row(rank, col);
+            if a.get(pivot, col).abs() <= tolerance {
+                continue;
+            }
+            a.swap_rows(pivot, rank);
+            a.eliminate_below(rank, col);
+            rank += 1;
+        }
+        rank
+    }
+
+    /// Show as "[[1, 2], [3, 4]]", rounded to `precision` places if one is given
+    pub fn format(&self, precision: Option<usize>) -> String {
+        let rows: Vec<String> = (0..self.rows).map(|row| format_vector(self.row(row), precision)).collect();
+        format!("[{}]", rows.join(", "))
+    }
+
+    /// Gauss-Jordan elimination on [self | b], giving x with self * x = b
+    /// for every column of `b`; None if self is singular
+    fn solve_columns(&self, b: Matrix) -> Option<Matrix> {
+        let n = self.rows;
+        let width = n + b.cols;
+        let mut data = Vec::with_capacity(n * width);
+        for row in 0..n {
+            data.extend_from_slice(self.row(row));
+            data.extend_from_slice(b.row(row));
+        }
+        let mut a = Matrix::new(n, width, data);
+
+        let tolerance = self.tolerance();
+        for col in 0..n {
+            let pivot = a.pivot_row(col, col);
+            if a.get(pivot, col).abs() <= tolerance {
+                return None;
+            }
+            a.swap_rows(pivot, col);
+            let scale = a.get(col, col);
+            for k in 0..width {
+                a.data[col * width + k] /= scale;
+            }
+            for row in (0..n).filter(|row| *row != col) {
+                let factor = a.get(row, col);
+                for k in 0..width {
+                    a.data[row * width + k] -= factor * a.get(col, k);
+                }
+            }
+        }
+
+        let data = (0..n).flat_map(|row| a.row(row)[n..].to_vec()).collect();
+        Some(Matrix::new(n, b.cols, data))
+    }
+
+    /// Pivots smaller than this count as zero, relative to the largest entry
+    fn tolerance(&self) -> f64 {
+        SINGULAR_TOLERANCE * self.data.iter().fold(0.0_f64, |largest, value| largest.max(value.abs()))
+    }
+
+    /// The row from `start` down with the largest entry in `col` (partial pivoting keeps errors small)
+    fn pivot_row(&self, start: usize, col: usize) -> usize {
+        (start..self.rows).max_by(|a, b| self.get(*a, col).abs().total_cmp(&self.get(*b, col).abs())).unwrap_or(start)
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        if a != b {
+            for col in 0..self.cols {
+                self.data.swap(a * self.cols + col, b * self.cols + col);
+            }
+        }
+    }
+
+    /// Subtract multiples of row `pivot` to make zeros below it in `col`
+    fn eliminate_below(&mut self, pivot: usize, col: usize) {
+        for row in pivot + 1..self.rows {
+            let factor = self.get(row, col) / self.get(pivot, col);
+            for k in col..self.cols {
+                self.data[row * self.cols + k] -= factor * self.get(pivot, k);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[f64]]) -> Matrix {
+        Matrix::from_rows(&rows.iter().map(|row| row.to_vec()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_shapes_and_display() {
+        let a = matrix(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.5]]);
+        assert_eq!(a.shape(), Shape::Matrix(2, 3));
+        assert_eq!(a.to_string(), "[[1, 2, 3], [4, 5, 6.5]]");
+        assert_eq!(a.transpose().to_string(), "[[1, 4], [2, 5], [3, 6.5]]");
+        assert_eq!(Shape::Vector(3).to_string(), "vector of length 3");
+        assert_eq!(a.shape().to_string(), "2x3 matrix");
+        assert_eq!(format_vector(&[1.0 / 3.0, 2.0], Some(2)), "[0.33, 2.00]");
+        assert_eq!(Matrix::from_rows(&[vec![1.0], vec![2.0, 3.0]]), None);
+        assert_eq!(Matrix::from_rows(&[vec![]]), None);
+    }
+
+    #[test]
+    fn test_products() {
+        let a = matrix(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = matrix(&[&[0.0, 1.0], &[1.0, 0.0]]);
+        assert_eq!(a.times(&b).unwrap().to_string(), "[[2, 1], [4, 3]]");
+        assert_eq!(a.times_vector(&[1.0, 1.0]), Some(vec![3.0, 7.0]));
+        assert_eq!(a.times(&matrix(&[&[1.0, 2.0, 3.0]])), None);
+        assert_eq!(a.times_vector(&[1.0]), None);
+        assert_eq!(a.power(3).unwrap().to_string(), "[[37, 54], [81, 118]]");
+        assert_eq!(a.power(0), Some(Matrix::identity(2)));
+        assert_eq!(matrix(&[&[1.0, 2.0, 3.0]]).power(2), None);
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(matrix(&[&[1.0, 2.0], &[3.0, 4.0]]).determinant(), Some(-2.0));
+        assert_eq!(matrix(&[&[2.0, 0.0, 1.0], &[1.0, 3.0, 2.0], &[1.0, 1.0, 2.0]]).determinant(), Some(6.0));
+        assert_eq!(matrix(&[&[1.0, 2.0], &[2.0, 4.0]]).determinant(), Some(0.0));
+        // The first pivot is zero, so rows must be swapped
+        assert_eq!(matrix(&[&[0.0, 1.0], &[1.0, 0.0]]).determinant(), Some(-1.0));
+        assert_eq!(matrix(&[&[1.0, 2.0, 3.0]]).determinant(), None);
+    }
+
+    #[test]
+    fn test_inverse_and_solve() {
+        let a = matrix(&[&[4.0, 7.0], &[2.0, 6.0]]);
+        let inverse = a.inverse().unwrap();
+        assert_close(inverse.data(), &[0.6, -0.7, -0.2, 0.4]);
+        assert_close(a.times(&inverse).unwrap().data(), Matrix::identity(2).data());
+
+        let a = matrix(&[&[2.0, 1.0, -1.0], &[-3.0, -1.0, 2.0], &[-2.0, 1.0, 2.0]]);
+        assert_close(&a.solve(&[8.0, -11.0, -3.0]).unwrap(), &[2.0, 3.0, -1.0]);
+
+        let singular = matrix(&[&[1.0, 2.0], &[2.0, 4.0]]);
+        assert_eq!(singular.inverse(), None);
+        assert_eq!(singular.solve(&[1.0, 2.0]), None);
+        assert_eq!(a.solve(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn test_rank() {
+        assert_eq!(matrix(&[&[1.0, 2.0], &[3.0, 4.0]]).rank(), 2);
+        assert_eq!(matrix(&[&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]]).rank(), 1);
+        assert_eq!(matrix(&[&[0.0, 0.0], &[0.0, 0.0]]).rank(), 0);
+        assert_eq!(matrix(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0], &[7.0, 8.0, 9.0]]).rank(), 2);
+        assert_eq!(matrix(&[&[1.0], &[2.0], &[3.0]]).rank(), 1);
+    }
+}
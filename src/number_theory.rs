@@ -0,0 +1,277 @@
+// src/number_theory.rs
+// Whole-number functions: gcd, modular arithmetic, primes, factors and binomials
+//
+// The calculator only holds whole numbers exactly up to 2^53 - 1, so that is the
+// range these are written for. They take u64 to leave room for products, which
+// are done in u128, and an answer that does not fit gives None instead of a wrong number
+
+/// The largest n that `nth_prime` works out (the millionth prime is 15,485,863)
+pub const MAX_NTH_PRIME: u64 = 1_000_000;
+
+/// Greatest common divisor, by Euclid's algorithm; gcd(0, 0) is 0
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple; None if it does not fit in a u64
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// a * b mod m, without overflowing
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// base^exponent mod m, by repeated squaring; m must not be 0
+pub fn pow_mod(base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// The x with a * x = 1 (mod m), from the extended Euclidean algorithm;
+/// None if a and m share a factor (or m is 0)
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    // Keep r = s * a (mod m) for both rows while r shrinks like in gcd
+    let (mut r, mut next_r) = (m as i128, (a % m) as i128);
+    let (mut s, mut next_s) = (0_i128, 1_i128);
+    while next_r != 0 {
+        let quotient = r / next_r;
+        (r, next_r) = (next_r, r - quotient * next_r);
+        (s, next_s) = (next_s, s - quotient * next_s);
+    }
+    (r == 1).then(|| s.rem_euclid(m as i128) as u64)
+}
+
+/// Is n prime? Miller-Rabin with the primes up to 37 as witnesses, which is
+/// known to give the right answer for every u64 (far past the 2^53 the calculator uses)
+pub fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// The prime factors of n from smallest to largest, repeated as often as they divide it:
+/// 12 gives [2, 2, 3]. 0 and 1 have none
+pub fn factorize(n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return factors;
+    }
+    // Small factors by trial division, the rest with Pollard's rho
+    let mut n = n;
+    for p in 2..1000 {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    split(n, &mut factors);
+    factors.sort_unstable();
+    factors
+}
+
+/// Add the prime factors of n (which has no factors below 1000) to `factors`
+fn split(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        factors.push(n);
+        return;
+    }
+    let divisor = pollard_rho(n);
+    split(divisor, factors);
+    split(n / divisor, factors);
+}
+
+/// A divisor of the odd composite n other than 1 and n, found by walking
+/// x -> x^2 + c (mod n) until two values meet modulo a hidden factor
+fn pollard_rho(n: u64) -> u64 {
+    let step = |x: u64, c: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+    for c in 1.. {
+        let (mut slow, mut fast, mut divisor) = (2, 2, 1);
+        while divisor == 1 {
+            slow = step(slow, c);
+            fast = step(step(fast, c), c);
+            divisor = gcd(slow.abs_diff(fast), n);
+        }
+        if divisor != n {
+            return divisor;
+        }
+    }
+    unreachable!("some c always finds a divisor")
+}
+
+/// The nth prime, counting 2 as the first; None for 0 or past MAX_NTH_PRIME
+pub fn nth_prime(n: u64) -> Option<u64> {
+    if n == 0 || n > MAX_NTH_PRIME {
+        return None;
+    }
+    // The nth prime is below n (ln n + ln ln n) once n >= 6
+    let x = n.max(6) as f64;
+    let limit = (x * (x.ln() + x.ln().ln())) as usize + 1;
+
+    // Sieve of Eratosthenes
+    let mut composite = vec![false; limit + 1];
+    let mut count = 0;
+    for i in 2..=limit {
+        if composite[i] {
+            continue;
+        }
+        count += 1;
+        if count == n {
+            return Some(i as u64);
+        }
+        for multiple in (i * i..=limit).step_by(i) {
+            composite[multiple] = true;
+        }
+    }
+    unreachable!("the limit is above the nth prime")
+}
+
+/// Euler's totient: how many numbers from 1 to n share no factor with n
+pub fn totient(n: u64) -> u64 {
+    let mut factors = factorize(n);
+    factors.dedup();
+    factors.iter().fold(n, |result, p| result / p * (p - 1))
+}
+
+/// The binomial coefficient "n choose k"; None if it does not fit in a u64
+pub fn binomial(n: u64, k: u64) -> Option<u64> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    // After step i this is (n - k + i) choose i, a whole number, so the division is exact
+    let mut result: u128 = 1;
+    for i in 1..=k as u128 {
+        result = result * (n as u128 - k as u128 + i) / i;
+        if result > u64::MAX as u128 {
+            return None;
+        }
+    }
+    Some(result as u64)
+}
+
+/// The number of ways to line up k of n things, n! / (n - k)!; None if it does not fit in a u64
+pub fn permutations(n: u64, k: u64) -> Option<u64> {
+    if k > n {
+        return Some(0);
+    }
+    (n - k + 1..=n).try_fold(1_u64, |result, factor| result.checked_mul(factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_and_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(17, 5), 1);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm(u64::MAX, u64::MAX - 1), None);
+    }
+
+    #[test]
+    fn test_modular_arithmetic() {
+        assert_eq!(pow_mod(4, 13, 497), 445);
+        assert_eq!(pow_mod(5, 0, 1), 0);
+        // Would overflow without u128 products
+        assert_eq!(pow_mod(u64::MAX - 1, u64::MAX, u64::MAX), u64::MAX - 1);
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(10, 17), Some(12));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mod_inverse(5, 1), Some(0));
+    }
+
+    #[test]
+    fn test_primes() {
+        let small: Vec<u64> = (0..30).filter(|n| is_prime(*n)).collect();
+        assert_eq!(small, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        // Strong pseudoprimes that fool the first few bases, and the largest prime below 2^53
+        assert!(!is_prime(3_215_031_751));
+        assert!(!is_prime(3_474_749_660_383));
+        assert!(!is_prime(341_550_071_728_321));
+        assert!(is_prime(9_007_199_254_740_881));
+        assert!(!is_prime(9_007_199_254_740_991));
+        // The test holds for every u64: this one fools the primes up to 23,
+        // and the largest prime below 2^64 is still found
+        assert!(!is_prime(3_825_123_056_546_413_051));
+        assert!(is_prime(18_446_744_073_709_551_557));
+        assert!(!is_prime(u64::MAX));
+        assert_eq!(nth_prime(1), Some(2));
+        assert_eq!(nth_prime(6), Some(13));
+        assert_eq!(nth_prime(10_000), Some(104_729));
+        assert_eq!(nth_prime(0), None);
+    }
+
+    #[test]
+    fn test_factorize_and_totient() {
+        assert_eq!(factorize(360), [2, 2, 2, 3, 3, 5]);
+        assert_eq!(factorize(1), Vec::<u64>::new());
+        assert_eq!(factorize(1_000_003 * 1_000_033), [1_000_003, 1_000_033]);
+        assert_eq!(factorize(1_000_003 * 1_000_003), [1_000_003, 1_000_003]);
+        assert_eq!(factorize(9_007_199_254_740_991), [6361, 69_431, 20_394_401]);
+        assert_eq!(totient(36), 12);
+        assert_eq!(totient(1), 1);
+        assert_eq!(totient(97), 96);
+    }
+
+    #[test]
+    fn test_binomial() {
+        assert_eq!(binomial(5, 2), Some(10));
+        assert_eq!(binomial(52, 5), Some(2_598_960));
+        assert_eq!(binomial(3, 5), Some(0));
+        assert_eq!(binomial(67, 33), Some(14_226_520_737_620_288_370));
+        assert_eq!(binomial(68, 34), None);
+        assert_eq!(permutations(5, 2), Some(20));
+        assert_eq!(permutations(5, 0), Some(1));
+        assert_eq!(permutations(20, 20), Some(2_432_902_008_176_640_000));
+        assert_eq!(permutations(21, 21), None);
+    }
+}
@@ -0,0 +1,205 @@
+// src/output.rs
+// How results are written when the calculator is not interactive
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{CalcError, ErrorKind};
+use crate::json::Json;
+use crate::matrix::Shape;
+use crate::session::Outcome;
+use crate::value::Value;
+
+/// The format used for results in argument and batch mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// One plain result per line, errors on stderr
+    #[default]
+    Text,
+    /// One JSON object per evaluation (JSON Lines)
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format '{}' (expected 'text' or 'json')", name)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// A value as `{"type", "value"}`; a polynomial's value is its coefficients, highest power first,
+/// and a matrix's value is an array of rows
+fn value_json(value: &Value) -> Json {
+    let numbers = |values: &[f64]| Json::Array(values.iter().map(|value| Json::Number(*value)).collect());
+    let inner = match value {
+        Value::Number(number) => Json::Number(*number),
+        Value::Polynomial(polynomial) => Json::Array(polynomial.coefficients().iter().rev().map(|c| Json::Number(*c)).collect()),
+        Value::Vector(values) => numbers(values),
+        Value::Matrix(matrix) => Json::Array((0..matrix.rows()).map(|row| numbers(matrix.row(row))).collect()),
+    };
+    Json::object(vec![("type", Json::string(value.type_name())), ("value", inner)])
+}
+
+/// Describe one evaluation as a JSON object:
+/// `{"input", "result": {"type", "value"}, "formatted", "error": {"kind", "message", "span"}}`
+/// Fields that do not apply are `null`; `formatted` honours `precision`
+/// An estimate like an integral also has `"error_estimate"` in its result
+/// Lines without a number (definitions, settings) have a null result and their message as `formatted`
+/// `diff(f, x)` also has `"derivative": {"variable", "expression"}`; its result is the slope
+/// when x has a value, and otherwise null with the derivative as `formatted`
+pub fn evaluation_json(input: &str, outcome: &Result<Outcome, CalcError>, precision: Option<usize>) -> Json {
+    let (result, formatted, error) = match outcome {
+        Ok(Outcome::Value(value) | Outcome::Assigned(_, value)) => {
+            (value_json(value), Json::string(value.format(precision)), Json::Null)
+        }
+        Ok(Outcome::Estimate(value, estimate)) => {
+            let mut result = value_json(value);
+            if let Json::Object(fields) = &mut result {
+                fields.push(("error_estimate".to_string(), Json::Number(*estimate)));
+            }
+            (result, Json::string(value.format(precision)), Json::Null)
+        }
+        Ok(Outcome::Derivative(_, slope, value)) => {
+            let formatted = value.as_ref().map_or_else(|| slope.to_string(), |value| value.format(precision));
+            (value.as_ref().map_or(Json::Null, value_json), Json::string(formatted), Json::Null)
+        }
+        Ok(Outcome::Defined(name)) => (Json::Null, Json::string(format!("defined {}", name)), Json::Null),
+        Ok(Outcome::Setting(message)) => (Json::Null, Json::string(message.clone()), Json::Null),
+        Err(error) => (Json::Null, Json::Null, error_json(error)),
+    };
+
+    let mut fields = vec![("input", Json::string(input)), ("result", result), ("formatted", formatted), ("error", error)];
+    if let Ok(Outcome::Derivative(variable, slope, _)) = outcome {
+        fields.push(("derivative", Json::object(vec![("variable", Json::string(variable)), ("expression", Json::string(slope.to_string()))])));
+    }
+    Json::object(fields)
+}
+
+/// A shape as its sizes: [3] for a vector, [2, 3] for a matrix
+fn shape_json(shape: Shape) -> Json {
+    let sizes = match shape {
+        Shape::Vector(length) => vec![length],
+        Shape::Matrix(rows, cols) => vec![rows, cols],
+    };
+    Json::Array(sizes.into_iter().map(|size| Json::Number(size as f64)).collect())
+}
+
+/// Describe an error, with its span as byte offsets into the input;
+/// a shape mismatch also lists both shapes
+fn error_json(error: &CalcError) -> Json {
+    let span = match error.span {
+        Some(span) => Json::object(vec![
+            ("start", Json::Number(span.start as f64)),
+            ("end", Json::Number(span.end as f64)),
+        ]),
+        None => Json::Null,
+    };
+
+    let mut fields = vec![
+        ("kind", Json::string(error.kind.name())),
+        ("message", Json::string(error.message.clone())),
+        ("span", span),
+    ];
+    if let ErrorKind::ShapeMismatch(left, right) = error.kind {
+        fields.push(("shapes", Json::Array(vec![shape_json(left), shape_json(right)])));
+    }
+    Json::object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+
+    fn run(input: &str) -> Result<Outcome, CalcError> {
+        Session::new().execute(input)
+    }
+
+    #[test]
+    fn test_parse_output_format() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_successful_evaluation() {
+        let json = evaluation_json("5 + 3", &run("5 + 3"), None);
+        assert_eq!(
+            json.to_string(),
+            r#"{"input":"5 + 3","result":{"type":"number","value":8},"formatted":"8","error":null}"#
+        );
+    }
+
+    #[test]
+    fn test_formatted_uses_precision() {
+        let json = evaluation_json("2 / 3", &run("2 / 3"), Some(2));
+        assert!(json.to_string().contains(r#""formatted":"0.67""#));
+    }
+
+    #[test]
+    fn test_failed_evaluation() {
+        let json = evaluation_json("5 / 0", &run("5 / 0"), None);
+        assert_eq!(
+            json.to_string(),
+            r#"{"input":"5 / 0","result":null,"formatted":null,"error":{"kind":"division_by_zero","message":"Cannot divide by zero!","span":{"start":4,"end":5}}}"#
+        );
+    }
+
+    #[test]
+    fn test_result_type_follows_the_value() {
+        let result = |input: &str| evaluation_json(input, &run(input), None).to_string();
+        assert!(result("poly(1, 0, -2)").contains(r#""result":{"type":"polynomial","value":[1,0,-2]}"#));
+        assert!(result("[1, 2, 3]").contains(r#""result":{"type":"vector","value":[1,2,3]}"#));
+    }
+
+    #[test]
+    fn test_integral_has_error_estimate() {
+        let json = evaluation_json("integrate(x, x, 0, 2)", &run("integrate(x, x, 0, 2)"), None);
+        assert!(json.to_string().contains(r#""result":{"type":"number","value":2,"error_estimate":"#));
+    }
+
+    #[test]
+    fn test_vectors_and_shape_errors() {
+        let json = evaluation_json("[[1, 2], [3, 4]]", &run("[[1, 2], [3, 4]]"), None);
+        assert!(json.to_string().contains(r#""result":{"type":"matrix","value":[[1,2],[3,4]]}"#));
+        let json = evaluation_json("[1, 2] + [1, 2, 3]", &run("[1, 2] + [1, 2, 3]"), None);
+        assert!(json.to_string().contains(r#""kind":"shape_mismatch""#));
+        assert!(json.to_string().ends_with(r#""shapes":[[2],[3]]}}"#));
+    }
+
+    #[test]
+    fn test_derivative_is_described() {
+        let json = evaluation_json("diff(x^2, x)", &run("diff(x^2, x)"), None);
+        assert_eq!(
+            json.to_string(),
+            r#"{"input":"diff(x^2, x)","result":null,"formatted":"2 * x","error":null,"derivative":{"variable":"x","expression":"2 * x"}}"#
+        );
+    }
+
+    #[test]
+    fn test_definition_has_no_result() {
+        let json = evaluation_json("f(x) = x", &run("f(x) = x"), None);
+        assert_eq!(json.to_string(), r#"{"input":"f(x) = x","result":null,"formatted":"defined f","error":null}"#);
+    }
+
+    #[test]
+    fn test_input_is_escaped() {
+        let json = evaluation_json("\"5\" + 3", &run("\"5\" + 3"), None);
+        assert!(json.to_string().starts_with(r#"{"input":"\"5\" + 3","#));
+    }
+}
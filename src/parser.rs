@@ -0,0 +1,445 @@
+// src/parser.rs
+// Turning tokens into an expression tree, following the usual precedence rules:
+// ( ) first, then ^, then unary -, then * and /, then + and -
+
+use crate::ast::{BinaryOp, Expr, ExprKind, Statement, UnaryOp};
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::lexer::{tokenize, Token, TokenKind};
+
+/// Parse a whole line: an expression, an assignment or a function definition
+pub fn parse_statement(input: &str) -> Result<Statement, CalcError> {
+    let mut parser = Parser::new(input);
+
+    let statement = if let Some((name, params)) = parser.definition_head()? {
+        Statement::Define(name, params, parser.expression()?)
+    } else if let Some(name) = parser.assignment_head() {
+        Statement::Assign(name, parser.expression()?)
+    } else {
+        Statement::Expr(parser.expression()?)
+    };
+
+    parser.finish()?;
+    Ok(statement)
+}
+
+/// Parse a line that must be a plain expression
+pub fn parse_expression(input: &str) -> Result<Expr, CalcError> {
+    let mut parser = Parser::new(input);
+    let expr = parser.expression()?;
+    parser.finish()?;
+    Ok(expr)
+}
+
+/// Reads tokens from left to right, one grammar rule per method
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Length of the input, used to point at its end
+    input_len: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Parser { tokens: tokenize(input), position: 0, input_len: input.len() }
+    }
+
+    fn peek_kind(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.position + offset).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consume the next token if it is `kind`
+    fn eat(&mut self, kind: &TokenKind) -> Option<Token> {
+        if self.peek_kind(0) == Some(kind) { self.next() } else { None }
+    }
+
+    /// `name =` at the start of the line
+    fn assignment_head(&mut self) -> Option<String> {
+        match (self.peek_kind(0), self.peek_kind(1)) {
+            (Some(TokenKind::Ident(name)), Some(TokenKind::Equals)) => {
+                let name = name.clone();
+                self.position += 2;
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// `name(a, b) =` at the start of the line
+    /// Looks ahead first, so a call like `f(2)` is left alone
+    fn definition_head(&mut self) -> Result<Option<(String, Vec<String>)>, CalcError> {
+        let Some(TokenKind::Ident(name)) = self.peek_kind(0) else {
+            return Ok(None);
+        };
+        if self.peek_kind(1) != Some(&TokenKind::LParen) {
+            return Ok(None);
+        }
+
+        // Find the matching ')' and check that '=' follows it
+        let mut offset = 2;
+        while let Some(kind) = self.peek_kind(offset) {
+            if *kind == TokenKind::RParen {
+                break;
+            }
+            offset += 1;
+        }
+        if self.peek_kind(offset + 1) != Some(&TokenKind::Equals) {
+            return Ok(None);
+        }
+
+        let name = name.clone();
+        self.position += 2;
+
+        // Parameters: names separated by commas
+        let mut params: Vec<String> = Vec::new();
+        while let Some(token) = self.next() {
+            match token.kind {
+                TokenKind::RParen if params.is_empty() => break,
+                TokenKind::Ident(param) => {
+                    if params.contains(&param) {
+                        return Err(syntax(format!("Parameter '{}' is listed twice", param), token.span));
+                    }
+                    params.push(param);
+                    match self.next() {
+                        Some(Token { kind: TokenKind::Comma, .. }) => continue,
+                        Some(Token { kind: TokenKind::RParen, .. }) => break,
+                        Some(token) => return Err(syntax("Separate parameters with commas, like f(x, y)", token.span)),
+                        None => return Err(self.unexpected_end()),
+                    }
+                }
+                kind => {
+                    return Err(syntax(
+                        format!("Function parameters must be names, like f(x, y) = x + y - not '{}'", describe(&kind)),
+                        token.span,
+                    ))
+                }
+            }
+        }
+
+        // The '=' is known to follow
+        self.position += 1;
+        Ok(Some((name, params)))
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek_kind(0) {
+                Some(TokenKind::Plus) => BinaryOp::Add,
+                Some(TokenKind::Minus) => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.next();
+            let right = self.term()?;
+            left = binary(op, left, right);
+        }
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek_kind(0) {
+                Some(TokenKind::Star) => BinaryOp::Multiply,
+                Some(TokenKind::Slash) => BinaryOp::Divide,
+                _ => return Ok(left),
+            };
+            self.next();
+            let right = self.unary()?;
+            left = binary(op, left, right);
+        }
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        if let Some(minus) = self.eat(&TokenKind::Minus) {
+            let operand = self.unary()?;
+            let span = Span::new(minus.span.start, operand.span.end);
+            return Ok(Expr::new(ExprKind::Unary(UnaryOp::Negate, Box::new(operand)), span));
+        }
+        if self.eat(&TokenKind::Plus).is_some() {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// power := primary ('^' unary)?
+    /// The right side is parsed with `unary`, so 2^3^2 is 2^(3^2) and 2^-1 works
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.primary()?;
+        if self.eat(&TokenKind::Caret).is_some() {
+            let exponent = self.unary()?;
+            return Ok(binary(BinaryOp::Power, base, exponent));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | name | name '(' arguments ')' | '(' expression ')' | '[' items ']'
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        let Some(token) = self.next() else {
+            return Err(self.unexpected_end());
+        };
+
+        match token.kind {
+            TokenKind::Number(text) => match text.parse::<f64>() {
+                Ok(value) => Ok(Expr::new(ExprKind::Number(value), token.span)),
+                Err(_) => Err(CalcError::new(
+                    ErrorKind::InvalidNumber,
+                    format!("'{}' is not a valid number", text),
+                    Some(token.span),
+                )),
+            },
+            TokenKind::Ident(name) => {
+                if self.eat(&TokenKind::LParen).is_some() {
+                    let (args, end) = self.arguments(token.span)?;
+                    return Ok(Expr::new(ExprKind::Call(name, args), Span::new(token.span.start, end)));
+                }
+                Ok(Expr::new(ExprKind::Variable(name), token.span))
+            }
+            TokenKind::LParen => {
+                let mut inner = self.expression()?;
+                match self.eat(&TokenKind::RParen) {
+                    Some(close) => {
+                        inner.span = Span::new(token.span.start, close.span.end);
+                        Ok(inner)
+                    }
+                    None => Err(syntax("Missing ')' to close this '('", token.span)),
+                }
+            }
+            TokenKind::LBracket => {
+                let (items, end) = self.items(token.span)?;
+                Ok(Expr::new(ExprKind::Vector(items), Span::new(token.span.start, end)))
+            }
+            other => Err(self.unexpected(&other, token.span)),
+        }
+    }
+
+    /// Arguments of a call, after the '(' ; returns them and where the ')' ends
+    fn arguments(&mut self, name_span: Span) -> Result<(Vec<Expr>, usize), CalcError> {
+        let mut args = Vec::new();
+        if let Some(close) = self.eat(&TokenKind::RParen) {
+            return Ok((args, close.span.end));
+        }
+
+        loop {
+            args.push(self.expression()?);
+            if self.eat(&TokenKind::Comma).is_some() {
+                continue;
+            }
+            return match self.eat(&TokenKind::RParen) {
+                Some(close) => Ok((args, close.span.end)),
+                None => Err(syntax("Missing ')' to close this function call", name_span)),
+            };
+        }
+    }
+
+    /// The items of a vector like [1, 2, 3], after the '[' ; returns them and where the ']' ends
+    fn items(&mut self, open_span: Span) -> Result<(Vec<Expr>, usize), CalcError> {
+        let mut items = Vec::new();
+        if let Some(close) = self.eat(&TokenKind::RBracket) {
+            return Ok((items, close.span.end));
+        }
+
+        loop {
+            items.push(self.expression()?);
+            if self.eat(&TokenKind::Comma).is_some() {
+                continue;
+            }
+            return match self.eat(&TokenKind::RBracket) {
+                Some(close) => Ok((items, close.span.end)),
+                None => Err(syntax("Missing ']' to close this '['", open_span)),
+            };
+        }
+    }
+
+    /// Make sure nothing is left over after a complete statement
+    fn finish(&mut self) -> Result<(), CalcError> {
+        match self.next() {
+            None => Ok(()),
+            Some(Token { kind: TokenKind::Equals, span }) => {
+                Err(syntax("Only a name can be assigned to, like 'x = 5'", span))
+            }
+            Some(Token { kind: TokenKind::RParen, span }) => Err(syntax("This ')' has no matching '('", span)),
+            Some(Token { kind: TokenKind::RBracket, span }) => Err(syntax("This ']' has no matching '['", span)),
+            Some(token) => Err(self.unexpected(&token.kind, token.span)),
+        }
+    }
+
+    /// Error for a token that cannot appear here
+    fn unexpected(&self, kind: &TokenKind, span: Span) -> CalcError {
+        match kind {
+            TokenKind::Unknown(c) => CalcError::new(
+                ErrorKind::UnknownOperator,
+                format!("Unknown operator '{}'. Use +, -, *, /, or ^", c),
+                Some(span),
+            ),
+            kind => syntax(format!("Unexpected '{}' here", describe(kind)), span),
+        }
+    }
+
+    /// Error for input that stops in the middle of an expression
+    fn unexpected_end(&self) -> CalcError {
+        let span = match self.tokens.last() {
+            Some(last) => last.span,
+            None => Span::new(0, self.input_len),
+        };
+        let message = match self.tokens.last() {
+            Some(last) => format!("Expected a number, name or '(' after '{}'", describe(&last.kind)),
+            None => "Please enter an expression (e.g., '5 + 3')".to_string(),
+        };
+        syntax(message, span)
+    }
+}
+
+/// Build a binary node spanning both operands
+fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+    let span = Span::new(left.span.start, right.span.end);
+    Expr::new(ExprKind::Binary(op, Box::new(left), Box::new(right)), span)
+}
+
+fn syntax(message: impl Into<String>, span: Span) -> CalcError {
+    CalcError::new(ErrorKind::Syntax, message, Some(span))
+}
+
+/// A token as the user typed it, for error messages
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Number(text) | TokenKind::Ident(text) => text.clone(),
+        TokenKind::Plus => "+".to_string(),
+        TokenKind::Minus => "-".to_string(),
+        TokenKind::Star => "*".to_string(),
+        TokenKind::Slash => "/".to_string(),
+        TokenKind::Caret => "^".to_string(),
+        TokenKind::LParen => "(".to_string(),
+        TokenKind::RParen => ")".to_string(),
+        TokenKind::LBracket => "[".to_string(),
+        TokenKind::RBracket => "]".to_string(),
+        TokenKind::Comma => ",".to_string(),
+        TokenKind::Equals => "=".to_string(),
+        TokenKind::Unknown(c) => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(value: f64) -> Expr {
+        Expr::new(ExprKind::Number(value), Span::new(0, 0))
+    }
+
+    fn var(name: &str) -> Expr {
+        Expr::new(ExprKind::Variable(name.to_string()), Span::new(0, 0))
+    }
+
+    fn bin(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        binary(op, left, right)
+    }
+
+    fn neg(operand: Expr) -> Expr {
+        Expr::new(ExprKind::Unary(UnaryOp::Negate, Box::new(operand)), Span::new(0, 0))
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            parse_expression("2 + 3 * 4").unwrap(),
+            bin(BinaryOp::Add, num(2.0), bin(BinaryOp::Multiply, num(3.0), num(4.0)))
+        );
+        assert_eq!(
+            parse_expression("(2 + 3) * 4").unwrap(),
+            bin(BinaryOp::Multiply, bin(BinaryOp::Add, num(2.0), num(3.0)), num(4.0))
+        );
+    }
+
+    #[test]
+    fn test_left_and_right_associativity() {
+        assert_eq!(
+            parse_expression("8 - 3 - 1").unwrap(),
+            bin(BinaryOp::Subtract, bin(BinaryOp::Subtract, num(8.0), num(3.0)), num(1.0))
+        );
+        assert_eq!(
+            parse_expression("2 ^ 3 ^ 2").unwrap(),
+            bin(BinaryOp::Power, num(2.0), bin(BinaryOp::Power, num(3.0), num(2.0)))
+        );
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        // -2^2 is -(2^2), like in maths
+        assert_eq!(parse_expression("-2 ^ 2").unwrap(), neg(bin(BinaryOp::Power, num(2.0), num(2.0))));
+        assert_eq!(parse_expression("2 ^ -1").unwrap(), bin(BinaryOp::Power, num(2.0), neg(num(1.0))));
+        assert_eq!(parse_expression("+x").unwrap(), var("x"));
+    }
+
+    #[test]
+    fn test_calls() {
+        let call = parse_expression("max(1, x + 1)").unwrap();
+        assert_eq!(
+            call.kind,
+            ExprKind::Call("max".to_string(), vec![num(1.0), bin(BinaryOp::Add, var("x"), num(1.0))])
+        );
+        assert_eq!(call.span, Span::new(0, 13));
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vector = parse_expression("[1, [x], []]").unwrap();
+        let inner = |items| Expr::new(ExprKind::Vector(items), Span::new(0, 0));
+        assert_eq!(vector.kind, ExprKind::Vector(vec![num(1.0), inner(vec![var("x")]), inner(vec![])]));
+        assert_eq!(vector.span, Span::new(0, 12));
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(parse_statement("x = 5").unwrap(), Statement::Assign("x".to_string(), num(5.0)));
+        assert_eq!(
+            parse_statement("area(w, h) = w * h").unwrap(),
+            Statement::Define(
+                "area".to_string(),
+                vec!["w".to_string(), "h".to_string()],
+                bin(BinaryOp::Multiply, var("w"), var("h"))
+            )
+        );
+        assert_eq!(
+            parse_statement("area(2, 3)").unwrap(),
+            Statement::Expr(Expr::new(ExprKind::Call("area".to_string(), vec![num(2.0), num(3.0)]), Span::new(0, 0)))
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let expr = parse_expression("5 / (1 - 1)").unwrap();
+        assert_eq!(expr.span, Span::new(0, 11));
+        let ExprKind::Binary(_, _, right) = expr.kind else { panic!("expected a division") };
+        assert_eq!(right.span, Span::new(4, 11));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let cases = [
+            ("5 +", ErrorKind::Syntax, Span::new(2, 3)),
+            ("(5 + 3", ErrorKind::Syntax, Span::new(0, 1)),
+            ("5 + 3)", ErrorKind::Syntax, Span::new(5, 6)),
+            ("[1, 2", ErrorKind::Syntax, Span::new(0, 1)),
+            ("[1, 2]]", ErrorKind::Syntax, Span::new(6, 7)),
+            ("5 3", ErrorKind::Syntax, Span::new(2, 3)),
+            ("2 + 3 = 5", ErrorKind::Syntax, Span::new(6, 7)),
+            ("5 % 3", ErrorKind::UnknownOperator, Span::new(2, 3)),
+            ("1.2.3 + 1", ErrorKind::InvalidNumber, Span::new(0, 5)),
+            ("f(1) = 2", ErrorKind::Syntax, Span::new(2, 3)),
+            ("f(x, x) = 2", ErrorKind::Syntax, Span::new(5, 6)),
+            ("", ErrorKind::Syntax, Span::new(0, 0)),
+        ];
+        for (input, kind, span) in cases {
+            let error = parse_statement(input).unwrap_err();
+            assert_eq!((error.kind, error.span), (kind, Some(span)), "for input {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,512 @@
+// src/plot.rs
+// Plotting functions: `plot sin(x), cos(x), x, -pi, pi` draws a chart in the terminal,
+// and `plot ... > chart.svg` saves it as an SVG picture instead
+//
+// Each character of a terminal chart is a braille pattern, a grid of 2 x 4 dots,
+// so a chart 60 characters wide has 120 points across and 4 dots per line of height
+
+use crate::ast::Expr;
+use crate::color::Palette;
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::parser::parse_expression;
+use crate::stats;
+use crate::svg::{self, Svg};
+
+/// Lines of text a terminal chart takes up, not counting the axis labels and legend
+pub const CHART_HEIGHT: usize = 16;
+
+/// Charts are this many columns wide when the terminal width is unknown
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How many points of each function go into an SVG chart
+pub const SVG_SAMPLES: usize = 600;
+
+/// Split `f, x, a, b > chart.svg` into the plot and the file to save it to, if any
+pub fn split_output(argument: &str) -> (&str, Option<&str>) {
+    match argument.rsplit_once('>') {
+        Some((plot, path)) => (plot.trim_end(), Some(path.trim())),
+        None => (argument, None),
+    }
+}
+
+/// What `plot` was asked to draw: expressions in one variable over a range
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotRequest {
+    /// The expressions, each with the text it was typed as (for the legend)
+    pub series: Vec<(String, Expr)>,
+    pub variable: String,
+    pub low: Expr,
+    pub high: Expr,
+}
+
+/// Read `f, g, ..., x, low, high`
+/// Spans in errors and in the expressions point into `text`
+pub fn parse_plot(text: &str) -> Result<PlotRequest, CalcError> {
+    let parts = split_top_level(text);
+    if parts.len() < 4 {
+        return Err(CalcError::new(
+            ErrorKind::Syntax,
+            "Say what to plot and where, like 'plot sin(x), x, -pi, pi'",
+            Some(Span::new(0, text.len())),
+        ));
+    }
+
+    let parse = |(start, end): (usize, usize)| parse_expression(&format!("{}{}", " ".repeat(start), &text[start..end]));
+    let count = parts.len() - 3;
+    let series = parts[..count].iter().map(|&part| Ok((text[part.0..part.1].trim().to_string(), parse(part)?))).collect::<Result<_, CalcError>>()?;
+
+    let (start, end) = parts[count];
+    let variable = text[start..end].trim();
+    if variable.is_empty() || !variable.chars().all(|c| c.is_alphanumeric() || c == '_') || variable.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(CalcError::new(ErrorKind::Syntax, format!("'{}' is not a variable name", variable), Some(Span::new(start, end))));
+    }
+    Ok(PlotRequest { series, variable: variable.to_string(), low: parse(parts[count + 1])?, high: parse(parts[count + 2])? })
+}
+
+/// Where the comma-separated parts of `text` start and end, skipping commas inside brackets like f(1, 2)
+fn split_top_level(text: &str) -> Vec<(usize, usize)> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push((start, index));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push((start, text.len()));
+    parts
+}
+
+/// One line on a chart: its name and its value at each x (NaN where it has none)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub label: String,
+    pub ys: Vec<f64>,
+}
+
+/// Everything needed to draw a chart: the x values and each series at them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub variable: String,
+    pub xs: Vec<f64>,
+    pub series: Vec<Series>,
+}
+
+impl Chart {
+    /// The smallest and largest x
+    pub fn x_range(&self) -> (f64, f64) {
+        (self.xs[0], self.xs[self.xs.len() - 1])
+    }
+
+    /// The y values to show, rounded out to the ticks that `target` ticks would use
+    /// A few huge values (like tan near its poles) do not squash everything else flat:
+    /// if they would stretch the range more than ten times, the top and bottom 2% are cut off
+    pub fn y_range(&self, target: usize) -> (f64, f64) {
+        let values: Vec<f64> = self.series.iter().flat_map(|series| series.ys.iter().copied()).filter(|y| y.is_finite()).collect();
+        let (mut low, mut high) = match (stats::min(&values), stats::max(&values)) {
+            (Some(low), Some(high)) => (low, high),
+            _ => (-1.0, 1.0),
+        };
+        if let (Some(trimmed_low), Some(trimmed_high)) = (stats::quantile(&values, 0.02), stats::quantile(&values, 0.98))
+            && trimmed_high > trimmed_low
+            && high - low > 10.0 * (trimmed_high - trimmed_low)
+        {
+            (low, high) = (trimmed_low, trimmed_high);
+        }
+        if low == high {
+            let pad = if low == 0.0 { 1.0 } else { low.abs() / 10.0 };
+            (low, high) = (low - pad, high + pad);
+        }
+        let step = tick_step(low, high, target);
+        ((low / step).floor() * step, (high / step).ceil() * step)
+    }
+}
+
+/// A round distance between ticks (1, 2 or 5 times a power of ten) giving about `target` ticks
+pub fn tick_step(low: f64, high: f64, target: usize) -> f64 {
+    // Far from 0 the ticks cannot be closer than the numbers can tell apart
+    let rough = ((high - low) / target.max(1) as f64).max(high.abs().max(low.abs()) * 4.0 * f64::EPSILON);
+    let power = 10_f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].into_iter().map(|factor| factor * power).find(|step| *step >= rough).unwrap_or(10.0 * power)
+}
+
+/// The round numbers from `low` to `high` to put ticks at
+pub fn ticks(low: f64, high: f64, target: usize) -> Vec<f64> {
+    let step = tick_step(low, high, target);
+    let first = (low / step).ceil() as i64;
+    let last = (high / step + 1e-9).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// Most digits after the point in a tick label; a number holds no more than this anyway
+const MAX_LABEL_DECIMALS: f64 = 15.0;
+
+/// A tick label with just enough decimals for the step between ticks: 0.5, 1, 1.5
+/// Very big and very small numbers get an exponent instead: 2e300, 1.5e-7
+pub fn tick_label(value: f64, step: f64) -> String {
+    let step_power = step.log10().floor();
+    let label = if value != 0.0 && (value.abs() >= 1e6 || value.abs() < 1e-4) {
+        let decimals = (value.abs().log10().floor() - step_power).clamp(0.0, MAX_LABEL_DECIMALS);
+        format!("{:.*e}", decimals as usize, value)
+    } else if value == 0.0 && step < 1e-4 {
+        "0".to_string()
+    } else {
+        format!("{:.*}", (-step_power).clamp(0.0, MAX_LABEL_DECIMALS) as usize, value)
+    };
+    // Don't show "-0"
+    if label.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') { label.trim_start_matches('-').to_string() } else { label }
+}
+
+/// Whether the line from sample `i` to the next one would cross a pole (like 1/x at 0)
+/// instead of following the function: the jump is taller than the whole chart, or it is
+/// bigger than the steps on both sides and goes the other way from both of them
+pub fn crosses_pole(ys: &[f64], i: usize, height: f64) -> bool {
+    let jump = ys[i + 1] - ys[i];
+    if jump.abs() > height {
+        return true;
+    }
+    // A missing neighbour is NaN, and NaN never counts as a smaller step the other way
+    let before = if i > 0 { ys[i] - ys[i - 1] } else { f64::NAN };
+    let after = ys.get(i + 2).map_or(f64::NAN, |next| next - ys[i + 1]);
+    [before, after].iter().all(|side| side.abs() < jump.abs() && side * jump < 0.0)
+}
+
+/// The bit for each dot of a braille character, by [row][column]
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Draw a chart in braille characters, `width` columns wide with axes, ticks and a legend
+/// Each series gets its own color from the palette
+pub fn render_terminal(chart: &Chart, width: usize, palette: &Palette) -> String {
+    let rows = CHART_HEIGHT;
+    let (x_low, x_high) = chart.x_range();
+    let (y_low, y_high) = chart.y_range(rows / 3);
+    let y_step = tick_step(y_low, y_high, rows / 3);
+    let y_ticks = ticks(y_low, y_high, rows / 3);
+    let y_labels: Vec<String> = y_ticks.iter().map(|tick| tick_label(*tick, y_step)).collect();
+    let label_width = y_labels.iter().map(|label| label.chars().count()).max().unwrap_or(1);
+    // The label, a space and the axis come before the plot itself
+    let columns = width.saturating_sub(label_width + 2).max(10);
+
+    // Each cell holds its dots and the series drawn there last
+    let mut cells = vec![vec![(0_u8, 0_usize); columns]; rows];
+    let (dots_across, dots_down) = (columns * 2, rows * 4);
+    let dot_row = |y: f64| {
+        let position = (y_high - y) / (y_high - y_low) * (dots_down - 1) as f64;
+        (y.is_finite() && (-0.5..dots_down as f64 - 0.5).contains(&position)).then(|| position.round() as usize)
+    };
+    for (index, series) in chart.series.iter().enumerate() {
+        let (mut previous, mut previous_sample): (Option<usize>, usize) = (None, 0);
+        for dot_column in 0..dots_across {
+            // The sample closest to this column of dots
+            let sample = (dot_column * (chart.xs.len() - 1) + (dots_across - 1) / 2) / (dots_across - 1).max(1);
+            let current = dot_row(series.ys[sample]);
+            if (previous_sample..sample).any(|i| crosses_pole(&series.ys, i, y_high - y_low)) {
+                previous = None;
+            }
+            previous_sample = sample;
+            if let Some(row) = current {
+                // Join this dot to the one before, so steep lines stay unbroken
+                let (from, to) = match previous {
+                    Some(before) if before < row => (before + 1, row),
+                    Some(before) if before > row => (row, before - 1),
+                    _ => (row, row),
+                };
+                for dot_row in from..=to {
+                    let cell = &mut cells[dot_row / 4][dot_column / 2];
+                    cell.0 |= BRAILLE_DOTS[dot_row % 4][dot_column % 2];
+                    cell.1 = index;
+                }
+            }
+            previous = current;
+        }
+    }
+
+    // The rows of the chart, with the y axis and its labels on the left
+    let mut lines = Vec::new();
+    for (row, cells) in cells.iter().enumerate() {
+        let tick = y_ticks.iter().position(|tick| ((y_high - tick) / (y_high - y_low) * (rows - 1) as f64).round() as usize == row);
+        let (label, axis) = match tick {
+            Some(tick) => (y_labels[tick].as_str(), '┤'),
+            None => ("", '│'),
+        };
+        let mut line = format!("{:>width$} {}", label, axis, width = label_width);
+        for (dots, series) in cells {
+            if *dots == 0 {
+                line.push(' ');
+            } else {
+                let character = char::from_u32(0x2800 + *dots as u32).unwrap_or(' ');
+                line.push_str(&palette.paint_series(*series, &character.to_string()));
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    // The x axis, with a tick under each round number and the numbers below that
+    let x_step = tick_step(x_low, x_high, columns / 12);
+    let mut axis: Vec<char> = "─".repeat(columns).chars().collect();
+    let mut labels = vec![' '; label_width + 2 + columns];
+    let mut free_from = 0;
+    for tick in ticks(x_low, x_high, columns / 12) {
+        let column = ((tick - x_low) / (x_high - x_low) * (columns - 1) as f64).round() as usize;
+        axis[column] = '┬';
+        let label: Vec<char> = tick_label(tick, x_step).chars().collect();
+        // Centred under its tick, but moved left if it would run off the end
+        let start = (label_width + 2 + column).saturating_sub(label.len() / 2).min(labels.len().saturating_sub(label.len()));
+        if start >= free_from && start + label.len() <= labels.len() {
+            labels[start..start + label.len()].copy_from_slice(&label);
+            free_from = start + label.len() + 1;
+        }
+    }
+    lines.push(format!("{} └{}", " ".repeat(label_width), axis.iter().collect::<String>()));
+    lines.push(labels.iter().collect::<String>().trim_end().to_string());
+
+    // Which color is which
+    let legend: Vec<String> =
+        chart.series.iter().enumerate().map(|(index, series)| format!("{} {}", palette.paint_series(index, "⣿"), series.label)).collect();
+    lines.push(format!("{}  {}", " ".repeat(label_width), legend.join("   ")));
+    lines.join("\n") + "\n"
+}
+
+/// Size of an SVG chart and the room around the plot for labels and the legend
+const SVG_WIDTH: f64 = 800.0;
+const SVG_HEIGHT: f64 = 500.0;
+const SVG_LEFT: f64 = 70.0;
+const SVG_RIGHT: f64 = 30.0;
+const SVG_TOP: f64 = 20.0;
+const SVG_BOTTOM: f64 = 90.0;
+
+/// The color of each line in an SVG chart, in the same order as in the terminal
+const SVG_COLORS: [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e", "#17becf"];
+
+/// Draw a chart as a standalone SVG picture, with gridlines at the ticks,
+/// labelled axes, one colored line per series and a legend below
+pub fn render_svg(chart: &Chart) -> String {
+    let (x_low, x_high) = chart.x_range();
+    let (y_low, y_high) = chart.y_range(6);
+    let (plot_width, plot_height) = (SVG_WIDTH - SVG_LEFT - SVG_RIGHT, SVG_HEIGHT - SVG_TOP - SVG_BOTTOM);
+    // From chart values to pixels; y grows downwards in SVG
+    let px = |x: f64| SVG_LEFT + (x - x_low) / (x_high - x_low) * plot_width;
+    let py = |y: f64| SVG_TOP + (y_high - y) / (y_high - y_low) * plot_height;
+    let (left, right, top, bottom) = (SVG_LEFT, SVG_LEFT + plot_width, SVG_TOP, SVG_TOP + plot_height);
+
+    let mut picture = Svg::new(SVG_WIDTH, SVG_HEIGHT);
+    let font = || ("font-family", "sans-serif".to_string());
+    picture.element("rect", &[("width", "100%".to_string()), ("height", "100%".to_string()), ("fill", "white".to_string())]);
+    // Lines are cut off at the edges of the plot
+    picture.open("clipPath", &[("id", "plot-area".to_string())]);
+    picture.element("rect", &[("x", svg::number(left)), ("y", svg::number(top)), ("width", svg::number(plot_width)), ("height", svg::number(plot_height))]);
+    picture.close("clipPath");
+
+    // Gridlines and tick labels
+    picture.open("g", &[("stroke", "#e0e0e0".to_string()), ("stroke-width", "1".to_string())]);
+    let x_ticks = ticks(x_low, x_high, 8);
+    let y_ticks = ticks(y_low, y_high, 6);
+    for x in &x_ticks {
+        picture.element("line", &[("x1", svg::number(px(*x))), ("y1", svg::number(top)), ("x2", svg::number(px(*x))), ("y2", svg::number(bottom))]);
+    }
+    for y in &y_ticks {
+        picture.element("line", &[("x1", svg::number(left)), ("y1", svg::number(py(*y))), ("x2", svg::number(right)), ("y2", svg::number(py(*y)))]);
+    }
+    picture.close("g");
+    picture.open("g", &[font(), ("font-size", "12".to_string()), ("fill", "#333".to_string())]);
+    let x_step = tick_step(x_low, x_high, 8);
+    for x in &x_ticks {
+        let label = tick_label(*x, x_step);
+        picture.text(&label, &[("x", svg::number(px(*x))), ("y", svg::number(bottom + 18.0)), ("text-anchor", "middle".to_string())]);
+    }
+    let y_step = tick_step(y_low, y_high, 6);
+    for y in &y_ticks {
+        let label = tick_label(*y, y_step);
+        picture.text(&label, &[("x", svg::number(left - 8.0)), ("y", svg::number(py(*y) + 4.0)), ("text-anchor", "end".to_string())]);
+    }
+    picture.text(&chart.variable, &[("x", svg::number((left + right) / 2.0)), ("y", svg::number(bottom + 38.0)), ("text-anchor", "middle".to_string())]);
+    picture.close("g");
+
+    // The axes
+    let axes = format!("M {} {} V {} H {}", svg::number(left), svg::number(top), svg::number(bottom), svg::number(right));
+    picture.element("path", &[("d", axes), ("fill", "none".to_string()), ("stroke", "#333".to_string()), ("stroke-width", "1.5".to_string())]);
+
+    // Each series as lines between its points, broken where it has no value,
+    // leaves the plot or jumps across a pole (so tan is not joined up past pi/2)
+    picture.open("g", &[("clip-path", "url(#plot-area)".to_string()), ("fill", "none".to_string()), ("stroke-width", "2".to_string())]);
+    for (index, series) in chart.series.iter().enumerate() {
+        let color = SVG_COLORS[index % SVG_COLORS.len()];
+        let inside = |y: f64| (y_low..=y_high).contains(&y);
+        let mut pieces: Vec<Vec<(f64, f64)>> = Vec::new();
+        let mut piece: Vec<(f64, f64)> = Vec::new();
+        for (i, (x, y)) in chart.xs.iter().zip(&series.ys).enumerate() {
+            if !y.is_finite() {
+                pieces.push(std::mem::take(&mut piece));
+                continue;
+            }
+            if i > 0 && crosses_pole(&series.ys, i - 1, y_high - y_low) {
+                pieces.push(std::mem::take(&mut piece));
+            }
+            // A point outside joins the line next to it only if that one is inside
+            let previous_inside = i > 0 && inside(series.ys[i - 1]);
+            let next_inside = series.ys.get(i + 1).is_some_and(|next| inside(*next));
+            if inside(*y) || previous_inside || next_inside {
+                // Keep far away points near the plot, so the lines to them still head the right way
+                let y = y.clamp(y_low - (y_high - y_low), y_high + (y_high - y_low));
+                piece.push((px(*x), py(y)));
+            }
+            if !inside(*y) && !next_inside {
+                pieces.push(std::mem::take(&mut piece));
+            }
+        }
+        pieces.push(piece);
+        for piece in pieces.iter().filter(|piece| piece.len() > 1) {
+            let points: Vec<String> = piece.iter().map(|(x, y)| format!("{},{}", svg::number(*x), svg::number(*y))).collect();
+            picture.element("polyline", &[("points", points.join(" ")), ("stroke", color.to_string())]);
+        }
+    }
+    picture.close("g");
+
+    // The legend: a short line in each color and what it is
+    picture.open("g", &[font(), ("font-size", "13".to_string()), ("fill", "#333".to_string())]);
+    let mut x = left;
+    let y = SVG_HEIGHT - 18.0;
+    for (index, series) in chart.series.iter().enumerate() {
+        let color = SVG_COLORS[index % SVG_COLORS.len()];
+        picture.element("line", &[("x1", svg::number(x)), ("y1", svg::number(y - 4.0)), ("x2", svg::number(x + 24.0)), ("y2", svg::number(y - 4.0)), ("stroke", color.to_string()), ("stroke-width", "3".to_string())]);
+        picture.text(&series.label, &[("x", svg::number(x + 30.0)), ("y", svg::number(y))]);
+        // Roughly the width of the label, and a gap
+        x += 30.0 + 7.5 * series.label.chars().count() as f64 + 24.0;
+    }
+    picture.close("g");
+    picture.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(ys: &[&[f64]]) -> Chart {
+        let xs = (0..ys[0].len()).map(|i| i as f64).collect();
+        let series = ys.iter().enumerate().map(|(i, ys)| Series { label: format!("f{}", i), ys: ys.to_vec() }).collect();
+        Chart { variable: "x".to_string(), xs, series }
+    }
+
+    #[test]
+    fn test_parse_plot() {
+        let request = parse_plot("sin(x), max(x, 1), x, -pi, 2 * pi").unwrap();
+        let labels: Vec<&str> = request.series.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["sin(x)", "max(x, 1)"]);
+        assert_eq!((request.variable.as_str(), request.high.to_string().as_str()), ("x", "2 * pi"));
+        // Spans point into the whole command
+        assert_eq!(request.low.span, Span::new(22, 25));
+        assert_eq!(parse_plot("sin(x), x, 0").unwrap_err().kind, ErrorKind::Syntax);
+        assert_eq!(parse_plot("sin(x), 2, 0, 1").unwrap_err().span, Some(Span::new(7, 9)));
+        assert_eq!(parse_plot("sin(x, x, 0, 1").unwrap_err().kind, ErrorKind::Syntax);
+    }
+
+    #[test]
+    fn test_ticks() {
+        assert_eq!(ticks(0.0, 10.0, 5), [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(ticks(-3.2, 3.2, 6), [-2.0, 0.0, 2.0]);
+        assert_eq!(ticks(-3.2, 3.2, 7), [-3.0, -2.0, -1.0, 0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(tick_step(0.0, 0.3, 5), 0.1);
+        assert_eq!(tick_label(0.5, 0.5), "0.5");
+        assert_eq!(tick_label(-0.0, 1.0), "0");
+        assert_eq!(tick_label(20.0, 5.0), "20");
+        // Very big and very small numbers get an exponent
+        assert_eq!(tick_label(4e299, 2e299), "4e299");
+        assert_eq!(tick_label(2.5e-7, 5e-8), "2.5e-7");
+        assert_eq!(tick_label(0.0, 5e-8), "0");
+        assert_eq!(tick_label(1e300, 1e-100).len(), "1.000000000000000e300".len());
+        // Ticks far from 0 are still as close as the numbers allow
+        assert_eq!(ticks(1e15, 1e15 + 1.0, 3), [1e15, 1e15 + 1.0]);
+        assert_eq!(tick_label(1e15 + 1.0, 1.0), "1.000000000000001e15");
+    }
+
+    #[test]
+    fn test_crosses_pole() {
+        // 1/x from -0.3 to 0.3 jumps from -10 to 10 between the middle samples
+        let ys = [-1.0 / 0.3, -5.0, -10.0, 10.0, 5.0, 1.0 / 0.3];
+        assert!(crosses_pole(&ys, 2, 100.0));
+        assert!(!crosses_pole(&ys, 1, 100.0) && !crosses_pole(&ys, 3, 100.0));
+        // A steep but smooth rise is joined up, unless it is taller than the chart
+        let ys = [0.0, 1.0, 10.0, 11.0];
+        assert!(!crosses_pole(&ys, 1, 100.0));
+        assert!(crosses_pole(&ys, 1, 5.0));
+    }
+
+    #[test]
+    fn test_y_range() {
+        assert_eq!(chart(&[&[0.2, 0.9], &[-0.7, 0.1]]).y_range(5), (-1.0, 1.0));
+        assert_eq!(chart(&[&[0.2, 0.9], &[-0.7, 0.1]]).y_range(8), (-0.8, 1.0));
+        // A flat line still gets some room
+        assert_eq!(chart(&[&[3.0, 3.0]]).y_range(2), (2.5, 3.5));
+        // One huge value among many small ones is cut off
+        let mut ys: Vec<f64> = (0..100).map(|i| (i as f64 / 10.0).sin()).collect();
+        ys[50] = 1e9;
+        assert_eq!(chart(&[&ys]).y_range(4), (-1.0, 1.0));
+        assert_eq!(chart(&[&[f64::NAN, f64::NAN]]).y_range(4), (-1.0, 1.0));
+    }
+
+    #[test]
+    fn test_render_terminal() {
+        let xs: Vec<f64> = (0..=100).map(|i| i as f64 / 10.0).collect();
+        let line = Series { label: "x".to_string(), ys: xs.clone() };
+        let flat = Series { label: "5".to_string(), ys: vec![5.0; xs.len()] };
+        let chart = Chart { variable: "x".to_string(), xs, series: vec![line, flat] };
+        let text = render_terminal(&chart, 40, &Palette::plain());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), CHART_HEIGHT + 3);
+        assert!(lines.iter().all(|line| line.chars().count() <= 40));
+        // The y axis is labelled at its ends, and the line starts bottom left and ends top right
+        assert_eq!(lines[0], "10 ┤                                  ⡠⠊");
+        assert_eq!(lines[CHART_HEIGHT - 1], " 0 ┤⡠⠊");
+        assert!(lines[8].contains("⠉⠉⠉"));
+        assert!(lines[CHART_HEIGHT].starts_with("   └┬"));
+        assert!(lines[CHART_HEIGHT + 1].starts_with("    0"));
+        assert_eq!(lines[CHART_HEIGHT + 2], "    ⣿ x   ⣿ 5");
+
+        // With colors, each series has its own
+        let colored = render_terminal(&chart, 40, &Palette::new(Default::default(), true));
+        assert!(colored.contains(&Palette::new(Default::default(), true).paint_series(1, "⣿")));
+        assert_ne!(Palette::new(Default::default(), true).paint_series(0, "⣿"), Palette::new(Default::default(), true).paint_series(1, "⣿"));
+    }
+
+    #[test]
+    fn test_split_output() {
+        assert_eq!(split_output("sin(x), x, 0, pi > chart.svg"), ("sin(x), x, 0, pi", Some("chart.svg")));
+        assert_eq!(split_output("sin(x), x, 0, pi"), ("sin(x), x, 0, pi", None));
+        assert_eq!(split_output("x, x, 0, 1 >"), ("x, x, 0, 1", Some("")));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let xs: Vec<f64> = (0..=4).map(|i| i as f64).collect();
+        let gap = vec![1.0, 2.0, f64::NAN, 2.0, 1.0];
+        let line = Series { label: "x".to_string(), ys: xs.clone() };
+        let chart = Chart { variable: "t".to_string(), xs: xs.clone(), series: vec![line, Series { label: "g".to_string(), ys: gap }] };
+        let picture = render_svg(&chart);
+        assert!(picture.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(picture.ends_with("</svg>\n"));
+        // The gap splits the second series in two, and each series has its color and legend entry
+        assert_eq!(picture.matches("<polyline").count(), 3);
+        assert_eq!(picture.matches("stroke=\"#d62728\"").count(), 3);
+        assert!(picture.contains(">g</text>") && picture.contains(">t</text>"));
+        // Gridlines and labels at the ticks
+        assert!(picture.contains("<text x=\"420\" y=\"428\" text-anchor=\"middle\">2.0</text>"));
+
+        // A point far off the chart is not joined to another one far off the other way
+        let xs: Vec<f64> = (0..100).map(|i| i as f64 / 10.0).collect();
+        let mut ys: Vec<f64> = xs.iter().map(|x| x.sin()).collect();
+        (ys[50], ys[51]) = (1e9, -1e9);
+        let spike = Chart { variable: "t".to_string(), xs, series: vec![Series { label: "g".to_string(), ys }] };
+        assert_eq!(render_svg(&spike).matches("<polyline").count(), 2);
+
+        // Nor are the two sides of a pole inside the chart
+        let xs: Vec<f64> = (0..100).map(|i| (i as f64 - 49.5) / 50.0).collect();
+        let ys = xs.iter().map(|x| 1.0 / x).collect();
+        let pole = Chart { variable: "x".to_string(), xs, series: vec![Series { label: "1/x".to_string(), ys }] };
+        assert_eq!(render_svg(&pole).matches("<polyline").count(), 2);
+    }
+}
@@ -0,0 +1,375 @@
+// src/polynomial.rs
+// Polynomials in x as values: poly(1, 0, -2) is x^2 - 2, and they can be added,
+// multiplied, divided, differentiated, evaluated and solved for all their roots
+
+use std::fmt;
+
+use crate::calculator::format_number;
+use crate::solve::Complex;
+
+/// Coefficients this much smaller than the largest one count as zero when dividing
+const TOLERANCE: f64 = 1e-9;
+
+/// Most rounds of the Durand-Kerner root finder
+const MAX_ITERATIONS: usize = 1000;
+
+/// A polynomial in x, like x^2 - 2
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polynomial {
+    /// `coefficients[i]` goes with x^i; the last one is never 0, so the zero polynomial has none
+    coefficients: Vec<f64>,
+}
+
+impl Polynomial {
+    /// Build from coefficients lowest power first: [-2, 0, 1] is x^2 - 2
+    pub fn new(mut coefficients: Vec<f64>) -> Self {
+        while coefficients.last() == Some(&0.0) {
+            coefficients.pop();
+        }
+        Polynomial { coefficients }
+    }
+
+    /// Build from coefficients highest power first, the way `poly(1, 0, -2)` writes them
+    pub fn from_highest(coefficients: &[f64]) -> Self {
+        Polynomial::new(coefficients.iter().rev().copied().collect())
+    }
+
+    pub fn constant(value: f64) -> Self {
+        Polynomial::new(vec![value])
+    }
+
+    /// The coefficients, lowest power first
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefficients
+    }
+
+    /// The highest power of x, or None for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// The coefficient of the highest power (0 for the zero polynomial)
+    fn leading(&self) -> f64 {
+        self.coefficients.last().copied().unwrap_or(0.0)
+    }
+
+    /// The value at x, worked out with Horner's rule: ((a x + b) x + c) ...
+    pub fn evaluate(&self, x: f64) -> f64 {
+        self.coefficients.iter().rev().fold(0.0, |total, c| total * x + c)
+    }
+
+    /// The value at a complex x
+    pub fn evaluate_complex(&self, x: Complex) -> Complex {
+        self.coefficients.iter().rev().fold(Complex::real(0.0), |total, c| total.times(x).plus(Complex::real(*c)))
+    }
+
+    pub fn plus(&self, other: &Polynomial) -> Polynomial {
+        let length = self.coefficients.len().max(other.coefficients.len());
+        let coefficient = |p: &Polynomial, i: usize| p.coefficients.get(i).copied().unwrap_or(0.0);
+        Polynomial::new((0..length).map(|i| coefficient(self, i) + coefficient(other, i)).collect())
+    }
+
+    pub fn minus(&self, other: &Polynomial) -> Polynomial {
+        self.plus(&other.scaled(-1.0))
+    }
+
+    pub fn times(&self, other: &Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return Polynomial::default();
+        }
+        let mut product = vec![0.0; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                product[i + j] += a * b;
+            }
+        }
+        Polynomial::new(product)
+    }
+
+    /// Multiply every coefficient by `factor`
+    pub fn scaled(&self, factor: f64) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().map(|c| c * factor).collect())
+    }
+
+    /// Multiply by itself `power` times (p^0 is 1)
+    pub fn power(&self, power: u32) -> Polynomial {
+        (0..power).fold(Polynomial::constant(1.0), |total, _| total.times(self))
+    }
+
+    /// Long division: the quotient and remainder, so that self = quotient * divisor + remainder
+    /// Returns None when dividing by the zero polynomial
+    pub fn divide(&self, divisor: &Polynomial) -> Option<(Polynomial, Polynomial)> {
+        let divisor_degree = divisor.degree()?;
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![0.0; remainder.len().saturating_sub(divisor_degree)];
+        // Take away the right multiple of the divisor to clear each leading term in turn
+        for power in (divisor_degree..remainder.len()).rev() {
+            let factor = remainder[power] / divisor.leading();
+            quotient[power - divisor_degree] = factor;
+            for (i, c) in divisor.coefficients.iter().enumerate() {
+                remainder[power - divisor_degree + i] -= factor * c;
+            }
+            remainder[power] = 0.0;
+        }
+        remainder.truncate(divisor_degree);
+        Some((Polynomial::new(quotient), Polynomial::new(remainder)))
+    }
+
+    /// The greatest common divisor, scaled so its leading coefficient is 1
+    /// Remainder coefficients that are tiny next to the numbers that cancelled
+    /// to make them are rounding errors and count as zero
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while let Some(remainder) = a.rounded_remainder(&b) {
+            a = b;
+            b = remainder;
+        }
+        a.monic()
+    }
+
+    /// The remainder of `divide`, with rounding errors cleared to zero
+    /// Each coefficient is compared with the numbers that went into it, not with
+    /// the other coefficients, so a genuinely tiny one like 1e-20 survives
+    fn rounded_remainder(&self, divisor: &Polynomial) -> Option<Polynomial> {
+        let divisor_degree = divisor.degree()?;
+        let mut remainder = self.coefficients.clone();
+        let mut sizes: Vec<f64> = remainder.iter().map(|c| c.abs()).collect();
+        for power in (divisor_degree..remainder.len()).rev() {
+            let factor = remainder[power] / divisor.leading();
+            for (i, c) in divisor.coefficients.iter().enumerate() {
+                let index = power - divisor_degree + i;
+                remainder[index] -= factor * c;
+                sizes[index] = sizes[index].max((factor * c).abs());
+                if remainder[index].abs() <= TOLERANCE * sizes[index] {
+                    remainder[index] = 0.0;
+                }
+            }
+            remainder[power] = 0.0;
+        }
+        remainder.truncate(divisor_degree);
+        Some(Polynomial::new(remainder))
+    }
+
+    /// Divide by the leading coefficient, so it becomes 1
+    fn monic(&self) -> Polynomial {
+        if self.is_zero() { self.clone() } else { self.scaled(1.0 / self.leading()) }
+    }
+
+    /// The derivative: each a x^n becomes n a x^(n-1)
+    pub fn derivative(&self) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().enumerate().skip(1).map(|(power, c)| power as f64 * c).collect())
+    }
+
+    /// Every root, complex ones included, repeated roots as often as they repeat,
+    /// smallest real part first. The zero polynomial has no list of roots, so it gives none.
+    ///
+    /// Repeated roots are hard to pin down directly, so they are split off first:
+    /// gcd(p, p') holds each repeated root once less than p does, and p / gcd(p, p')
+    /// has every root exactly once, which Durand-Kerner finds accurately.
+    pub fn roots(&self) -> Vec<Complex> {
+        let Some(degree) = self.degree() else {
+            return Vec::new();
+        };
+        let repeated = self.gcd(&self.derivative());
+        let mut roots = match repeated.degree() {
+            Some(extra) if extra > 0 && extra < degree => {
+                let (distinct, _) = self.divide(&repeated).unwrap_or_default();
+                let mut roots = distinct.durand_kerner();
+                roots.extend(repeated.roots());
+                roots
+            }
+            _ => self.durand_kerner(),
+        };
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        roots
+    }
+
+    /// The Durand-Kerner method: start with a guess for every root at once, then
+    /// keep moving each guess z to z - p(z) / (product of its distances to the other guesses)
+    fn durand_kerner(&self) -> Vec<Complex> {
+        // Roots at 0 are exact, and easy to take out first
+        let zeros = self.coefficients.iter().take_while(|c| **c == 0.0).count();
+        let rest = Polynomial::new(self.coefficients[zeros..].to_vec()).monic();
+        let mut roots = vec![Complex::real(0.0); zeros];
+        let Some(degree) = rest.degree() else {
+            return roots;
+        };
+
+        // Spread the first guesses around a circle that holds every root
+        let radius = 1.0 + rest.coefficients[..degree].iter().fold(0.0, |largest: f64, c| c.abs().max(largest));
+        let mut guesses: Vec<Complex> = (0..degree)
+            .map(|k| {
+                let angle = std::f64::consts::TAU * k as f64 / degree as f64 + 0.4;
+                Complex { re: radius * angle.cos(), im: radius * angle.sin() }
+            })
+            .collect();
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut largest_step: f64 = 0.0;
+            for k in 0..degree {
+                let distances = (0..degree)
+                    .filter(|&j| j != k)
+                    .fold(Complex::real(1.0), |product, j| product.times(guesses[k].minus(guesses[j])));
+                let step = rest.evaluate_complex(guesses[k]).divided_by(distances);
+                if step.re.is_finite() && step.im.is_finite() {
+                    guesses[k] = guesses[k].minus(step);
+                    // Measure the step against the root itself, so tiny roots get every digit too
+                    largest_step = largest_step.max(step.abs() / guesses[k].abs().max(f64::MIN_POSITIVE));
+                }
+            }
+            if largest_step <= 1e-15 {
+                break;
+            }
+        }
+
+        // A real or imaginary part that is tiny next to the root itself is a rounding error
+        // (next to the root, not next to 1, so genuinely small roots like 1e-10 survive)
+        for guess in &mut guesses {
+            let size = guess.abs();
+            if guess.im.abs() <= 1e-9 * size {
+                guess.im = 0.0;
+            }
+            if guess.re.abs() <= 1e-9 * size {
+                guess.re = 0.0;
+            }
+        }
+        roots.extend(guesses);
+        roots
+    }
+
+    /// Show, rounding the coefficients to `precision` decimal places if one is given
+    pub fn format(&self, precision: Option<usize>) -> String {
+        let mut text = String::new();
+        for (power, c) in self.coefficients.iter().enumerate().rev() {
+            if *c == 0.0 {
+                continue;
+            }
+            let sign = match (text.is_empty(), *c < 0.0) {
+                (true, true) => "-",
+                (true, false) => "",
+                (false, true) => " - ",
+                (false, false) => " + ",
+            };
+            // "x" rather than "1x", but a plain 1 stays
+            let size = if c.abs() == 1.0 && power > 0 { String::new() } else { format_number(c.abs(), precision) };
+            let x = match power {
+                0 => String::new(),
+                1 => "x".to_string(),
+                _ => format!("x^{}", power),
+            };
+            text.push_str(&format!("{}{}{}", sign, size, x));
+        }
+        if text.is_empty() { "0".to_string() } else { text }
+    }
+
+    /// How to write this polynomial in the calculator, like "poly(1, 0, -2)"
+    pub fn source(&self) -> String {
+        let coefficients: Vec<String> = self.coefficients.iter().rev().map(|c| c.to_string()).collect();
+        format!("poly({})", if coefficients.is_empty() { "0".to_string() } else { coefficients.join(", ") })
+    }
+}
+
+// Shown the usual way, highest power first: "x^2 - 2"
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coefficients: &[f64]) -> Polynomial {
+        Polynomial::from_highest(coefficients)
+    }
+
+    fn real_roots(p: &Polynomial) -> Vec<f64> {
+        p.roots()
+            .iter()
+            .map(|root| {
+                assert_eq!(root.im, 0.0, "{} has a complex root {}", p, root);
+                root.re
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(poly(&[1.0, 0.0, -2.0]).to_string(), "x^2 - 2");
+        assert_eq!(poly(&[-3.0, 1.0, -1.0, 0.5]).to_string(), "-3x^3 + x^2 - x + 0.5");
+        assert_eq!(poly(&[0.0, 0.0, 1.0]).to_string(), "1");
+        assert_eq!(poly(&[]).to_string(), "0");
+        assert_eq!(poly(&[2.0, 1.0 / 3.0]).format(Some(2)), "2.00x + 0.33");
+        assert_eq!(poly(&[1.0, 0.0, -2.0]).source(), "poly(1, 0, -2)");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let p = poly(&[1.0, 1.0]);
+        let q = poly(&[1.0, -1.0]);
+        assert_eq!(p.plus(&q), poly(&[2.0, 0.0]));
+        assert_eq!(p.minus(&p), poly(&[]));
+        assert_eq!(p.times(&q), poly(&[1.0, 0.0, -1.0]));
+        assert_eq!(p.power(3), poly(&[1.0, 3.0, 3.0, 1.0]));
+        assert_eq!(p.evaluate(2.0), 3.0);
+        assert_eq!(poly(&[1.0, 0.0, -2.0]).derivative(), poly(&[2.0, 0.0]));
+    }
+
+    #[test]
+    fn test_long_division() {
+        // x^3 - 2x + 5 = (x - 1)(x^2 + x - 1) + 4
+        let (quotient, remainder) = poly(&[1.0, 0.0, -2.0, 5.0]).divide(&poly(&[1.0, -1.0])).unwrap();
+        assert_eq!(quotient, poly(&[1.0, 1.0, -1.0]));
+        assert_eq!(remainder, poly(&[4.0]));
+
+        let (quotient, remainder) = poly(&[1.0, 2.0]).divide(&poly(&[1.0, 0.0, 0.0])).unwrap();
+        assert!(quotient.is_zero());
+        assert_eq!(remainder, poly(&[1.0, 2.0]));
+        assert_eq!(poly(&[1.0]).divide(&poly(&[])), None);
+    }
+
+    #[test]
+    fn test_gcd() {
+        // (x - 1)(x + 2) and (x - 1)(x - 3) share x - 1
+        let gcd = poly(&[1.0, 1.0, -2.0]).gcd(&poly(&[2.0, -8.0, 6.0]));
+        assert_eq!(gcd, poly(&[1.0, -1.0]));
+        assert_eq!(poly(&[1.0, 0.0, 1.0]).gcd(&poly(&[1.0, 1.0])), poly(&[1.0]));
+    }
+
+    #[test]
+    fn test_roots() {
+        assert_close(&real_roots(&poly(&[1.0, 0.0, -2.0])), &[-2f64.sqrt(), 2f64.sqrt()]);
+        assert_close(&real_roots(&poly(&[1.0, -6.0, 11.0, -6.0])), &[1.0, 2.0, 3.0]);
+        assert_close(&real_roots(&poly(&[1.0, 0.0, 0.0])), &[0.0, 0.0]);
+        // Repeated roots: (x - 1)^3 (x + 2)
+        let p = poly(&[1.0, -1.0]).power(3).times(&poly(&[1.0, 2.0]));
+        assert_close(&real_roots(&p), &[-2.0, 1.0, 1.0, 1.0]);
+
+        let roots: Vec<String> = poly(&[1.0, 0.0, 1.0]).roots().iter().map(|root| root.to_string()).collect();
+        assert_eq!(roots, vec!["-1i", "1i"]);
+        assert!(poly(&[5.0]).roots().is_empty());
+    }
+
+    #[test]
+    fn test_tiny_roots_are_kept() {
+        let roots = real_roots(&poly(&[1.0, 1e10, 1.0]));
+        assert!((roots[0] + 1e10).abs() < 1e-3, "{:?}", roots);
+        assert!((roots[1] + 1e-10).abs() < 1e-19, "{:?}", roots);
+        assert_eq!(real_roots(&poly(&[1.0, -1e-12])), vec![1e-12]);
+
+        let roots = poly(&[1.0, 0.0, 1e-20]).roots();
+        assert!(roots.iter().all(|root| root.re == 0.0 && (root.im.abs() - 1e-10).abs() < 1e-19), "{:?}", roots);
+    }
+}
@@ -0,0 +1,108 @@
+// src/random.rs
+// A small random number generator for rand(), randint(a, b) and seed(n)
+//
+// It is SplitMix64: a counter that goes up by a fixed odd step, scrambled into
+// a random-looking number. The same seed always gives the same numbers, which
+// makes calculations (and tests) with random numbers repeatable
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The amount the state moves on each step (2^64 divided by the golden ratio)
+const STEP: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Random numbers from a seed; `Cell` lets calculations draw numbers
+/// while only borrowing the session
+#[derive(Debug)]
+pub struct Random {
+    state: Cell<u64>,
+}
+
+impl Random {
+    /// A generator that always gives the same numbers for the same seed
+    pub fn new(seed: u64) -> Self {
+        Random { state: Cell::new(seed) }
+    }
+
+    /// Start again from `seed`
+    pub fn seed(&self, seed: u64) {
+        self.state.set(seed);
+    }
+
+    /// The next number, with all 64 bits random
+    pub fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(STEP);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to (but not including) 1
+    pub fn next_f64(&self) -> f64 {
+        // The top 53 bits fill an f64 exactly
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// A whole number from `low` to `high`, both included, each equally likely
+    pub fn between(&self, low: i64, high: i64) -> i64 {
+        assert!(low <= high, "the range must not be empty");
+        let count = (high as i128 - low as i128 + 1) as u128;
+        if count > u64::MAX as u128 {
+            return self.next_u64() as i64;
+        }
+        // Throw away the numbers past the last whole multiple of `count`,
+        // or the smaller results would come up slightly more often
+        let count = count as u64;
+        let limit = u64::MAX - u64::MAX % count;
+        loop {
+            let draw = self.next_u64();
+            if draw < limit {
+                return (low as i128 + (draw % count) as i128) as i64;
+            }
+        }
+    }
+}
+
+/// Seeded from the clock, so each run gets different numbers until `seed` is used
+impl Default for Random {
+    fn default() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        Random::new(nanos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let (a, b) = (Random::new(42), Random::new(42));
+        let first: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        a.seed(42);
+        assert_eq!(a.next_u64(), first[0]);
+        // The published first output of SplitMix64 from seed 0
+        assert_eq!(Random::new(0).next_u64(), 0xE220_A839_7B1D_CDAF);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let random = Random::new(7);
+        for _ in 0..1000 {
+            let x = random.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            let die = random.between(1, 6);
+            assert!((1..=6).contains(&die));
+        }
+        assert_eq!(random.between(5, 5), 5);
+        // Every face comes up, about equally often
+        let mut counts = [0; 6];
+        for _ in 0..6000 {
+            counts[random.between(0, 5) as usize] += 1;
+        }
+        assert!(counts.iter().all(|count| (850..1150).contains(count)), "{:?}", counts);
+    }
+}
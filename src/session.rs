@@ -0,0 +1,773 @@
+// src/session.rs
+// A calculator session: variables, functions, settings and history,
+// plus saving it all as a script that can be loaded again later
+
+use std::collections::BTreeMap;
+
+use crate::ast::{Expr, ExprKind, Statement};
+use crate::batch::strip_comment;
+use crate::builtins;
+use crate::calculus;
+use crate::csv::Table;
+use crate::calculator::{Context, Function, Mode};
+use crate::cli;
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::explain::Explanation;
+use crate::journal::{Change, Entry, Journal};
+use crate::parser::{parse_expression, parse_statement};
+use crate::plot::{self, Chart, Series};
+use crate::polynomial::Polynomial;
+use crate::random::Random;
+use crate::solve::{self, parse_equation, Complex, Solution};
+use crate::value::Value;
+
+/// Saved history lines start with this, so replaying the script skips them
+/// as comments but `load` can still restore them
+pub const HISTORY_MARKER: &str = "#>";
+
+/// What happened when a line was run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// An expression was calculated
+    Value(Value),
+    /// An expression was estimated, like an integral, and may be off by about the number given
+    Estimate(Value, f64),
+    /// `name = expression` stored a variable
+    Assigned(String, Value),
+    /// `name(params) = body` defined a function
+    Defined(String),
+    /// `mode` or `precision` showed or changed a setting; the text describes it
+    Setting(String),
+    /// `diff(f, x)` worked out the derivative of f by the variable x,
+    /// and its value when x has one
+    Derivative(String, Expr, Option<Value>),
+}
+
+impl Outcome {
+    /// The value this line produced, if any
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            Outcome::Value(value) | Outcome::Estimate(value, _) | Outcome::Assigned(_, value) => Some(value),
+            Outcome::Derivative(_, _, value) => value.as_ref(),
+            Outcome::Defined(_) | Outcome::Setting(_) => None,
+        }
+    }
+}
+
+/// A name that `load` gave a different meaning than it had before
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub name: String,
+    /// What the name meant before loading
+    pub old: String,
+    /// What it means now
+    pub new: String,
+}
+
+/// What happened while loading a saved session
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    /// Number of lines that ran successfully
+    pub loaded: usize,
+    /// Existing variables and functions that were replaced
+    pub conflicts: Vec<Conflict>,
+    /// Lines that failed, with their line numbers
+    pub errors: Vec<(usize, CalcError)>,
+}
+
+/// Everything a calculator run remembers from one line to the next
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Past calculations, formatted as "expression = result"
+    pub history: Vec<String>,
+    /// Most entries the history keeps; the oldest are dropped first (0 keeps everything)
+    pub history_size: usize,
+    /// Numeric mode used for new calculations
+    pub mode: Mode,
+    /// Decimal places shown in results (None shows the full value)
+    pub precision: Option<usize>,
+    /// Values stored with `name = expression`
+    pub variables: BTreeMap<String, Value>,
+    /// Functions defined with `name(params) = body`
+    pub functions: BTreeMap<String, Function>,
+    /// Where rand() and randint() get their numbers; seed(n) resets it
+    pub random: Random,
+    /// Changes that `undo` and `redo` can step through
+    journal: Journal,
+}
+
+/// If `input` is `command` (optionally followed by an argument), return the argument
+pub fn command_argument<'a>(input: &'a str, command: &str) -> Option<&'a str> {
+    let (word, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    if word.eq_ignore_ascii_case(command) {
+        Some(argument.trim())
+    } else {
+        None
+    }
+}
+
+/// Like `command_argument`, but `mode = 3` or `precision(2)` are about names, not settings
+fn setting_argument<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    command_argument(line, command).filter(|argument| !argument.starts_with('=') && !argument.starts_with('('))
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run one line: a setting command, an assignment, a function definition or an expression
+    pub fn execute(&mut self, line: &str) -> Result<Outcome, CalcError> {
+        // `mode` and `precision` are commands, unless they are being used as names
+        let (mode, precision) = (self.mode, self.precision);
+        let setting = if let Some(argument) = setting_argument(line, "mode") {
+            Some(self.change_mode(argument))
+        } else {
+            setting_argument(line, "precision").map(|argument| self.change_precision(argument))
+        };
+        if let Some(result) = setting {
+            let message =
+                result.map_err(|message| CalcError::new(ErrorKind::InvalidArgument, message, Some(Span::new(0, line.len()))))?;
+            if self.mode != mode {
+                self.journal.record(line, Change::Mode(mode));
+            }
+            if self.precision != precision {
+                self.journal.record(line, Change::Precision(precision));
+            }
+            return Ok(Outcome::Setting(message));
+        }
+
+        match parse_statement(line)? {
+            Statement::Expr(ref expr) if let Some((function, variable)) = self.symbolic_diff(expr) => {
+                let slope = self.derivative(function, variable)?;
+                let value = if self.variables.contains_key(variable) { Some(self.context().evaluate(expr)?) } else { None };
+                match &value {
+                    Some(value) => self.record(format!("{} = {}", line, self.format(value))),
+                    None => self.record(format!("{} = {}", line, slope)),
+                }
+                Ok(Outcome::Derivative(variable.to_string(), slope, value))
+            }
+            Statement::Expr(expr) => match self.context().estimate(&expr)? {
+                (value, Some(error)) => {
+                    self.record(format!("{} {}", line, self.format_estimate(&value, error)));
+                    Ok(Outcome::Estimate(value, error))
+                }
+                (value, None) => {
+                    self.record(format!("{} = {}", line, self.format(&value)));
+                    Ok(Outcome::Value(value))
+                }
+            },
+            Statement::Assign(name, expr) => {
+                let value = self.context().evaluate(&expr)?;
+                let old = self.variables.insert(name.clone(), value.clone());
+                self.journal.record(line, Change::Variable(name.clone(), old));
+                self.record(format!("{} = {}", name, self.format(&value)));
+                Ok(Outcome::Assigned(name, value))
+            }
+            Statement::Define(name, params, body) => {
+                let old = self.functions.insert(name.clone(), Function { params, body });
+                self.journal.record(line, Change::Function(name.clone(), old));
+                Ok(Outcome::Defined(name))
+            }
+        }
+    }
+
+    /// Calculate a plain expression and remember it in the history
+    pub fn evaluate(&mut self, expression: &str) -> Result<Value, CalcError> {
+        let expr = parse_expression(expression)?;
+        let result = self.context().evaluate(&expr)?;
+        self.record(format!("{} = {}", expression, self.format(&result)));
+        Ok(result)
+    }
+
+    /// Work out an expression step by step, without changing the session
+    pub fn explain(&self, expression: &str) -> Result<Explanation, CalcError> {
+        let expr = parse_expression(expression)?;
+        self.context().explain(&expr)
+    }
+
+    /// If `expr` is `diff(f, x)` with a plain variable x (and `diff` is not one of
+    /// the user's functions), f and x: the line is about the derivative itself
+    fn symbolic_diff<'a>(&self, expr: &'a Expr) -> Option<(&'a Expr, &'a str)> {
+        match &expr.kind {
+            ExprKind::Call(name, args) if name == "diff" && !self.functions.contains_key("diff") => match args.as_slice() {
+                [function, Expr { kind: ExprKind::Variable(variable), .. }] => Some((function, variable)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The derivative of `expr` by `variable`, seeing through the session's functions
+    pub fn derivative(&self, expr: &Expr, variable: &str) -> Result<Expr, CalcError> {
+        calculus::derivative(&calculus::inline_functions(expr, &self.functions)?, variable)
+    }
+
+    /// Solve an equation like `x^2 - 2 = 0 for x` or `cos(x) = x for x in 0, 1`
+    pub fn solve(&self, text: &str) -> Result<Solution, CalcError> {
+        let equation = parse_equation(text)?;
+        let context = self.context();
+        let interval = match &equation.interval {
+            Some((low, high)) => Some((context.eval(low)?, context.eval(high)?)),
+            None => None,
+        };
+        solve::solve(&equation.function, &equation.variable, interval, &context)
+    }
+
+    /// Work out `plot f, g, ..., x, low, high` at `samples` evenly spaced points
+    /// Points where a function has no value (like ln(x) for x <= 0) are left out;
+    /// if a function has no value anywhere, the first problem is the error
+    pub fn plot(&self, text: &str, samples: usize) -> Result<Chart, CalcError> {
+        let request = plot::parse_plot(text)?;
+        let context = self.context();
+        let (low, high) = (context.eval(&request.low)?, context.eval(&request.high)?);
+        if !(low.is_finite() && high.is_finite() && low < high) {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                format!("The range to plot must go from a smaller to a larger number, not {} to {}", low, high),
+                Some(Span::new(request.low.span.start, request.high.span.end)),
+            ));
+        }
+        let samples = samples.max(2);
+        let xs: Vec<f64> = (0..samples).map(|i| low + (high - low) * i as f64 / (samples - 1) as f64).collect();
+
+        let mut variables = self.variables.clone();
+        let mut series = Vec::new();
+        for (label, expr) in request.series {
+            let mut ys = Vec::with_capacity(samples);
+            let mut problem = None;
+            for x in &xs {
+                variables.insert(request.variable.clone(), Value::Number(*x));
+                let context = Context { variables: &variables, functions: &self.functions, mode: Mode::Float, random: &self.random };
+                match context.eval(&expr) {
+                    Ok(y) => ys.push(y),
+                    Err(error) => {
+                        problem.get_or_insert(error);
+                        ys.push(f64::NAN);
+                    }
+                }
+            }
+            if let Some(error) = problem
+                && ys.iter().all(|y| y.is_nan())
+            {
+                return Err(error);
+            }
+            series.push(Series { label, ys });
+        }
+        Ok(Chart { variable: request.variable, xs, series })
+    }
+
+    /// Every root of a polynomial, like `roots poly(1, 0, -2)` or `roots p`
+    pub fn roots(&self, expression: &str) -> Result<Vec<Complex>, CalcError> {
+        let expr = parse_expression(expression)?;
+        let polynomial = match self.context().evaluate(&expr)? {
+            Value::Polynomial(polynomial) => polynomial,
+            Value::Number(number) => Polynomial::constant(number),
+            other => {
+                return Err(CalcError::new(
+                    ErrorKind::TypeMismatch,
+                    format!("Expected a polynomial here, but this is a {}", other.type_name()),
+                    Some(expr.span),
+                ))
+            }
+        };
+        if polynomial.is_zero() {
+            return Err(CalcError::new(ErrorKind::InvalidArgument, "Every number is a root of 0", Some(expr.span)));
+        }
+        Ok(polynomial.roots())
+    }
+
+    /// Is `name` a variable or function this session knows about (built-ins included)?
+    pub fn knows(&self, name: &str) -> bool {
+        self.variables.contains_key(name) || self.functions.contains_key(name) || builtins::is_builtin(name)
+    }
+
+    /// Format a result using the session's precision
+    pub fn format(&self, value: &Value) -> String {
+        value.format(self.precision)
+    }
+
+    /// An estimate with its error, like "≈ 0.333333 ± 4e-15"
+    pub fn format_estimate(&self, value: &Value, error: f64) -> String {
+        // One significant digit is all an error estimate is good for
+        let error = if error == 0.0 { "0".to_string() } else { format!("{:.0e}", error) };
+        format!("≈ {} ± {}", self.format(value), error)
+    }
+
+    /// Everything an expression in this session can see
+    fn context(&self) -> Context<'_> {
+        Context { variables: &self.variables, functions: &self.functions, mode: self.mode, random: &self.random }
+    }
+
+    /// Add a history entry, dropping the oldest ones past `history_size`
+    fn record(&mut self, entry: String) {
+        self.history.push(entry);
+        if self.history_size > 0 && self.history.len() > self.history_size {
+            let extra = self.history.len() - self.history_size;
+            self.history.drain(..extra);
+        }
+    }
+
+    /// Forget every past calculation (`undo` brings them back)
+    pub fn clear_history(&mut self) {
+        let cleared = std::mem::take(&mut self.history);
+        self.journal.record("clear", Change::History(cleared));
+    }
+
+    /// Reverse the last assignment, definition, setting change, clear or load
+    /// Returns what was undone, or None if there is nothing left to undo
+    pub fn undo(&mut self) -> Option<String> {
+        let entry = self.journal.take_undo()?;
+        let change = self.put_back(entry.change);
+        self.journal.push_redo(Entry { action: entry.action.clone(), change });
+        Some(entry.action)
+    }
+
+    /// Apply the last undone action again
+    /// Returns what was redone, or None if there is nothing to redo
+    pub fn redo(&mut self) -> Option<String> {
+        let entry = self.journal.take_redo()?;
+        let change = self.put_back(entry.change);
+        self.journal.push_undo(Entry { action: entry.action.clone(), change });
+        Some(entry.action)
+    }
+
+    /// Put saved state back, returning the state it replaced
+    fn put_back(&mut self, change: Change) -> Change {
+        match change {
+            Change::Variable(name, value) => {
+                let replaced = match value {
+                    Some(value) => self.variables.insert(name.clone(), value),
+                    None => self.variables.remove(&name),
+                };
+                Change::Variable(name, replaced)
+            }
+            Change::Function(name, function) => {
+                let replaced = match function {
+                    Some(function) => self.functions.insert(name.clone(), function),
+                    None => self.functions.remove(&name),
+                };
+                Change::Function(name, replaced)
+            }
+            Change::Mode(mode) => Change::Mode(std::mem::replace(&mut self.mode, mode)),
+            Change::Precision(precision) => Change::Precision(std::mem::replace(&mut self.precision, precision)),
+            Change::History(history) => Change::History(std::mem::replace(&mut self.history, history)),
+            Change::Group(changes) => {
+                // Undo the newest change first, then keep the result in oldest-first order
+                let mut replaced: Vec<Change> = changes.into_iter().rev().map(|change| self.put_back(change)).collect();
+                replaced.reverse();
+                Change::Group(replaced)
+            }
+        }
+    }
+
+    /// Show the numeric mode, or switch to a new one
+    fn change_mode(&mut self, argument: &str) -> Result<String, String> {
+        if argument.is_empty() {
+            return Ok(format!("Current mode: {}", self.mode));
+        }
+        self.mode = argument.to_lowercase().parse()?;
+        Ok(format!("Mode set to {}", self.mode))
+    }
+
+    /// Show the result precision, or change it ("off" shows full results again)
+    fn change_precision(&mut self, argument: &str) -> Result<String, String> {
+        if argument.is_empty() {
+            return Ok(match self.precision {
+                Some(places) => format!("Results are shown with {} decimal places", places),
+                None => "Results are shown in full".to_string(),
+            });
+        }
+        if argument.eq_ignore_ascii_case("off") {
+            self.precision = None;
+            return Ok("Results will be shown in full".to_string());
+        }
+        let places = cli::parse_precision(argument)?;
+        self.precision = Some(places);
+        Ok(format!("Results will be shown with {} decimal places", places))
+    }
+
+    /// Write the session as a script that rebuilds it when run or loaded:
+    /// variables and functions, then the settings and (as comments) the history
+    pub fn save_script(&self) -> String {
+        let mut script = String::new();
+        script.push_str(&format!("# Rust Calculator session saved by {}\n", cli::version()));
+        script.push_str("# Restore it with: load <this file>\n");
+
+        // Values are restored in float mode, since integer mode would reject
+        // a saved fraction like 0.5; the real settings come after them
+        script.push_str("mode float\n");
+
+        for (name, value) in &self.variables {
+            match value.source() {
+                Some(source) => script.push_str(&format!("{} = {}\n", name, source)),
+                None => script.push_str(&format!("# {} = {} (cannot be saved)\n", name, value)),
+            }
+        }
+
+        for (name, function) in &self.functions {
+            script.push_str(&format!("{}{}\n", name, function));
+        }
+
+        script.push_str(&format!("mode {}\n", self.mode));
+        match self.precision {
+            Some(places) => script.push_str(&format!("precision {}\n", places)),
+            None => script.push_str("precision off\n"),
+        }
+
+        for entry in &self.history {
+            script.push_str(&format!("{} {}\n", HISTORY_MARKER, entry));
+        }
+
+        script
+    }
+
+    /// Run a saved session script in this session
+    /// Failing lines are reported and skipped; variables and functions that
+    /// end up with a different meaning than before are reported as conflicts
+    pub fn load_script(&mut self, script: &str) -> LoadReport {
+        let old_variables = self.variables.clone();
+        let old_functions = self.functions.clone();
+        let history_before = self.history.len();
+        let journal_before = self.journal.len();
+        let mut restored_history = Vec::new();
+        let mut report = LoadReport::default();
+
+        for (index, line) in script.lines().enumerate() {
+            if let Some(entry) = line.trim().strip_prefix(HISTORY_MARKER) {
+                restored_history.push(entry.trim().to_string());
+                continue;
+            }
+
+            let code = strip_comment(line).trim();
+            if code.is_empty() {
+                continue;
+            }
+            match self.execute(code) {
+                Ok(_) => report.loaded += 1,
+                Err(error) => report.errors.push((index + 1, error)),
+            }
+        }
+
+        // The whole load is undone in one step
+        self.journal.group_since(journal_before, "load");
+
+        // Running the script's lines is not new work, so the history is the saved one
+        self.history.truncate(history_before);
+        for entry in restored_history {
+            self.record(entry);
+        }
+
+        for (name, old) in &old_variables {
+            match self.variables.get(name) {
+                Some(new) if new != old => report.conflicts.push(Conflict {
+                    name: name.clone(),
+                    old: self.format(old),
+                    new: self.format(new),
+                }),
+                _ => {}
+            }
+        }
+        for (name, old) in &old_functions {
+            match self.functions.get(name) {
+                Some(new) if new != old => report.conflicts.push(Conflict {
+                    name: format!("{}()", name),
+                    old: format!("{}{}", name, old),
+                    new: format!("{}{}", name, new),
+                }),
+                _ => {}
+            }
+        }
+
+        report
+    }
+
+    /// Keep each column of a table as a vector called `name.column`, like d.price,
+    /// dropping the columns of an earlier table with the same name
+    /// Returns the new variable names; `undo` takes the whole table back in one step
+    pub fn load_table(&mut self, name: &str, table: &Table) -> Vec<String> {
+        let journal_before = self.journal.len();
+        let prefix = format!("{}.", name);
+        let action = format!("load as {}", name);
+
+        let stale: Vec<String> = self.variables.keys().filter(|key| key.starts_with(&prefix)).cloned().collect();
+        for variable in stale {
+            let old = self.variables.remove(&variable);
+            self.journal.record(&action, Change::Variable(variable, old));
+        }
+        let mut names = Vec::new();
+        for column in &table.columns {
+            let variable = format!("{}{}", prefix, column.name);
+            let old = self.variables.insert(variable.clone(), Value::Vector(column.values.clone()));
+            self.journal.record(&action, Change::Variable(variable.clone(), old));
+            names.push(variable);
+        }
+
+        self.journal.group_since(journal_before, &action);
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_records_history() {
+        let mut session = Session::new();
+        assert_eq!(session.evaluate("2 * 3").unwrap(), 6.0);
+        assert!(session.evaluate("2 / 0").is_err());
+        assert_eq!(session.history, vec!["2 * 3 = 6".to_string()]);
+    }
+
+    #[test]
+    fn test_history_size_drops_oldest() {
+        let mut session = Session::new();
+        session.history_size = 2;
+        for expression in ["1 + 1", "2 + 2", "3 + 3"] {
+            session.evaluate(expression).unwrap();
+        }
+        assert_eq!(session.history, vec!["2 + 2 = 4".to_string(), "3 + 3 = 6".to_string()]);
+    }
+
+    #[test]
+    fn test_command_argument() {
+        assert_eq!(command_argument("mode", "mode"), Some(""));
+        assert_eq!(command_argument("MODE  integer", "mode"), Some("integer"));
+        assert_eq!(command_argument("modest", "mode"), None);
+        assert_eq!(command_argument("5 + 3", "mode"), None);
+    }
+
+    #[test]
+    fn test_variables_and_functions() {
+        let mut session = Session::new();
+        assert_eq!(session.execute("rate = 0.25").unwrap(), Outcome::Assigned("rate".to_string(), Value::Number(0.25)));
+        assert_eq!(session.execute("tax(price) = price * rate").unwrap(), Outcome::Defined("tax".to_string()));
+        assert_eq!(session.execute("tax(80) + 1").unwrap(), Outcome::Value(Value::Number(21.0)));
+        assert_eq!(session.history, vec!["rate = 0.25".to_string(), "tax(80) + 1 = 21".to_string()]);
+        assert!(session.knows("rate") && session.knows("tax") && !session.knows("price"));
+    }
+
+    #[test]
+    fn test_settings_commands() {
+        let mut session = Session::new();
+        assert_eq!(session.execute("mode integer").unwrap(), Outcome::Setting("Mode set to integer".to_string()));
+        assert_eq!(session.mode, Mode::Integer);
+        session.execute("precision 2").unwrap();
+        assert_eq!(session.precision, Some(2));
+        assert_eq!(session.execute("mode complex").unwrap_err().kind, ErrorKind::InvalidArgument);
+
+        // A variable may still be called "mode"
+        assert_eq!(session.execute("mode = 3").unwrap(), Outcome::Assigned("mode".to_string(), Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut session = Session::new();
+        for line in ["x = 1", "x = 2", "f(a) = a", "mode integer"] {
+            session.execute(line).unwrap();
+        }
+        // Plain calculations are not undoable actions
+        session.execute("x + 1").unwrap();
+
+        assert_eq!(session.undo().as_deref(), Some("mode integer"));
+        assert_eq!(session.mode, Mode::Float);
+        assert_eq!(session.undo().as_deref(), Some("f(a) = a"));
+        assert!(!session.functions.contains_key("f"));
+        assert_eq!(session.undo().as_deref(), Some("x = 2"));
+        assert_eq!(session.variables["x"], 1.0);
+
+        assert_eq!(session.redo().as_deref(), Some("x = 2"));
+        assert_eq!(session.variables["x"], 2.0);
+        assert_eq!(session.redo().as_deref(), Some("f(a) = a"));
+        assert!(session.functions.contains_key("f"));
+
+        // A new action means the rest can no longer be redone
+        session.execute("y = 5").unwrap();
+        assert_eq!(session.redo(), None);
+
+        for _ in 0..4 {
+            assert!(session.undo().is_some());
+        }
+        assert_eq!(session.undo(), None);
+        assert!(session.variables.is_empty());
+    }
+
+    #[test]
+    fn test_undo_clear_and_load() {
+        let mut session = Session::new();
+        session.execute("1 + 1").unwrap();
+        session.clear_history();
+        assert!(session.history.is_empty());
+        assert_eq!(session.undo().as_deref(), Some("clear"));
+        assert_eq!(session.history, vec!["1 + 1 = 2".to_string()]);
+
+        session.execute("x = 1").unwrap();
+        let report = session.load_script("x = 5\nscale(v) = v * x\nprecision 2\n");
+        assert_eq!(report.loaded, 3);
+        assert_eq!(session.undo().as_deref(), Some("load"));
+        assert_eq!(session.variables["x"], 1.0);
+        assert!(session.functions.is_empty());
+        assert_eq!(session.precision, None);
+        assert_eq!(session.redo().as_deref(), Some("load"));
+        assert_eq!(session.variables["x"], 5.0);
+        assert_eq!(session.precision, Some(2));
+    }
+
+    #[test]
+    fn test_load_table() {
+        let mut session = Session::new();
+        session.execute("d.old = 1").unwrap();
+        let table = crate::csv::parse("item,price,qty\nnuts,1.5,4\nbolts,2,10\n", ',').unwrap();
+        assert_eq!(session.load_table("d", &table), ["d.price", "d.qty"]);
+        assert_eq!(session.evaluate("sum(d.price * d.qty)").unwrap(), 26.0);
+        assert!(!session.variables.contains_key("d.old"));
+
+        assert_eq!(session.undo().as_deref(), Some("load as d"));
+        assert_eq!(session.variables.keys().collect::<Vec<_>>(), ["d.old"]);
+    }
+
+    #[test]
+    fn test_save_script() {
+        let mut session = Session::new();
+        for line in ["precision 3", "x = 5", "y = -0.5", "area(w, h) = w * h", "x + 1"] {
+            session.execute(line).unwrap();
+        }
+        let script = session.save_script();
+        let lines: Vec<&str> = script.lines().filter(|line| !line.starts_with("# ")).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "mode float",
+                "x = 5",
+                "y = -0.5",
+                "area(w, h) = w * h",
+                "mode float",
+                "precision 3",
+                "#> x = 5.000",
+                "#> y = -0.500",
+                "#> x + 1 = 6.000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mut original = Session::new();
+        for line in ["mode integer", "count = 12", "half(n) = n / 2", "half(count)"] {
+            original.execute(line).unwrap();
+        }
+
+        let mut restored = Session::new();
+        let report = restored.load_script(&original.save_script());
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(restored.mode, Mode::Integer);
+        assert_eq!(restored.variables, original.variables);
+        assert_eq!(restored.functions, original.functions);
+        assert_eq!(restored.history, original.history);
+        assert_eq!(restored.execute("half(count) + 1").unwrap(), Outcome::Value(Value::Number(7.0)));
+    }
+
+    #[test]
+    fn test_integer_mode_keeps_fractions_on_round_trip() {
+        let mut original = Session::new();
+        for line in ["x = 0.5", "mode integer"] {
+            original.execute(line).unwrap();
+        }
+
+        // Loading into a session that is already in integer mode works too
+        let mut restored = Session::new();
+        restored.execute("mode integer").unwrap();
+        let report = restored.load_script(&original.save_script());
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(restored.mode, Mode::Integer);
+        assert_eq!(restored.variables, original.variables);
+    }
+
+    #[test]
+    fn test_load_reports_conflicts_and_errors() {
+        let mut session = Session::new();
+        for line in ["x = 1", "keep = 2", "f(a) = a + 1"] {
+            session.execute(line).unwrap();
+        }
+
+        let report = session.load_script("x = 10\nkeep = 2\nf(a) = a * 2\nbroken = 1 +\n");
+        assert_eq!(report.loaded, 3);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, 4);
+        assert_eq!(
+            report.conflicts,
+            vec![
+                Conflict { name: "x".to_string(), old: "1".to_string(), new: "10".to_string() },
+                Conflict { name: "f()".to_string(), old: "f(a) = a + 1".to_string(), new: "f(a) = a * 2".to_string() },
+            ]
+        );
+        assert_eq!(session.variables["x"], 10.0);
+    }
+
+    #[test]
+    fn test_symbolic_derivative() {
+        let mut session = Session::new();
+        let Outcome::Derivative(variable, slope, None) = session.execute("diff(x^3, x)").unwrap() else {
+            panic!("diff(f, x) should give the derivative");
+        };
+        assert_eq!((variable.as_str(), slope.to_string()), ("x", "3 * x^2".to_string()));
+        assert_eq!(session.history, vec!["diff(x^3, x) = 3 * x^2".to_string()]);
+
+        // When x has a value, the slope there comes too
+        session.execute("x = 2").unwrap();
+        let outcome = session.execute("diff(x^3, x)").unwrap();
+        assert_eq!(outcome.value(), Some(&Value::Number(12.0)));
+        // A point as the third argument is an ordinary calculation
+        assert_eq!(session.execute("diff(x^3, x, 1)").unwrap(), Outcome::Value(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_integral_reports_its_error() {
+        let mut session = Session::new();
+        let Outcome::Estimate(value, error) = session.execute("integrate(sqrt(x), x, 0, 1)").unwrap() else {
+            panic!("an integral should be an estimate");
+        };
+        let value = value.as_number().unwrap();
+        assert!(error > 0.0 && error < 1e-9, "{}", error);
+        assert!((value - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(session.format_estimate(&Value::Number(value), error), format!("≈ {} ± {:.0e}", value, error));
+        assert!(session.history[0].starts_with("integrate(sqrt(x), x, 0, 1) ≈ 0.66666"));
+        // Once the integral is part of a bigger calculation, it is just a number
+        assert!(matches!(session.execute("2 * integrate(sqrt(x), x, 0, 1)").unwrap(), Outcome::Value(_)));
+    }
+
+    #[test]
+    fn test_polynomials() {
+        let mut session = Session::new();
+        session.execute("p = poly(1, 0, -2)").unwrap();
+        assert_eq!(session.execute("p * p").unwrap().value().unwrap().to_string(), "x^4 - 4x^2 + 4");
+        assert_eq!(session.execute("p(3)").unwrap(), Outcome::Value(Value::Number(7.0)));
+
+        let roots: Vec<String> = session.roots("p + 3").unwrap().iter().map(|root| root.to_string()).collect();
+        assert_eq!(roots, vec!["-1i", "1i"]);
+        assert_eq!(session.roots("p - p").unwrap_err().kind, ErrorKind::InvalidArgument);
+
+        // Polynomials are saved the way they are written, so they load back the same
+        assert!(session.save_script().contains("p = poly(1, 0, -2)\n"));
+        let mut restored = Session::new();
+        assert!(restored.load_script(&session.save_script()).errors.is_empty());
+        assert_eq!(restored.variables, session.variables);
+    }
+
+    #[test]
+    fn test_plot() {
+        let mut session = Session::new();
+        session.execute("k = 2").unwrap();
+        let chart = session.plot("k * x, ln(x), x, -1, 1", 5).unwrap();
+        assert_eq!(chart.xs, [-1.0, -0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(chart.series[0].ys, [-2.0, -1.0, 0.0, 1.0, 2.0]);
+        // ln has no value at 0 and below, which leaves a gap
+        assert_eq!(chart.series[1].label, "ln(x)");
+        assert!(chart.series[1].ys[..3].iter().all(|y| y.is_nan()) && chart.series[1].ys[4] == 0.0);
+
+        assert_eq!(session.plot("y, x, 0, 1", 5).unwrap_err().kind, ErrorKind::UnknownVariable);
+        assert_eq!(session.plot("x, x, 1, 0", 5).unwrap_err().span, Some(Span::new(6, 10)));
+    }
+}
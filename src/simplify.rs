@@ -0,0 +1,688 @@
+// src/simplify.rs
+// Simplifying expressions that still contain unknowns, like x + x + 2*x -> 4 * x
+//
+// The idea: every expression is turned into a sum of terms, where each term is a
+// number times some "atoms" (variables, calls, bracketed sums...) raised to whole
+// powers. Like terms then add up by themselves, numbers fold together, and the
+// sum is turned back into the shortest expression we can print.
+
+use std::collections::BTreeMap;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::builtins;
+use crate::error::Span;
+
+/// Highest power a polynomial may have when we try to cancel a division
+const MAX_DEGREE: i32 = 64;
+
+/// When dividing polynomials, a coefficient that cancels down to less than this
+/// fraction of the numbers that went into it is rounding error, and counts as zero
+const TOLERANCE: f64 = 1e-9;
+
+/// A number times atoms raised to whole powers, e.g. 3 * x^2 * sin(y)
+/// Negative powers are divisions: x^-1 is 1 / x
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    coefficient: f64,
+    /// The atoms, keyed by how they print, with their powers (never 0)
+    factors: BTreeMap<String, (Expr, i32)>,
+}
+
+impl Term {
+    fn constant(value: f64) -> Self {
+        Term { coefficient: value, factors: BTreeMap::new() }
+    }
+
+    fn atom(expr: Expr, power: i32) -> Self {
+        Term { coefficient: 1.0, factors: BTreeMap::from([(atom_key(&expr), (expr, power))]) }
+    }
+
+    /// Terms with the same key are like terms and can be added up
+    fn key(&self) -> String {
+        let parts: Vec<String> = self.factors.iter().map(|(key, (_, power))| format!("{}^{}", key, power)).collect();
+        parts.join("*")
+    }
+
+    /// Used to put terms in order: highest powers first, plain numbers last
+    fn degree(&self) -> i32 {
+        self.factors.values().map(|(_, power)| power).sum()
+    }
+
+    fn multiply(&self, other: &Term) -> Term {
+        let mut factors = self.factors.clone();
+        for (key, (expr, power)) in &other.factors {
+            let entry = factors.entry(key.clone()).or_insert((expr.clone(), 0));
+            entry.1 += power;
+            if entry.1 == 0 {
+                factors.remove(key);
+            }
+        }
+        Term { coefficient: self.coefficient * other.coefficient, factors }
+    }
+
+    /// Raise the term to a whole power, or None if the number part would not be finite
+    fn power(&self, exponent: i32) -> Option<Term> {
+        let coefficient = self.coefficient.powi(exponent);
+        if !coefficient.is_finite() {
+            return None;
+        }
+        let factors = self.factors.iter().map(|(key, (expr, power))| (key.clone(), (expr.clone(), power * exponent))).collect();
+        Some(Term { coefficient, factors })
+    }
+
+    /// The term without its sign, as an expression: 3 * x^2 / y
+    fn to_expr(&self, span: Span) -> Expr {
+        let mut numerator: Vec<Expr> =
+            self.factors.values().filter(|(_, power)| *power > 0).map(|(expr, power)| raise(expr, *power, span)).collect();
+        let denominator: Vec<Expr> =
+            self.factors.values().filter(|(_, power)| *power < 0).map(|(expr, power)| raise(expr, -power, span)).collect();
+
+        // The number goes first, and a 1 is only written when there is nothing else
+        let magnitude = self.coefficient.abs();
+        if magnitude != 1.0 || numerator.is_empty() {
+            numerator.insert(0, number(magnitude, span));
+        }
+        let product = |a, b| node(BinaryOp::Multiply, a, b, span);
+        let mut expr = numerator.into_iter().reduce(product).unwrap_or_else(|| number(1.0, span));
+        if let Some(below) = denominator.into_iter().reduce(product) {
+            expr = node(BinaryOp::Divide, expr, below, span);
+        }
+        expr
+    }
+}
+
+/// How an atom is told apart from others: by how it prints, except that every
+/// call to a random function is its own atom, since rand() - rand() is not 0
+/// (they are told apart by where they were written)
+fn atom_key(expr: &Expr) -> String {
+    fn random_calls(expr: &Expr, places: &mut Vec<String>) {
+        match &expr.kind {
+            ExprKind::Call(name, args) => {
+                if builtins::RANDOM_FUNCTIONS.contains(&name.as_str()) {
+                    // Padded, so the calls still print in the order they were written
+                    places.push(format!("@{:010}", expr.span.start));
+                }
+                args.iter().for_each(|arg| random_calls(arg, places));
+            }
+            ExprKind::Vector(items) => items.iter().for_each(|item| random_calls(item, places)),
+            ExprKind::Unary(_, operand) => random_calls(operand, places),
+            ExprKind::Binary(_, left, right) => {
+                random_calls(left, places);
+                random_calls(right, places);
+            }
+            ExprKind::Number(_) | ExprKind::Variable(_) => {}
+        }
+    }
+    let mut places = Vec::new();
+    random_calls(expr, &mut places);
+    format!("{}{}", expr, places.concat())
+}
+
+/// A sum of terms, with like terms already added together
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Sum {
+    terms: BTreeMap<String, Term>,
+}
+
+impl Sum {
+    fn constant(value: f64) -> Self {
+        Sum::from_term(Term::constant(value))
+    }
+
+    fn from_term(term: Term) -> Self {
+        let mut sum = Sum::default();
+        sum.add_term(term);
+        sum
+    }
+
+    fn add_term(&mut self, term: Term) {
+        let key = term.key();
+        let coefficient = match self.terms.get(&key) {
+            Some(existing) => existing.coefficient + term.coefficient,
+            None => term.coefficient,
+        };
+        // Terms that cancel out disappear (x - x is nothing at all)
+        if coefficient == 0.0 {
+            self.terms.remove(&key);
+        } else {
+            self.terms.insert(key, Term { coefficient, ..term });
+        }
+    }
+
+    fn plus(mut self, other: Sum) -> Sum {
+        for term in other.terms.into_values() {
+            self.add_term(term);
+        }
+        self
+    }
+
+    fn scaled(self, factor: f64) -> Sum {
+        let mut sum = Sum::default();
+        for term in self.terms.into_values() {
+            sum.add_term(Term { coefficient: term.coefficient * factor, ..term });
+        }
+        sum
+    }
+
+    /// Whether every coefficient is an ordinary number (not inf or NaN)
+    fn is_finite(&self) -> bool {
+        self.terms.values().all(|term| term.coefficient.is_finite())
+    }
+
+    /// The value, if the sum is just a number
+    fn as_constant(&self) -> Option<f64> {
+        match self.terms.len() {
+            0 => Some(0.0),
+            1 => self.terms.get("").map(|term| term.coefficient),
+            _ => None,
+        }
+    }
+
+    /// The sum as a single term: itself if it has one term,
+    /// otherwise a bracketed sum treated as an atom
+    fn as_term(&self, span: Span) -> Term {
+        match self.terms.len() {
+            0 => Term::constant(0.0),
+            1 => self.terms.values().next().cloned().unwrap_or_else(|| Term::constant(0.0)),
+            _ => Term::atom(self.to_expr(span), 1),
+        }
+    }
+
+    /// The shortest expression for the sum: highest powers first, numbers last
+    fn to_expr(&self, span: Span) -> Expr {
+        let mut terms: Vec<&Term> = self.terms.values().collect();
+        terms.sort_by(|a, b| b.degree().cmp(&a.degree()).then_with(|| a.key().cmp(&b.key())));
+
+        let mut result: Option<Expr> = None;
+        for term in terms {
+            let magnitude = term.to_expr(span);
+            let negative = term.coefficient < 0.0;
+            result = Some(match result {
+                None if negative => match magnitude.kind {
+                    ExprKind::Number(value) => number(-value, span),
+                    _ => Expr::new(ExprKind::Unary(UnaryOp::Negate, Box::new(magnitude)), span),
+                },
+                None => magnitude,
+                Some(sum) if negative => node(BinaryOp::Subtract, sum, magnitude, span),
+                Some(sum) => node(BinaryOp::Add, sum, magnitude, span),
+            });
+        }
+        result.unwrap_or_else(|| number(0.0, span))
+    }
+}
+
+/// Simplify an expression: numbers are worked out, like terms collected,
+/// and rules like x * 1 = x, x + 0 = x and x^0 = 1 applied
+/// Variables do not need values; they stay in the result as they are
+///
+/// Note that cancelling can widen where an expression is defined:
+/// (x^2 - 1)/(x - 1) becomes x + 1, which also has a value at x = 1
+/// Variables stand for numbers, so x * y may come back as y * x; matrices written
+/// out in the expression keep their order, since for them `*` is the matrix product
+pub fn simplify(expr: &Expr) -> Expr {
+    to_sum(expr).to_expr(expr.span)
+}
+
+/// Turn an expression into a sum of terms, simplifying as we go
+fn to_sum(expr: &Expr) -> Sum {
+    let sum = collect(expr);
+    // Folding numbers can overflow (1e308 * x * 10 would become inf * x),
+    // so then the step stays as it was written, with its parts simplified
+    if sum.is_finite() {
+        sum
+    } else {
+        Sum::from_term(Term::atom(unfolded(expr), 1))
+    }
+}
+
+/// `expr` with its operands simplified but the operation itself left alone
+fn unfolded(expr: &Expr) -> Expr {
+    let span = expr.span;
+    match &expr.kind {
+        ExprKind::Unary(op, operand) => Expr::new(ExprKind::Unary(*op, Box::new(simplify(operand))), span),
+        ExprKind::Binary(op, left, right) => node(*op, simplify(left), simplify(right), span),
+        _ => expr.clone(),
+    }
+}
+
+fn collect(expr: &Expr) -> Sum {
+    let span = expr.span;
+    match &expr.kind {
+        ExprKind::Number(value) => Sum::constant(*value),
+        ExprKind::Variable(_) => Sum::from_term(Term::atom(expr.clone(), 1)),
+        ExprKind::Unary(UnaryOp::Negate, operand) => to_sum(operand).scaled(-1.0),
+        ExprKind::Binary(BinaryOp::Add, left, right) => to_sum(left).plus(to_sum(right)),
+        ExprKind::Binary(BinaryOp::Subtract, left, right) => to_sum(left).plus(to_sum(right).scaled(-1.0)),
+        ExprKind::Binary(BinaryOp::Multiply, left, right) => multiply(&to_sum(left), &to_sum(right), span),
+        ExprKind::Binary(BinaryOp::Divide, left, right) => divide(to_sum(left), to_sum(right), span),
+        ExprKind::Binary(BinaryOp::Power, base, exponent) => power(to_sum(base), to_sum(exponent), span),
+        ExprKind::Call(name, args) => {
+            let args: Vec<Expr> = args.iter().map(simplify).collect();
+            // Built-ins of a number fold when the answer is whole, like cos(0) = 1,
+            // but ln(10) stays as it is rather than turning into 2.302585...
+            if let [Expr { kind: ExprKind::Number(value), .. }] = args.as_slice()
+                && let Some(function) = builtins::function(name)
+                && function(*value).is_finite()
+                && function(*value).fract() == 0.0
+            {
+                return Sum::constant(function(*value));
+            }
+            Sum::from_term(Term::atom(Expr::new(ExprKind::Call(name.clone(), args), span), 1))
+        }
+        // A vector stays as it is, with each entry simplified
+        ExprKind::Vector(items) => {
+            Sum::from_term(Term::atom(Expr::new(ExprKind::Vector(items.iter().map(simplify).collect()), span), 1))
+        }
+    }
+}
+
+fn multiply(left: &Sum, right: &Sum, span: Span) -> Sum {
+    // A number times a sum multiplies every term: 2 * (x + 1) = 2 * x + 2
+    // (but 0 * (1/0) is an error, not 0, so that product stays as written)
+    if let Some(value) = left.as_constant()
+        && (value != 0.0 || !has_error(right))
+    {
+        return right.clone().scaled(value);
+    }
+    if let Some(value) = right.as_constant()
+        && (value != 0.0 || !has_error(left))
+    {
+        return left.clone().scaled(value);
+    }
+    if left.as_constant() == Some(0.0) || right.as_constant() == Some(0.0) {
+        return Sum::from_term(Term::atom(node(BinaryOp::Multiply, left.to_expr(span), right.to_expr(span), span), 1));
+    }
+
+    // Matrices do not commute (A * B is not B * A), so a product with one stays in order
+    if has_vector(left) || has_vector(right) {
+        return Sum::from_term(Term::atom(node(BinaryOp::Multiply, left.to_expr(span), right.to_expr(span), span), 1));
+    }
+
+    // Otherwise sums stay in brackets, so equal ones can still combine: (x + 1) * (x + 1) = (x + 1)^2
+    let product = left.as_term(span).multiply(&right.as_term(span));
+    expand_single_sum(product)
+}
+
+/// Does any atom of the sum have a vector or matrix written out in it?
+fn has_vector(sum: &Sum) -> bool {
+    fn contains_vector(expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Vector(_) => true,
+            ExprKind::Unary(_, operand) => contains_vector(operand),
+            ExprKind::Binary(_, left, right) => contains_vector(left) || contains_vector(right),
+            ExprKind::Call(_, args) => args.iter().any(contains_vector),
+            ExprKind::Number(_) | ExprKind::Variable(_) => false,
+        }
+    }
+    sum.terms.values().any(|term| term.factors.values().any(|(expr, _)| contains_vector(expr)))
+}
+
+/// Does any atom of the sum fail to work out whatever the variables are,
+/// like 1/0 or ln(-1)? Then 0 times it, or it to the power 0, is that error too
+fn has_error(sum: &Sum) -> bool {
+    fn fails(expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Binary(BinaryOp::Divide, _, right) if matches!(right.kind, ExprKind::Number(value) if value == 0.0) => true,
+            ExprKind::Binary(_, left, right) => fails(left) || fails(right),
+            ExprKind::Unary(_, operand) => fails(operand),
+            ExprKind::Call(name, args) => {
+                args.iter().any(fails)
+                    || matches!(args.as_slice(), [Expr { kind: ExprKind::Number(value), .. }]
+                        if builtins::function(name).is_some_and(|function| !function(*value).is_finite()))
+            }
+            ExprKind::Vector(items) => items.iter().any(fails),
+            ExprKind::Number(_) | ExprKind::Variable(_) => false,
+        }
+    }
+    sum.terms.values().any(|term| term.factors.values().any(|(expr, _)| fails(expr)))
+}
+
+/// If a term is just a number times one bracketed sum, multiply the number in
+fn expand_single_sum(term: Term) -> Sum {
+    if term.factors.len() == 1
+        && let Some((expr, 1)) = term.factors.values().next()
+    {
+        let inner = to_sum(expr);
+        if inner.terms.len() > 1 {
+            return inner.scaled(term.coefficient);
+        }
+    }
+    Sum::from_term(term)
+}
+
+fn divide(numerator: Sum, denominator: Sum, span: Span) -> Sum {
+    // Dividing by zero stays as it is, so calculating it still reports the error
+    if denominator.as_constant() == Some(0.0) {
+        return Sum::from_term(Term::atom(node(BinaryOp::Divide, numerator.to_expr(span), number(0.0, span), span), 1));
+    }
+
+    // Polynomials in one variable can cancel: (x^2 - 1)/(x - 1) = x + 1
+    if denominator.terms.len() > 1
+        && let Some(quotient) = divide_polynomials(&numerator, &denominator, span)
+    {
+        return quotient;
+    }
+
+    match denominator.as_term(span).power(-1) {
+        Some(inverse) => multiply(&numerator, &Sum::from_term(inverse), span),
+        None => Sum::from_term(Term::atom(node(BinaryOp::Divide, numerator.to_expr(span), denominator.to_expr(span), span), 1)),
+    }
+}
+
+fn power(base: Sum, exponent: Sum, span: Span) -> Sum {
+    let opaque = |base: &Sum, exponent: &Sum| {
+        Sum::from_term(Term::atom(node(BinaryOp::Power, base.to_expr(span), exponent.to_expr(span), span), 1))
+    };
+
+    let Some(exponent_value) = exponent.as_constant() else {
+        return opaque(&base, &exponent);
+    };
+
+    // Anything to the power 0 is 1 (unless working it out fails), and to the power 1 is itself
+    if exponent_value == 0.0 {
+        return if has_error(&base) { opaque(&base, &exponent) } else { Sum::constant(1.0) };
+    }
+    if exponent_value == 1.0 {
+        return base;
+    }
+
+    if let Some(base_value) = base.as_constant() {
+        let value = base_value.powf(exponent_value);
+        return if value.is_finite() { Sum::constant(value) } else { opaque(&base, &exponent) };
+    }
+
+    // Whole powers multiply the powers inside a term: (3 * x^2)^2 = 9 * x^4
+    if exponent_value.fract() == 0.0 && exponent_value.abs() <= MAX_DEGREE as f64 {
+        let whole = exponent_value as i32;
+        if let Some(term) = base.as_term(span).power(whole) {
+            return Sum::from_term(term);
+        }
+    }
+    opaque(&base, &exponent)
+}
+
+/// The coefficients of a polynomial in one variable, lowest power first,
+/// and the variable itself, if `sum` is one
+fn as_polynomial(sum: &Sum) -> Option<(String, Expr, Vec<f64>)> {
+    let mut variable: Option<(String, Expr)> = None;
+    let mut coefficients = vec![0.0];
+
+    for term in sum.terms.values() {
+        let power = match term.factors.iter().next() {
+            None => 0,
+            Some((key, (expr, power))) => {
+                if term.factors.len() > 1 || *power < 0 || *power > MAX_DEGREE || !matches!(expr.kind, ExprKind::Variable(_)) {
+                    return None;
+                }
+                match &variable {
+                    Some((name, _)) if name != key => return None,
+                    Some(_) => {}
+                    None => variable = Some((key.clone(), expr.clone())),
+                }
+                *power as usize
+            }
+        };
+        if coefficients.len() <= power {
+            coefficients.resize(power + 1, 0.0);
+        }
+        coefficients[power] += term.coefficient;
+    }
+
+    let (name, expr) = variable?;
+    Some((name, expr, coefficients))
+}
+
+/// Build a sum from polynomial coefficients (lowest power first)
+fn from_polynomial(variable: &Expr, coefficients: &[f64]) -> Sum {
+    let mut sum = Sum::default();
+    for (power, coefficient) in coefficients.iter().enumerate() {
+        let term = if power == 0 { Term::constant(*coefficient) } else { Term::atom(variable.clone(), power as i32) };
+        sum.add_term(Term { coefficient: *coefficient, ..term });
+    }
+    sum
+}
+
+/// Drop the highest coefficients that are zero, so the last one is the leading coefficient
+fn trim(coefficients: &mut Vec<f64>) {
+    while coefficients.last() == Some(&0.0) {
+        coefficients.pop();
+    }
+}
+
+/// The polynomial divided by its leading coefficient, so that it starts with 1 * x^n
+/// This keeps 1e-12*x - 1e-12 and x - 1 the same size when they are compared
+fn monic(coefficients: &[f64]) -> Vec<f64> {
+    let mut coefficients = coefficients.to_vec();
+    trim(&mut coefficients);
+    match coefficients.last() {
+        Some(&lead) => coefficients.iter().map(|c| c / lead).collect(),
+        None => coefficients,
+    }
+}
+
+/// Long division: the quotient and remainder of `numerator / denominator`
+/// The remainder is empty when the division is exact
+fn long_division(numerator: &[f64], denominator: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let mut remainder = numerator.to_vec();
+    trim(&mut remainder);
+    // How big the numbers were that went into each coefficient of the remainder
+    let mut sizes: Vec<f64> = remainder.iter().map(|c| c.abs()).collect();
+    let lead = denominator[denominator.len() - 1];
+    let mut quotient = vec![0.0; numerator.len().saturating_sub(denominator.len()) + 1];
+
+    while remainder.len() >= denominator.len() {
+        let shift = remainder.len() - denominator.len();
+        let factor = remainder[remainder.len() - 1] / lead;
+        quotient[shift] = factor;
+        for (index, c) in denominator.iter().enumerate() {
+            let i = shift + index;
+            remainder[i] -= factor * c;
+            sizes[i] = sizes[i].max((factor * c).abs());
+            // Whatever is left after cancelling is compared with the numbers that
+            // cancelled, not with the other coefficients, which may be much bigger
+            if remainder[i].abs() <= TOLERANCE * sizes[i] {
+                remainder[i] = 0.0;
+            }
+        }
+        remainder.pop();
+        trim(&mut remainder);
+        sizes.truncate(remainder.len());
+    }
+    (quotient, remainder)
+}
+
+/// The greatest common divisor of two polynomials, scaled so its highest coefficient is 1
+fn polynomial_gcd(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let (mut a, mut b) = (monic(a), monic(b));
+    while !b.is_empty() {
+        let (_, remainder) = long_division(&a, &b);
+        a = b;
+        b = monic(&remainder);
+    }
+    a
+}
+
+/// Divide two polynomials in the same variable, cancelling any common factor
+/// Returns None when they are not polynomials or nothing cancels
+fn divide_polynomials(numerator: &Sum, denominator: &Sum, span: Span) -> Option<Sum> {
+    let (name, variable, top) = as_polynomial(numerator)?;
+    let (other, _, bottom) = as_polynomial(denominator)?;
+    if name != other {
+        return None;
+    }
+
+    let gcd = polynomial_gcd(&top, &bottom);
+    if gcd.len() < 2 {
+        return None;
+    }
+    // Only a factor that divides both exactly is cancelled
+    let (top, top_remainder) = long_division(&top, &gcd);
+    let (bottom, bottom_remainder) = long_division(&bottom, &gcd);
+    if !top_remainder.is_empty() || !bottom_remainder.is_empty() {
+        return None;
+    }
+
+    let top = from_polynomial(&variable, &top);
+    let bottom = from_polynomial(&variable, &bottom);
+    // What is left of the denominator is divided out like any other sum
+    Some(match bottom.as_constant() {
+        Some(value) => top.scaled(1.0 / value),
+        None => multiply(&top, &Sum::from_term(bottom.as_term(span).power(-1)?), span),
+    })
+}
+
+fn number(value: f64, span: Span) -> Expr {
+    Expr::new(ExprKind::Number(value), span)
+}
+
+fn node(op: BinaryOp, left: Expr, right: Expr, span: Span) -> Expr {
+    Expr::new(ExprKind::Binary(op, Box::new(left), Box::new(right)), span)
+}
+
+/// `expr` to a whole power, leaving out "^1"
+fn raise(expr: &Expr, power: i32, span: Span) -> Expr {
+    if power == 1 {
+        expr.clone()
+    } else {
+        node(BinaryOp::Power, expr.clone(), number(power as f64, span), span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::{Context, Mode};
+    use crate::random::Random;
+    use crate::parser::parse_expression;
+    use crate::value::Value;
+
+    fn simplified(input: &str) -> String {
+        simplify(&parse_expression(input).unwrap()).to_string()
+    }
+
+    /// Check that `input` and its simplified form give the same value at a few points
+    fn assert_same_values(input: &str) {
+        let original = parse_expression(input).unwrap();
+        let simple = simplify(&original);
+        let functions = BTreeMap::new();
+        for (x, y) in [(-2.5, 0.5), (-1.3, 2.0), (0.7, -3.0), (2.0, 1.5), (3.1, -0.25)] {
+            let variables = BTreeMap::from([("x".to_string(), Value::Number(x)), ("y".to_string(), Value::Number(y))]);
+            let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() };
+            let expected = context.eval(&original).unwrap();
+            let actual = context.eval(&simple).unwrap();
+            assert!(
+                (expected - actual).abs() <= 1e-9 * expected.abs().max(1.0),
+                "{} -> {}: {} != {} at x = {}, y = {}",
+                input,
+                simple,
+                expected,
+                actual,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_like_terms() {
+        assert_eq!(simplified("x + x + 2*x"), "4 * x");
+        assert_eq!(simplified("3*x*y - y*x + 1"), "2 * x * y + 1");
+        assert_eq!(simplified("x - x"), "0");
+        assert_eq!(simplified("x*x*x / x"), "x^2");
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        assert_eq!(simplified("2 + 3 * 4"), "14");
+        assert_eq!(simplified("x + 2^3 - 8"), "x");
+        assert_eq!(simplified("(1 + 1) * x / 4"), "0.5 * x");
+        assert_eq!(simplified("cos(0) * x + sqrt(9)"), "x + 3");
+        assert_eq!(simplified("ln(10) * x"), "ln(10) * x");
+    }
+
+    #[test]
+    fn test_identities() {
+        assert_eq!(simplified("x * 1 + 0"), "x");
+        assert_eq!(simplified("x * 0 + y"), "y");
+        assert_eq!(simplified("x ^ 1"), "x");
+        assert_eq!(simplified("(x + y) ^ 0"), "1");
+        assert_eq!(simplified("--x"), "x");
+        assert_eq!(simplified("0 - x"), "-x");
+    }
+
+    #[test]
+    fn test_cancelling_polynomials() {
+        assert_eq!(simplified("(x^2 - 1)/(x - 1)"), "x + 1");
+        assert_eq!(simplified("(x^2 + 2*x + 1)/(x + 1)"), "x + 1");
+        assert_eq!(simplified("(2*x + 2)/(x^2 - 1)"), "2 / (x - 1)");
+        // Nothing cancels, so the division stays
+        assert_eq!(simplified("(x + 2)/(x + 1)"), "(x + 2) / (x + 1)");
+    }
+
+    #[test]
+    fn test_badly_scaled_polynomials() {
+        // Very big or very small coefficients are real numbers, not rounding error
+        assert_eq!(simplified("(1e10*x^2 - 1)/(x - 1)"), "(10000000000 * x^2 - 1) / (x - 1)");
+        assert_eq!(simplified("(x^2 - 1)/(1e-12*x - 1e-12)"), "1000000000000 * x + 1000000000000");
+        assert_eq!(simplified("(1e-12*x^2 - 1e-12)/(x - 1)"), "0.000000000001 * x + 0.000000000001");
+        assert_eq!(simplified("(x^2 - 1e-20)/(x - 1e-10)"), "x + 0.0000000001");
+        for input in ["(1e10*x^2 - 1)/(x - 1)", "(x^2 - 1)/(1e-12*x - 1e-12)", "(1e-6*x^3 - 1e6*x)/(x^2 + 1e6*x)"] {
+            assert_same_values(input);
+        }
+        // 1e308 * 10 is too big to fold, so that multiplication stays as written
+        let overflowing = simplified("1e308*x*x*10");
+        assert!(overflowing.ends_with("* x^2 * 10") && !overflowing.contains("inf"), "{}", overflowing);
+    }
+
+    #[test]
+    fn test_sums_in_products_stay_together() {
+        assert_eq!(simplified("2*(x + 1)"), "2 * x + 2");
+        assert_eq!(simplified("(x + 1)*(x + 1)"), "(x + 1)^2");
+        assert_eq!(simplified("(3 * x^2)^2"), "9 * x^4");
+        assert_eq!(simplified("f(1 + 1, x + x)"), "f(2, 2 * x)");
+        // Matrix products keep their order
+        assert_eq!(simplified("[[0, 1], [1, 0]] * [[1, 2], [3, 4]]"), "[[0, 1], [1, 0]] * [[1, 2], [3, 4]]");
+        assert_eq!(simplified("[[1, 2], [3, 4]] * [[0, 1], [1, 0]]"), "[[1, 2], [3, 4]] * [[0, 1], [1, 0]]");
+        assert_eq!(simplified("2 * [[1, 2], [3, 4]] * 3"), "6 * [[1, 2], [3, 4]]");
+    }
+
+    #[test]
+    fn test_simplified_forms_have_the_same_values() {
+        for input in [
+            "x + x + 2*x",
+            "(x^2 - 1)/(x - 1)",
+            "(x^2 + 2*x + 1)/(x + 1)",
+            "(2*x + 2)/(x^2 - 1)",
+            "3*x*y - y*x + 1",
+            "(x + y) * (x + y) - 2 * (x + 1) / y",
+            "x^2 * x^-3 + 1 / x",
+            "-(x - y) * -(2 - 3) ^ 2",
+            "(x^2 + 1)^y / (x^2 + 1)^y * x",
+            "2^x * 2^x",
+        ] {
+            assert_same_values(input);
+        }
+    }
+
+    #[test]
+    fn test_random_calls_are_never_combined() {
+        assert_eq!(simplified("rand() - rand()"), "rand() - rand()");
+        assert_eq!(simplified("rand() + rand()"), "rand() + rand()");
+        assert_eq!(simplified("rand() * rand()"), "rand() * rand()");
+        assert_eq!(simplified("sin(rand()) - sin(rand())"), "sin(rand()) - sin(rand())");
+        assert_eq!(simplified("(x + rand())^2 / (x + rand())"), "(rand() + x)^2 / (rand() + x)");
+        // A single call can still be scaled
+        assert_eq!(simplified("3 * rand() - rand() * 2"), "3 * rand() - 2 * rand()");
+    }
+
+    #[test]
+    fn test_division_by_zero_is_kept() {
+        assert_eq!(simplified("x / (2 - 2)"), "x / 0");
+        // Multiplying an error by 0 (or raising it to the power 0) does not make it go away
+        assert_eq!(simplified("0 * (1/0)"), "0 * (1 / 0)");
+        assert_eq!(simplified("(x/0) * 0"), "x / 0 * 0");
+        assert_eq!(simplified("0 * ln(-1)"), "0 * ln(-1)");
+        assert_eq!(simplified("(1/0)^0"), "(1 / 0)^0");
+        assert_eq!(simplified("0 * ln(2)"), "0");
+    }
+}
@@ -0,0 +1,569 @@
+// src/solve.rs
+// Solving equations like `x^2 - 2 = 0 for x`: with a formula when the equation is
+// linear or quadratic, otherwise by searching an interval for the roots
+
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, ExprKind};
+use crate::calculator::{format_number, Context, Mode};
+use crate::calculus;
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::parser::parse_expression;
+use crate::value::Value;
+
+/// How many equal pieces the interval is cut into when looking for sign changes
+const SAMPLES: usize = 200;
+
+/// How many steps Brent's or Newton's method may take for one root
+const MAX_ITERATIONS: usize = 100;
+
+/// How close (in x) a numerical root must be pinned down
+const TOLERANCE: f64 = 1e-12;
+
+/// How small |f(x)| must be for a point found by Newton's method to count as a root
+const RESIDUAL_LIMIT: f64 = 1e-9;
+
+/// A parsed `solve` command: the equation as `left - right`, the unknown, and maybe an interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equation {
+    pub function: Expr,
+    pub variable: String,
+    pub interval: Option<(Expr, Expr)>,
+}
+
+/// A number that may have an imaginary part, like the roots of x^2 + 1 = 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn real(re: f64) -> Self {
+        Complex { re, im: 0.0 }
+    }
+
+    /// Show as "1.5", "1 + 2i" or "-2i", rounded to `precision` decimal places if one is given
+    pub fn format(&self, precision: Option<usize>) -> String {
+        let im = format_number(self.im.abs(), precision);
+        // -0 + 0 is 0, so a root like -0 / 2 is not shown as "-0"
+        let re = self.re + 0.0;
+        match (re == 0.0, self.im == 0.0) {
+            (_, true) => format_number(re, precision),
+            (true, false) => format!("{}{}i", if self.im < 0.0 { "-" } else { "" }, im),
+            (false, false) => {
+                format!("{} {} {}i", format_number(re, precision), if self.im < 0.0 { "-" } else { "+" }, im)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+// Complex arithmetic, for finding the roots of polynomials
+impl Complex {
+    /// The distance from 0
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn plus(self, other: Complex) -> Complex {
+        Complex { re: self.re + other.re, im: self.im + other.im }
+    }
+
+    pub fn minus(self, other: Complex) -> Complex {
+        Complex { re: self.re - other.re, im: self.im - other.im }
+    }
+
+    pub fn times(self, other: Complex) -> Complex {
+        Complex { re: self.re * other.re - self.im * other.im, im: self.re * other.im + self.im * other.re }
+    }
+
+    pub fn divided_by(self, other: Complex) -> Complex {
+        let size = other.re * other.re + other.im * other.im;
+        Complex {
+            re: (self.re * other.re + self.im * other.im) / size,
+            im: (self.im * other.re - self.re * other.im) / size,
+        }
+    }
+}
+
+/// How a root was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// x = -c / b
+    Linear,
+    /// x = (-b +/- sqrt(b^2 - 4ac)) / 2a
+    Quadratic,
+    /// A sign change was narrowed down with Brent's method
+    Brent,
+    /// A root that only touches zero, found with Newton's method
+    Newton,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Linear => write!(f, "linear formula"),
+            Method::Quadratic => write!(f, "quadratic formula"),
+            Method::Brent => write!(f, "Brent's method"),
+            Method::Newton => write!(f, "Newton's method"),
+        }
+    }
+}
+
+/// One root, with a report on how well it was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub value: Complex,
+    pub method: Method,
+    /// Steps the numerical method took (0 for a formula)
+    pub iterations: usize,
+    /// |f(x)| at the root, for real roots
+    pub residual: Option<f64>,
+    /// False when the method ran out of steps before reaching the tolerance
+    pub converged: bool,
+}
+
+impl Root {
+    /// A short note like "Brent's method, 6 iterations, |f(x)| = 1.1e-16"
+    pub fn report(&self) -> String {
+        let mut parts = vec![self.method.to_string()];
+        if self.iterations > 0 {
+            parts.push(format!("{} iteration{}", self.iterations, if self.iterations == 1 { "" } else { "s" }));
+        }
+        if let Some(residual) = self.residual {
+            parts.push(format!("|f(x)| = {:.1e}", residual));
+        }
+        if !self.converged {
+            parts.push("did not converge".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Every root that was found, smallest (real part) first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub variable: String,
+    pub roots: Vec<Root>,
+}
+
+/// Read `left = right for x` or `left = right for x in a, b`
+/// Spans in errors and in the expressions point into `text`
+pub fn parse_equation(text: &str) -> Result<Equation, CalcError> {
+    let whole = Span::new(0, text.len());
+    let Some(for_at) = text.rfind(" for ") else {
+        return Err(CalcError::new(
+            ErrorKind::Syntax,
+            "Say which variable to solve for, like 'solve x^2 - 2 = 0 for x'",
+            Some(whole),
+        ));
+    };
+    let equation = &text[..for_at];
+    let Some(equals) = equation.find('=') else {
+        return Err(CalcError::new(ErrorKind::Syntax, "An equation needs an '=', like 'x^2 = 2'", Some(whole)));
+    };
+    if let Some(second) = equation[equals + 1..].find('=') {
+        let at = equals + 1 + second;
+        return Err(CalcError::new(ErrorKind::Syntax, "An equation can only have one '='", Some(Span::new(at, at + 1))));
+    }
+
+    let left = parse_part(text, 0, equals)?;
+    let right = parse_part(text, equals + 1, for_at)?;
+    let function = Expr::new(ExprKind::Binary(BinaryOp::Subtract, Box::new(left), Box::new(right)), Span::new(0, for_at));
+
+    // After "for": the variable, then maybe "in a, b"
+    let rest_start = for_at + " for ".len();
+    let rest = &text[rest_start..];
+    let (name, interval) = match rest.split_once(" in ") {
+        Some((name, bounds)) => {
+            let bounds_start = rest_start + name.len() + " in ".len();
+            let Some(comma) = top_level_comma(bounds) else {
+                return Err(CalcError::new(
+                    ErrorKind::Syntax,
+                    "An interval needs two ends, like 'for x in 0, 1'",
+                    Some(Span::new(bounds_start, text.len())),
+                ));
+            };
+            let low = parse_part(text, bounds_start, bounds_start + comma)?;
+            let high = parse_part(text, bounds_start + comma + 1, text.len())?;
+            (name, Some((low, high)))
+        }
+        None => (rest, None),
+    };
+
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(CalcError::new(
+            ErrorKind::Syntax,
+            format!("'{}' is not a variable name", name),
+            Some(Span::new(rest_start, rest_start + rest.len())),
+        ));
+    }
+    Ok(Equation { function, variable: name.to_string(), interval })
+}
+
+/// Parse `text[start..end]` so that spans still point into the whole of `text`:
+/// the part is padded with spaces, which the lexer skips
+fn parse_part(text: &str, start: usize, end: usize) -> Result<Expr, CalcError> {
+    parse_expression(&format!("{}{}", " ".repeat(start), &text[start..end]))
+}
+
+/// Where the comma between two bounds is, skipping commas inside brackets like f(1, 2)
+fn top_level_comma(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find the roots of `function` = 0 for `variable`, using the variables and functions in `context`
+/// Polynomials of degree 1 or 2 are solved with a formula (keeping only real roots inside the
+/// interval, if one is given); anything else needs an interval to search. Works in float mode.
+pub fn solve(
+    function: &Expr,
+    variable: &str,
+    interval: Option<(f64, f64)>,
+    context: &Context,
+) -> Result<Solution, CalcError> {
+    let span = function.span;
+    let function = &calculus::inline_functions(function, context.functions)?;
+    let at = |expr: &Expr, x: f64| {
+        let mut variables = context.variables.clone();
+        variables.insert(variable.to_string(), Value::Number(x));
+        Context { variables: &variables, functions: context.functions, mode: Mode::Float, random: context.random }.eval(expr)
+    };
+
+    // Up to the second derivative is enough to tell linear and quadratic equations apart
+    let first = calculus::derivative(function, variable).ok();
+    let second = first.as_ref().and_then(|first| calculus::derivative(first, variable).ok());
+    let third = second.as_ref().and_then(|second| calculus::derivative(second, variable).ok());
+
+    let roots = match (&first, &second, &third) {
+        (Some(first), _, _) if is_zero(first) => {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                format!("The equation does not depend on '{}'", variable),
+                Some(span),
+            ))
+        }
+        (Some(first), Some(second), _) if is_zero(second) => {
+            let (b, c) = (at(first, 0.0)?, at(function, 0.0)?);
+            linear(b, c, span, variable)?
+        }
+        (Some(first), Some(second), Some(third)) if is_zero(third) => {
+            let (a, b, c) = (at(second, 0.0)? / 2.0, at(first, 0.0)?, at(function, 0.0)?);
+            if a == 0.0 { linear(b, c, span, variable)? } else { quadratic(a, b, c) }
+        }
+        _ => {
+            let Some((low, high)) = interval else {
+                return Err(CalcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "This equation has no formula; give an interval to search, like 'solve ... for {} in 0, 10'",
+                        variable
+                    ),
+                    Some(span),
+                ));
+            };
+            let slope = first.as_ref();
+            search(&mut |x| at(function, x).unwrap_or(f64::NAN), &mut |x| slope.and_then(|slope| at(slope, x).ok()), low, high, span)?
+        }
+    };
+
+    // A formula's roots still have to lie inside the interval, if there is one
+    let mut roots: Vec<Root> = roots
+        .into_iter()
+        .filter(|root| match interval {
+            Some((low, high)) => root.value.im == 0.0 && low.min(high) <= root.value.re && root.value.re <= low.max(high),
+            None => true,
+        })
+        .collect();
+    for root in &mut roots {
+        if root.value.im == 0.0 {
+            root.residual = at(function, root.value.re).ok().map(f64::abs);
+        }
+    }
+    roots.sort_by(|a, b| a.value.re.total_cmp(&b.value.re).then(a.value.im.total_cmp(&b.value.im)));
+    Ok(Solution { variable: variable.to_string(), roots })
+}
+
+/// Is `expr` the number 0 (after simplifying)?
+fn is_zero(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Number(value) if value == 0.0)
+}
+
+fn formula_root(value: Complex, method: Method) -> Root {
+    Root { value, method, iterations: 0, residual: None, converged: true }
+}
+
+/// b x + c = 0
+fn linear(b: f64, c: f64, span: Span, variable: &str) -> Result<Vec<Root>, CalcError> {
+    if b == 0.0 {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("The equation does not depend on '{}'", variable),
+            Some(span),
+        ));
+    }
+    Ok(vec![formula_root(Complex::real(-c / b), Method::Linear)])
+}
+
+/// a x^2 + b x + c = 0, with a != 0
+fn quadratic(a: f64, b: f64, c: f64) -> Vec<Root> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        let (re, im) = (-b / (2.0 * a), (-discriminant).sqrt() / (2.0 * a.abs()));
+        return vec![
+            formula_root(Complex { re, im: -im }, Method::Quadratic),
+            formula_root(Complex { re, im }, Method::Quadratic),
+        ];
+    }
+    if discriminant == 0.0 {
+        return vec![formula_root(Complex::real(-b / (2.0 * a)), Method::Quadratic)];
+    }
+    // Subtracting two nearly equal numbers loses digits, so the smaller root comes from
+    // the product of the roots (c / a) instead of the +/- formula
+    let q = -(b + b.signum() * discriminant.sqrt()) / 2.0;
+    let first = q / a;
+    let second = if q == 0.0 { -first } else { c / q };
+    vec![formula_root(Complex::real(first), Method::Quadratic), formula_root(Complex::real(second), Method::Quadratic)]
+}
+
+/// Look for roots between `low` and `high`: sign changes are narrowed down with Brent's method,
+/// and places where |f| dips close to zero without crossing get a try with Newton's method
+fn search(
+    f: &mut dyn FnMut(f64) -> f64,
+    slope: &mut dyn FnMut(f64) -> Option<f64>,
+    low: f64,
+    high: f64,
+    span: Span,
+) -> Result<Vec<Root>, CalcError> {
+    if !low.is_finite() || !high.is_finite() || low == high {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            format!("The interval must have two different, finite ends (got {} and {})", low, high),
+            Some(span),
+        ));
+    }
+    let (low, high) = (low.min(high), low.max(high));
+    let xs: Vec<f64> = (0..=SAMPLES).map(|i| low + (high - low) * i as f64 / SAMPLES as f64).collect();
+    let ys: Vec<f64> = xs.iter().map(|&x| f(x)).collect();
+
+    let mut roots: Vec<Root> = Vec::new();
+    for i in 0..=SAMPLES {
+        // A sign change between two samples
+        if i < SAMPLES && ys[i] * ys[i + 1] < 0.0 {
+            let (x, iterations, converged) = brent(f, xs[i], xs[i + 1], ys[i], ys[i + 1]);
+            let residual = f(x).abs();
+            // A jump over a pole (like tan at pi/2) changes sign too, but |f| is huge there
+            if residual <= RESIDUAL_LIMIT.max(1e-6 * ys[i].abs().min(ys[i + 1].abs())) {
+                roots.push(Root { value: Complex::real(x), method: Method::Brent, iterations, residual: None, converged });
+            }
+            continue;
+        }
+        // A dip towards zero that does not cross it, like (x - 1)^2 at 1 (or a sample right on a root)
+        let dips = ys[i] == 0.0 || (i > 0 && i < SAMPLES && ys[i].abs() < ys[i - 1].abs() && ys[i].abs() <= ys[i + 1].abs());
+        let crosses = (i > 0 && ys[i - 1] * ys[i] < 0.0) || (i < SAMPLES && ys[i] * ys[i + 1] < 0.0);
+        if dips
+            && !crosses
+            && let Some((x, iterations)) = newton(f, slope, xs[i])
+            && (low..=high).contains(&x)
+        {
+            roots.push(Root { value: Complex::real(x), method: Method::Newton, iterations, residual: None, converged: true });
+        }
+    }
+
+    // The same root can be found from both sides of a sample
+    roots.sort_by(|a, b| a.value.re.total_cmp(&b.value.re));
+    roots.dedup_by(|a, b| (a.value.re - b.value.re).abs() <= 1e-9 * a.value.re.abs().max(1.0));
+    Ok(roots)
+}
+
+/// Brent's method on [a, b] where f(a) and f(b) have opposite signs: bisection, sped up with
+/// secant and inverse quadratic steps whenever they can be trusted
+/// Returns the root, the number of iterations, and whether the tolerance was reached
+fn brent(f: &mut dyn FnMut(f64) -> f64, mut a: f64, mut b: f64, mut fa: f64, mut fb: f64) -> (f64, usize, bool) {
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+    for iteration in 1..=MAX_ITERATIONS {
+        // Keep the root between b and c
+        if (fb > 0.0) == (fc > 0.0) {
+            (c, fc) = (a, fa);
+            (d, e) = (b - a, b - a);
+        }
+        // b is the best guess so far
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let half = 0.5 * (c - b);
+        if half.abs() <= tolerance || fb == 0.0 {
+            return (b, iteration, true);
+        }
+
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            // Try interpolating: a secant step, or an inverse quadratic one when we have three points
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * half * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (s * (2.0 * half * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+            if p > 0.0 {
+                q = -q;
+            } else {
+                p = -p;
+            }
+            // Only take the step if it stays well inside the bracket and is shrinking fast enough
+            if 2.0 * p < (3.0 * half * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                (d, e) = (half, half);
+            }
+        } else {
+            (d, e) = (half, half);
+        }
+
+        (a, fa) = (b, fb);
+        b += if d.abs() > tolerance { d } else { tolerance.copysign(half) };
+        fb = f(b);
+    }
+    (b, MAX_ITERATIONS, false)
+}
+
+/// Newton's method from `start`, using the exact slope when we have one
+/// Returns the root and the number of iterations, or None if it wandered off
+fn newton(f: &mut dyn FnMut(f64) -> f64, slope: &mut dyn FnMut(f64) -> Option<f64>, start: f64) -> Option<(f64, usize)> {
+    let mut x = start;
+    for iteration in 1..=MAX_ITERATIONS {
+        let y = f(x);
+        let h = 1e-7 * x.abs().max(1.0);
+        let dy = slope(x).unwrap_or_else(|| (f(x + h) - f(x - h)) / (2.0 * h));
+        if !y.is_finite() || !dy.is_finite() {
+            return None;
+        }
+        if y == 0.0 || dy == 0.0 {
+            return (y.abs() <= RESIDUAL_LIMIT).then_some((x, iteration));
+        }
+        let step = y / dy;
+        x -= step;
+        if step.abs() <= TOLERANCE * x.abs().max(1.0) {
+            return (f(x).abs() <= RESIDUAL_LIMIT).then_some((x, iteration));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::random::Random;
+
+    fn solved(text: &str) -> Result<Solution, CalcError> {
+        let equation = parse_equation(text)?;
+        let (variables, functions) = (BTreeMap::new(), BTreeMap::new());
+        let context = Context { variables: &variables, functions: &functions, mode: Mode::Float, random: &Random::default() };
+        let interval = equation.interval.map(|(low, high)| (context.eval(&low).unwrap(), context.eval(&high).unwrap()));
+        solve(&equation.function, &equation.variable, interval, &context)
+    }
+
+    fn values(solution: &Solution) -> Vec<f64> {
+        solution.roots.iter().map(|root| root.value.re).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} vs {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_equation() {
+        let equation = parse_equation("x^2 = 2 for x in -1, f(1, 2)").unwrap();
+        assert_eq!(equation.function.to_string(), "x^2 - 2");
+        assert_eq!(equation.variable, "x");
+        assert_eq!(equation.interval.unwrap().1.to_string(), "f(1, 2)");
+
+        assert_eq!(parse_equation("x^2 = 2").unwrap_err().kind, ErrorKind::Syntax);
+        assert_eq!(parse_equation("x^2 for x").unwrap_err().kind, ErrorKind::Syntax);
+        // The spans of the right-hand side still point into the whole text
+        assert_eq!(parse_equation("x = 2 + for x").unwrap_err().span, Some(Span::new(6, 7)));
+    }
+
+    #[test]
+    fn test_formulas() {
+        let solution = solved("2 * x + 1 = 4 for x").unwrap();
+        assert_close(&values(&solution), &[1.5]);
+        assert_eq!(solution.roots[0].method, Method::Linear);
+
+        let solution = solved("x^2 - 2 = 0 for x").unwrap();
+        assert_close(&values(&solution), &[-2f64.sqrt(), 2f64.sqrt()]);
+        assert!(solution.roots.iter().all(|root| root.method == Method::Quadratic && root.residual.unwrap() < 1e-15));
+
+        assert_close(&values(&solved("(x - 3)^2 = 0 for x").unwrap()), &[3.0]);
+        // Only the roots inside the interval are kept
+        assert_close(&values(&solved("x^2 = 4 for x in 0, 5").unwrap()), &[2.0]);
+    }
+
+    #[test]
+    fn test_complex_roots() {
+        let solution = solved("x^2 + 2 * x + 5 = 0 for x").unwrap();
+        let roots: Vec<String> = solution.roots.iter().map(|root| root.value.to_string()).collect();
+        assert_eq!(roots, vec!["-1 - 2i", "-1 + 2i"]);
+        assert_eq!(Complex { re: 0.0, im: -0.5 }.format(Some(2)), "-0.50i");
+    }
+
+    #[test]
+    fn test_zero_root_has_no_sign() {
+        for equation in ["x^2 = 0 for x", "2 * x = 0 for x", "-x = 0 for x"] {
+            let roots: Vec<String> = solved(equation).unwrap().roots.iter().map(|root| root.value.to_string()).collect();
+            assert_eq!(roots, vec!["0"], "{}", equation);
+        }
+    }
+
+    #[test]
+    fn test_numerical_roots() {
+        let solution = solved("cos(x) = x for x in 0, 1").unwrap();
+        assert_close(&values(&solution), &[0.7390851332151607]);
+        assert_eq!(solution.roots[0].method, Method::Brent);
+        assert!(solution.roots[0].converged && solution.roots[0].iterations > 0);
+
+        let pi = std::f64::consts::PI;
+        assert_close(&values(&solved("sin(x) = 0 for x in -1, 7").unwrap()), &[0.0, pi, 2.0 * pi]);
+
+        // A root that only touches zero, next to one that crosses it
+        let solution = solved("(x - 1)^2 * (x + 3) = 0 for x in -5, 5").unwrap();
+        assert_close(&values(&solution), &[-3.0, 1.0]);
+        assert_eq!(solution.roots[1].method, Method::Newton);
+    }
+
+    #[test]
+    fn test_poles_are_not_roots() {
+        assert!(solved("tan(x) = 0 for x in 1, 2").unwrap().roots.is_empty());
+    }
+
+    #[test]
+    fn test_solve_errors() {
+        assert_eq!(solved("sin(x) = 0.5 for x").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(solved("2 = 3 for x").unwrap_err().kind, ErrorKind::InvalidArgument);
+        assert_eq!(solved("y * x = 1 for x").unwrap_err().kind, ErrorKind::UnknownVariable);
+    }
+}
@@ -0,0 +1,240 @@
+// src/stats.rs
+// Statistics over lists of numbers: averages, spread, quantiles and straight-line fits
+//
+// Long lists are summed with Neumaier's version of Kahan summation, and means and
+// variances are built up one number at a time with Welford's method, so rounding
+// errors stay small even for thousands of numbers far from zero
+
+/// Add up numbers, carrying the rounding error of each addition along
+/// so it can be put back at the end
+pub fn sum(values: &[f64]) -> f64 {
+    let mut total = 0.0_f64;
+    let mut lost = 0.0;
+    for &value in values {
+        let next = total + value;
+        // Whatever part of the smaller number did not fit into `next` is lost; keep it
+        if total.abs() >= value.abs() {
+            lost += (total - next) + value;
+        } else {
+            lost += (value - next) + total;
+        }
+        total = next;
+    }
+    // With infinities the lost part is meaningless (inf - inf)
+    if total.is_finite() { total + lost } else { values.iter().sum() }
+}
+
+/// All the numbers multiplied together
+pub fn product(values: &[f64]) -> f64 {
+    values.iter().product()
+}
+
+/// The count, mean and sum of squared differences from the mean, built up with Welford's method
+struct Moments {
+    count: usize,
+    mean: f64,
+    squares: f64,
+}
+
+fn moments(values: &[f64]) -> Moments {
+    let mut moments = Moments { count: 0, mean: 0.0, squares: 0.0 };
+    for &value in values {
+        moments.count += 1;
+        let before = value - moments.mean;
+        moments.mean += before / moments.count as f64;
+        moments.squares += before * (value - moments.mean);
+    }
+    moments
+}
+
+/// The average; None for an empty list
+pub fn mean(values: &[f64]) -> Option<f64> {
+    let moments = moments(values);
+    (moments.count > 0).then_some(moments.mean)
+}
+
+/// The variance: of a sample (dividing by n - 1, needs two numbers)
+/// or of a whole population (dividing by n, needs one)
+pub fn variance(values: &[f64], sample: bool) -> Option<f64> {
+    let moments = moments(values);
+    let divisor = if sample { moments.count.checked_sub(1)? } else { moments.count };
+    (divisor > 0).then(|| moments.squares / divisor as f64)
+}
+
+/// The standard deviation, the square root of the variance
+pub fn stddev(values: &[f64], sample: bool) -> Option<f64> {
+    variance(values, sample).map(f64::sqrt)
+}
+
+/// The numbers from smallest to largest
+pub fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+/// The value below which a fraction `p` (0 to 1) of the numbers lie, going in
+/// a straight line between neighbours; None for an empty list or p outside 0 to 1
+pub fn quantile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() || !(0.0..=1.0).contains(&p) {
+        return None;
+    }
+    let sorted = sorted(values);
+    let position = p * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = (below + 1).min(sorted.len() - 1);
+    Some(sorted[below] + (position - below as f64) * (sorted[above] - sorted[below]))
+}
+
+/// The middle number (or the average of the two middle ones); None for an empty list
+pub fn median(values: &[f64]) -> Option<f64> {
+    quantile(values, 0.5)
+}
+
+/// The most common number, the smallest one if there is a tie; None for an empty list
+pub fn mode(values: &[f64]) -> Option<f64> {
+    let sorted = sorted(values);
+    let mut best: Option<(f64, usize)> = None;
+    for run in sorted.chunk_by(|a, b| a == b) {
+        if best.is_none_or(|(_, count)| run.len() > count) {
+            best = Some((run[0], run.len()));
+        }
+    }
+    best.map(|(value, _)| value)
+}
+
+/// The smallest number; None for an empty list
+pub fn min(values: &[f64]) -> Option<f64> {
+    values.iter().copied().reduce(f64::min)
+}
+
+/// The largest number; None for an empty list
+pub fn max(values: &[f64]) -> Option<f64> {
+    values.iter().copied().reduce(f64::max)
+}
+
+/// Welford's method for two lists at once: both means, both sums of squared
+/// differences, and the sum of the products of differences
+struct Paired {
+    count: usize,
+    mean_x: f64,
+    mean_y: f64,
+    squares_x: f64,
+    squares_y: f64,
+    products: f64,
+}
+
+fn paired(xs: &[f64], ys: &[f64]) -> Paired {
+    let mut paired = Paired { count: 0, mean_x: 0.0, mean_y: 0.0, squares_x: 0.0, squares_y: 0.0, products: 0.0 };
+    for (&x, &y) in xs.iter().zip(ys) {
+        paired.count += 1;
+        let n = paired.count as f64;
+        let (before_x, before_y) = (x - paired.mean_x, y - paired.mean_y);
+        paired.mean_x += before_x / n;
+        paired.mean_y += before_y / n;
+        paired.squares_x += before_x * (x - paired.mean_x);
+        paired.squares_y += before_y * (y - paired.mean_y);
+        paired.products += before_x * (y - paired.mean_y);
+    }
+    paired
+}
+
+/// The sample covariance of two lists of the same length; None with fewer than two pairs
+pub fn covariance(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let paired = paired(xs, ys);
+    (paired.count >= 2).then(|| paired.products / (paired.count - 1) as f64)
+}
+
+/// Pearson's correlation, from -1 to 1; None with fewer than two pairs
+/// or when either list has all the same numbers
+pub fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let paired = paired(xs, ys);
+    let spread = (paired.squares_x * paired.squares_y).sqrt();
+    (paired.count >= 2 && spread > 0.0).then(|| (paired.products / spread).clamp(-1.0, 1.0))
+}
+
+/// The least-squares line y = slope * x + intercept, as (slope, intercept);
+/// None unless there are at least two different x values
+pub fn linear_regression(xs: &[f64], ys: &[f64]) -> Option<(f64, f64)> {
+    let paired = paired(xs, ys);
+    if paired.count < 2 || paired.squares_x == 0.0 {
+        return None;
+    }
+    let slope = paired.products / paired.squares_x;
+    Some((slope, paired.mean_y - slope * paired.mean_x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_sums_keep_small_numbers() {
+        // Plain adding loses every 1 against 1e16
+        let values = [1e16, 1.0, 1.0, 1.0, 1.0, -1e16];
+        assert_eq!(sum(&values), 4.0);
+        assert_eq!(sum(&[0.1; 10]), 1.0);
+        assert_eq!(sum(&[]), 0.0);
+        assert_eq!(sum(&[f64::INFINITY, 1.0]), f64::INFINITY);
+        assert_eq!(product(&[2.0, 3.0, 4.0]), 24.0);
+    }
+
+    #[test]
+    fn test_averages() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&values), Some(5.0));
+        assert_eq!(median(&values), Some(4.5));
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(mode(&values), Some(4.0));
+        assert_eq!(mode(&[3.0, 1.0, 3.0, 1.0]), Some(1.0));
+        assert_eq!((min(&values), max(&values)), (Some(2.0), Some(9.0)));
+        assert_eq!((mean(&[]), median(&[]), mode(&[]), min(&[])), (None, None, None, None));
+    }
+
+    #[test]
+    fn test_spread() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(variance(&values, false), Some(4.0));
+        assert_eq!(stddev(&values, false), Some(2.0));
+        assert_close(variance(&values, true), 32.0 / 7.0);
+        assert_eq!(variance(&[1.0], true), None);
+        assert_eq!(variance(&[1.0], false), Some(0.0));
+        assert_eq!(variance(&[], false), None);
+    }
+
+    #[test]
+    fn test_welford_is_stable_far_from_zero() {
+        // The textbook sum-of-squares formula gives nonsense here
+        let values: Vec<f64> = [4.0, 7.0, 13.0, 16.0].iter().map(|x| 1e9 + x).collect();
+        assert_close(variance(&values, true), 30.0);
+        assert_close(mean(&values), 1e9 + 10.0);
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(&values, 0.25), Some(2.0));
+        assert_eq!(quantile(&values, 0.0), Some(1.0));
+        assert_eq!(quantile(&values, 1.0), Some(5.0));
+        assert_eq!(quantile(&[1.0, 2.0], 0.25), Some(1.25));
+        assert_eq!(quantile(&values, 1.5), None);
+    }
+
+    #[test]
+    fn test_two_lists() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+        let ys = [3.0, 5.0, 7.0, 9.0];
+        assert_close(covariance(&xs, &ys), 10.0 / 3.0);
+        assert_close(correlation(&xs, &ys), 1.0);
+        assert_close(correlation(&xs, &[4.0, 3.0, 2.0, 1.0]), -1.0);
+        assert_eq!(linear_regression(&xs, &ys), Some((2.0, 1.0)));
+        assert_eq!(linear_regression(&[1.0, 1.0], &[1.0, 2.0]), None);
+        assert_eq!(correlation(&xs, &[1.0; 4]), None);
+        assert_eq!(covariance(&[1.0], &[1.0]), None);
+    }
+}
@@ -0,0 +1,124 @@
+// src/svg.rs
+// A tiny SVG writer: just enough to build a picture out of lines, shapes and text
+//
+// SVG is XML, so the only care needed is escaping the characters XML gives a
+// meaning to (&, <, > and quotes) in text and attribute values
+
+use std::fmt::Write;
+
+/// An SVG picture being built up one element at a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Svg {
+    width: f64,
+    height: f64,
+    body: String,
+    /// Groups opened with `open` and not closed yet, to indent what is inside them
+    depth: usize,
+}
+
+impl Svg {
+    /// An empty picture `width` x `height` pixels
+    pub fn new(width: f64, height: f64) -> Self {
+        Svg { width, height, body: String::new(), depth: 1 }
+    }
+
+    /// Add an element with no content, like <line x1="0" ... />
+    pub fn element(&mut self, name: &str, attributes: &[(&str, String)]) {
+        let line = format!("<{}{} />", name, attributes_text(attributes));
+        self.push_line(&line);
+    }
+
+    /// Add some text, like <text x="10" y="20">sin(x)</text>
+    pub fn text(&mut self, text: &str, attributes: &[(&str, String)]) {
+        let line = format!("<text{}>{}</text>", attributes_text(attributes), escape(text));
+        self.push_line(&line);
+    }
+
+    /// Start an element that holds others, like <g> or <clipPath>; `close` ends it
+    pub fn open(&mut self, name: &str, attributes: &[(&str, String)]) {
+        let line = format!("<{}{}>", name, attributes_text(attributes));
+        self.push_line(&line);
+        self.depth += 1;
+    }
+
+    /// End the element `open` started last
+    pub fn close(&mut self, name: &str) {
+        self.depth = self.depth.saturating_sub(1);
+        self.push_line(&format!("</{}>", name));
+    }
+
+    fn push_line(&mut self, line: &str) {
+        let _ = writeln!(self.body, "{}{}", "  ".repeat(self.depth), line);
+    }
+
+    /// The whole SVG document, ready to save as a .svg file
+    pub fn finish(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}</svg>\n",
+            self.body,
+            w = number(self.width),
+            h = number(self.height)
+        )
+    }
+}
+
+/// ` name="value"` for each attribute
+fn attributes_text(attributes: &[(&str, String)]) -> String {
+    attributes.iter().map(|(name, value)| format!(" {}=\"{}\"", name, escape(value))).collect()
+}
+
+/// Make text safe to put inside XML
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A coordinate with at most two decimals, which is plenty on screen: 12.5, 3, 0.33
+pub fn number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_and_number() {
+        assert_eq!(escape("x < 1 & \"y\""), "x &lt; 1 &amp; &quot;y&quot;");
+        assert_eq!(number(12.5), "12.5");
+        assert_eq!(number(3.0), "3");
+        assert_eq!(number(1.0 / 3.0), "0.33");
+        assert_eq!(number(-0.001), "0");
+        assert_eq!(number(10.0), "10");
+    }
+
+    #[test]
+    fn test_document() {
+        let mut svg = Svg::new(100.0, 50.0);
+        svg.open("g", &[("stroke", "black".to_string())]);
+        svg.element("line", &[("x1", number(0.0)), ("y1", number(0.5))]);
+        svg.close("g");
+        svg.text("a<b", &[("x", number(10.0))]);
+        assert_eq!(
+            svg.finish(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"50\" viewBox=\"0 0 100 50\">\n\
+             \x20 <g stroke=\"black\">\n\
+             \x20   <line x1=\"0\" y1=\"0.5\" />\n\
+             \x20 </g>\n\
+             \x20 <text x=\"10\">a&lt;b</text>\n\
+             </svg>\n"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// src/value.rs
+// The values a calculation can produce: plain numbers, polynomials, vectors and matrices
+
+use std::fmt;
+
+use crate::calculator::format_number;
+use crate::matrix::{format_vector, Matrix, Shape};
+use crate::polynomial::Polynomial;
+
+/// A calculated value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    /// Made with poly(...) or arithmetic on polynomials
+    Polynomial(Polynomial),
+    /// A list of numbers like [1, 2, 3]
+    Vector(Vec<f64>),
+    /// Written as a vector of rows, like [[1, 2], [3, 4]]
+    Matrix(Matrix),
+}
+
+impl Value {
+    /// The name of this kind of value, as used in messages and JSON output
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Polynomial(_) => "polynomial",
+            Value::Vector(_) => "vector",
+            Value::Matrix(_) => "matrix",
+        }
+    }
+
+    /// The size of a vector or matrix; None for anything else
+    pub fn shape(&self) -> Option<Shape> {
+        match self {
+            Value::Vector(values) => Some(Shape::Vector(values.len())),
+            Value::Matrix(matrix) => Some(matrix.shape()),
+            _ => None,
+        }
+    }
+
+    /// The numbers in a vector or matrix (a matrix row by row); None for anything else
+    pub fn elements(&self) -> Option<&[f64]> {
+        match self {
+            Value::Vector(values) => Some(values),
+            Value::Matrix(matrix) => Some(matrix.data()),
+            _ => None,
+        }
+    }
+
+    /// The number inside, if this is a number
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Show, rounded to `precision` decimal places if one is given
+    pub fn format(&self, precision: Option<usize>) -> String {
+        match self {
+            Value::Number(value) => format_number(*value, precision),
+            Value::Polynomial(polynomial) => polynomial.format(precision),
+            Value::Vector(values) => format_vector(values, precision),
+            Value::Matrix(matrix) => matrix.format(precision),
+        }
+    }
+
+    /// How to write this value in the calculator so it comes back the same,
+    /// or None if it cannot be written (like infinity)
+    pub fn source(&self) -> Option<String> {
+        match self {
+            Value::Number(value) => value.is_finite().then(|| value.to_string()),
+            Value::Polynomial(polynomial) => {
+                polynomial.coefficients().iter().all(|c| c.is_finite()).then(|| polynomial.source())
+            }
+            Value::Vector(values) => values.iter().all(|value| value.is_finite()).then(|| format_vector(values, None)),
+            Value::Matrix(matrix) => matrix.data().iter().all(|value| value.is_finite()).then(|| matrix.format(None)),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(None))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<Polynomial> for Value {
+    fn from(polynomial: Polynomial) -> Self {
+        Value::Polynomial(polynomial)
+    }
+}
+
+impl From<Vec<f64>> for Value {
+    fn from(values: Vec<f64>) -> Self {
+        Value::Vector(values)
+    }
+}
+
+impl From<Matrix> for Value {
+    fn from(matrix: Matrix) -> Self {
+        Value::Matrix(matrix)
+    }
+}
+
+// So a value can be checked against a plain number, like `value == 2.0`
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        self.as_number() == Some(*other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_source() {
+        let p = Value::from(Polynomial::from_highest(&[0.5, -1.0]));
+        assert_eq!(p.to_string(), "0.5x - 1");
+        assert_eq!(p.source().as_deref(), Some("poly(0.5, -1)"));
+        assert_eq!(p.type_name(), "polynomial");
+        assert_eq!(Value::from(2.0 / 3.0).format(Some(2)), "0.67");
+        assert_eq!(Value::from(f64::INFINITY).source(), None);
+        assert!(Value::Number(2.0) == 2.0 && p != 2.0);
+    }
+
+    #[test]
+    fn test_vectors_and_matrices() {
+        let v = Value::from(vec![1.0, -2.5]);
+        assert_eq!(v.to_string(), "[1, -2.5]");
+        assert_eq!(v.source().as_deref(), Some("[1, -2.5]"));
+        assert_eq!(v.shape(), Some(Shape::Vector(2)));
+        let m = Value::from(Matrix::new(2, 2, vec![1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(m.format(Some(1)), "[[1.0, 2.0], [3.0, 4.0]]");
+        assert_eq!((m.type_name(), m.shape()), ("matrix", Some(Shape::Matrix(2, 2))));
+        assert_eq!(m.elements(), Some(&[1.0, 2.0, 3.0, 4.0][..]));
+        assert_eq!(Value::from(vec![f64::NAN]).source(), None);
+        assert_eq!(Value::from(1.0).shape(), None);
+    }
+}